@@ -1,177 +1,530 @@
 use anyhow::{Context, Result};
-use colored::Colorize;
-use ethers::types::{Block, Transaction, U64, U256};
-use std::collections::HashMap;
-
-use crate::rpc::{EthereumRpcClient, wei_to_eth, wei_to_gwei};
+use ethers::types::{Block, H256, Transaction, TransactionReceipt, U64, U256};
+use futures::StreamExt;
+use std::collections::{HashMap, HashSet};
+
+use crate::builders::BuilderRegistry;
+use crate::labels::LabelSet;
+use crate::mev::{BlockContext, DetectorRegistry, MevFinding};
+use crate::reorg::ReorgTracker;
+use crate::rpc::{EthereumRpcClient, RpcConfig, wei_to_eth, wei_to_gwei};
+use crate::sink::SinkWriter;
+use crate::source::BlockSource;
 use crate::types::*;
 
-pub struct BlockAnalyzer {
-    client: EthereumRpcClient,
+/// Default number of concurrent block batches used for range analysis
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 16;
+
+/// Progress reported while blocks are analyzed
+#[derive(Debug, Clone, Copy)]
+pub enum AnalysisEvent<'a> {
+    /// A block was fetched and analyzed
+    BlockAnalyzed(&'a BlockLifecycle),
+
+    /// The node returned no block at this height
+    BlockMissing(u64),
+
+    /// A block reached the `detect_mev` threshold
+    MevDetected(&'a BlockLifecycle),
+
+    /// Live monitoring follows an `eth_subscribe("newHeads")` stream
+    Subscribed,
+
+    /// The newHeads subscription ended; live monitoring polls from here on
+    SubscriptionClosed,
+
+    /// The newHeads subscription couldn't be set up; live monitoring polls
+    /// instead
+    SubscriptionFailed(&'a anyhow::Error),
+
+    /// Live monitoring found a reorg
+    Reorg(&'a ReorgEvent),
+}
+
+/// Callback receiving [`AnalysisEvent`]s
+pub type ProgressCallback = Box<dyn Fn(AnalysisEvent<'_>) + Send + Sync>;
+
+/// A block analyzed on its own, with the raw data behind it
+#[derive(Debug, Clone)]
+pub struct BlockDetails {
+    pub lifecycle: BlockLifecycle,
+    pub block: Block<Transaction>,
+    pub receipts: Vec<TransactionReceipt>,
+}
+
+/// Blocks and reorgs seen while monitoring live
+#[derive(Debug, Clone, Default)]
+pub struct LiveResults {
+    pub blocks: Vec<BlockLifecycle>,
+    pub reorgs: Vec<ReorgEvent>,
+}
+
+/// Outcome of [`BlockAnalyzer::detect_mev`]
+#[derive(Debug, Clone, Default)]
+pub struct MevSummary {
+    pub blocks_analyzed: u64,
+
+    /// Blocks whose estimated MEV reached the threshold (empty when they
+    /// were streamed to a sink)
+    pub flagged: Vec<BlockLifecycle>,
+
+    /// Number of blocks whose estimated MEV reached the threshold
+    pub blocks_flagged: u64,
+
+    /// Estimated MEV summed over the flagged blocks
+    pub total_mev_eth: f64,
+}
+
+impl MevSummary {
+    pub fn average_mev_eth(&self) -> f64 {
+        if self.blocks_analyzed == 0 {
+            0.0
+        } else {
+            self.total_mev_eth / self.blocks_analyzed as f64
+        }
+    }
+}
+
+/// Chain state carried between heads while monitoring live blocks
+struct LiveState {
+    last_block: u64,
+    tracker: ReorgTracker,
+    results: LiveResults,
+}
+
+/// Analyzes blocks read from a [`BlockSource`], a live node by default
+pub struct BlockAnalyzer<S = EthereumRpcClient> {
+    source: S,
+    max_in_flight: usize,
+    builders: BuilderRegistry,
+    labels: LabelSet,
+    detectors: DetectorRegistry,
+    progress: Option<ProgressCallback>,
+    sink: Option<SinkWriter>,
 }
 
 impl BlockAnalyzer {
     pub async fn new(rpc_url: &str) -> Result<Self> {
-        println!("Connecting to Ethereum node at {}...", rpc_url);
-        let client = EthereumRpcClient::new(rpc_url).await?;
-        println!("✓ Connected successfully!\n");
-        Ok(Self { client })
+        Self::with_config(&[rpc_url.to_string()], RpcConfig::default()).await
     }
 
-    /// Analyze a single block with detailed output
-    pub async fn analyze_single_block(&self, block_id: &str, verbose: bool) -> Result<()> {
-        let block = self
-            .client
-            .get_block(block_id)
-            .await?
-            .context("Block not found")?;
+    pub async fn with_config(rpc_urls: &[String], config: RpcConfig) -> Result<Self> {
+        let client = EthereumRpcClient::with_config(rpc_urls, config).await?;
+        Ok(Self::with_source(client))
+    }
+}
+
+impl<S: BlockSource> BlockAnalyzer<S> {
+    /// Analyze blocks from any source, e.g. a fixture in tests
+    pub fn with_source(source: S) -> Self {
+        Self {
+            source,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            builders: BuilderRegistry::default(),
+            labels: LabelSet::default(),
+            detectors: DetectorRegistry::default(),
+            progress: None,
+            sink: None,
+        }
+    }
+
+    /// The source blocks are read from
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Set the maximum number of block requests kept in flight at once
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Replace the built-in builder registry used to identify PBS builders
+    pub fn with_builder_registry(mut self, builders: BuilderRegistry) -> Self {
+        self.builders = builders;
+        self
+    }
+
+    /// Replace the built-in set of labelled addresses
+    pub fn with_labels(mut self, labels: LabelSet) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Replace the built-in MEV detectors
+    pub fn with_detectors(mut self, detectors: DetectorRegistry) -> Self {
+        self.detectors = detectors;
+        self
+    }
 
-        let lifecycle = self.analyze_block(&block).await?;
-        println!("{}", lifecycle);
+    /// Report progress through `callback` instead of staying silent
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(AnalysisEvent<'_>) + Send + Sync + 'static,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Stream analyzed blocks and reorgs to `sink` instead of collecting
+    /// them, so a long `monitor_live` run keeps nothing in memory. Results
+    /// returned by the analysis methods then hold no blocks, and analysis
+    /// stops with an error once a sink write fails.
+    pub fn with_sink(mut self, sink: SinkWriter) -> Self {
+        self.sink = Some(sink);
+        self
+    }
 
-        if verbose {
-            self.print_transaction_details(&block, &lifecycle).await?;
+    /// The sink blocks are streamed to, to finish once analysis is done
+    pub fn sink(&self) -> Option<&SinkWriter> {
+        self.sink.as_ref()
+    }
+
+    fn emit(&self, event: AnalysisEvent<'_>) {
+        if let Some(progress) = &self.progress {
+            progress(event);
         }
+    }
 
+    /// Hand an analyzed block to the sink, or keep it for the caller. Fails
+    /// once the sink has stopped on a write error.
+    fn collect(&self, blocks: &mut Vec<BlockLifecycle>, lifecycle: BlockLifecycle) -> Result<()> {
+        match &self.sink {
+            Some(sink) => sink.write_block(lifecycle)?,
+            None => blocks.push(lifecycle),
+        }
         Ok(())
     }
 
+    /// Analyze a single block (a number or `latest`)
+    pub async fn analyze_single_block(&self, block_id: &str) -> Result<BlockDetails> {
+        let block_number = if block_id == "latest" {
+            self.source.get_latest_block_number().await?
+        } else {
+            block_id.parse().context("Invalid block number")?
+        };
+
+        let block = self
+            .source
+            .get_block(block_number)
+            .await?
+            .context("Block not found")?;
+        let prev_block = self.previous_block(block_number).await?;
+        let receipts = self.source.get_block_receipts(&block).await?;
+
+        let mut lifecycle = self.analyze_block(&block, prev_block.as_ref(), &receipts);
+        if self.source.quorum_enabled()
+            && let Some(hash) = block.hash
+        {
+            lifecycle.provider_disagreements = self
+                .source
+                .cross_check(&[(block_number, hash)])
+                .await
+                .remove(&block_number)
+                .unwrap_or_default();
+        }
+
+        Ok(BlockDetails {
+            lifecycle,
+            block,
+            receipts,
+        })
+    }
+
     /// Analyze a range of blocks
     pub async fn analyze_range(&self, start: u64, end: u64) -> Result<Vec<BlockLifecycle>> {
-        println!(
-            "Analyzing blocks {} to {} ({} blocks)...\n",
-            start,
-            end,
-            end - start + 1
-        );
-
         let mut results = Vec::new();
 
-        for block_num in start..=end {
-            print!("  Block {}: ", block_num);
-
-            match self.client.get_block(&block_num.to_string()).await? {
-                Some(block) => {
-                    let lifecycle = self.analyze_block(&block).await?;
-                    println!(
-                        "✓ {} txs, {:.2} gwei base fee",
-                        lifecycle.transactions.total_count, lifecycle.gas.base_fee_gwei
-                    );
-                    results.push(lifecycle);
-                }
-                None => {
-                    println!("✗ Not found");
-                }
+        self.analyze_blocks(start, end, |block_num, lifecycle| match lifecycle {
+            Some(lifecycle) => {
+                self.emit(AnalysisEvent::BlockAnalyzed(&lifecycle));
+                self.collect(&mut results, lifecycle)
             }
-        }
+            None => {
+                self.emit(AnalysisEvent::BlockMissing(block_num));
+                Ok(())
+            }
+        })
+        .await?;
 
-        println!("\n✓ Analysis complete!");
         Ok(results)
     }
 
-    /// Monitor live blocks
-    pub async fn monitor_live(&self, count: u64, output: Option<String>) -> Result<()> {
-        println!("Monitoring live blocks...\n");
-
-        let mut results = Vec::new();
-        let mut last_block = self.client.get_latest_block_number().await?;
+    /// Monitor live blocks.
+    ///
+    /// Driven by an `eth_subscribe("newHeads")` stream when a WebSocket or IPC
+    /// endpoint is configured, otherwise (or if the subscription fails or
+    /// drops) the latest block number is polled every 3 seconds. `count` is the number of
+    /// heads (or polls) to wait for; 0 runs forever.
+    ///
+    /// Parent hashes are checked against a window of recent blocks; reorgs
+    /// found are reported as events and returned with the blocks.
+    pub async fn monitor_live(&self, count: u64) -> Result<LiveResults> {
+        let last_block = self.source.get_latest_block_number().await?;
+        let mut state = LiveState {
+            last_block,
+            tracker: ReorgTracker::default(),
+            results: LiveResults::default(),
+        };
+        if let Some(hash) = self.source.get_block_hash(last_block).await? {
+            state.tracker.insert(last_block, format!("{:?}", hash));
+        }
 
         let iterations = if count == 0 { u64::MAX } else { count };
+        let mut seen = 0;
 
-        for i in 0..iterations {
-            let current = self.client.get_latest_block_number().await?;
+        let heads = match self.source.subscribe_new_heads().await {
+            Ok(heads) => heads,
+            Err(err) => {
+                self.emit(AnalysisEvent::SubscriptionFailed(&err));
+                None
+            }
+        };
 
-            if current > last_block {
-                for block_num in (last_block + 1)..=current {
-                    if let Some(block) = self.client.get_block(&block_num.to_string()).await? {
-                        let lifecycle = self.analyze_block(&block).await?;
-                        println!("{}", lifecycle);
-                        results.push(lifecycle);
-                    }
-                }
-                last_block = current;
+        if let Some(mut heads) = heads {
+            self.emit(AnalysisEvent::Subscribed);
+
+            while seen < iterations {
+                let Some(head) = heads.next().await else {
+                    self.emit(AnalysisEvent::SubscriptionClosed);
+                    break;
+                };
+                let received_at = unix_now();
+                seen += 1;
+
+                let Some(current) = head.number.map(|n| n.as_u64()) else {
+                    continue;
+                };
+                self.follow_head(&mut state, current, head.hash, Some(received_at))
+                    .await?;
             }
+        }
 
-            if i < iterations - 1 {
+        while seen < iterations {
+            let current = self.source.get_latest_block_number().await?;
+            seen += 1;
+
+            // Polling only bounds the arrival time to the poll interval, so
+            // propagation delay is not recorded here
+            self.follow_head(&mut state, current, None, None).await?;
+
+            if seen < iterations {
                 tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
             }
         }
 
-        if let Some(path) = output {
-            crate::exporter::Exporter::export_to_csv(&results, &path)?;
-            println!("\n✓ Exported {} blocks to {}", results.len(), path);
+        Ok(state.results)
+    }
+
+    /// Analyze every block after `state.last_block` up to the new head `current`.
+    ///
+    /// A head at or below `state.last_block` is only followed if its hash
+    /// (`head_hash`, or fetched when polling) replaces the one tracked there.
+    /// `received_at` is the local arrival time of the `current` header; blocks
+    /// skipped in between were never received individually and get none.
+    async fn follow_head(
+        &self,
+        state: &mut LiveState,
+        current: u64,
+        head_hash: Option<H256>,
+        received_at: Option<f64>,
+    ) -> Result<()> {
+        if current <= state.last_block {
+            let hash = match head_hash {
+                Some(hash) => Some(hash),
+                None => self.source.get_block_hash(current).await?,
+            };
+            let replaced = match (hash, state.tracker.hash_at(current)) {
+                (Some(hash), Some(stored)) => !stored.eq_ignore_ascii_case(&format!("{:?}", hash)),
+                _ => false,
+            };
+            if !replaced || current == 0 {
+                return Ok(());
+            }
+            state.last_block = current - 1;
+        }
+
+        let mut fresh = Vec::new();
+        self.analyze_blocks(state.last_block + 1, current, |block_num, lifecycle| {
+            if let Some(mut lifecycle) = lifecycle {
+                if block_num == current
+                    && let Some(received_at) = received_at
+                {
+                    lifecycle.timing.record_arrival(received_at);
+                }
+                fresh.push(lifecycle);
+            }
+            Ok(())
+        })
+        .await?;
+        state.last_block = current;
+
+        for lifecycle in fresh {
+            if state
+                .tracker
+                .is_reorg(lifecycle.block_number, &lifecycle.parent_hash)
+                || replaces_tracked(&state.tracker, &lifecycle)
+            {
+                let reorg = self.resolve_reorg(&mut state.tracker, &lifecycle).await?;
+                self.emit(AnalysisEvent::Reorg(&reorg));
+                match &self.sink {
+                    Some(sink) => sink.write_reorg(reorg)?,
+                    None => state.results.reorgs.push(reorg),
+                }
+            }
+
+            state
+                .tracker
+                .insert(lifecycle.block_number, lifecycle.block_hash.clone());
+            self.emit(AnalysisEvent::BlockAnalyzed(&lifecycle));
+            self.collect(&mut state.results.blocks, lifecycle)?;
         }
 
         Ok(())
     }
 
-    /// Detect MEV in recent blocks
-    pub async fn detect_mev(&self, blocks: u64, threshold: f64) -> Result<()> {
-        let latest = self.client.get_latest_block_number().await?;
+    /// Walk back from a block that replaces a tracked one, or whose parent
+    /// doesn't match the tracked chain, until both chains agree, replacing
+    /// the orphaned hashes in `tracker`
+    async fn resolve_reorg(
+        &self,
+        tracker: &mut ReorgTracker,
+        lifecycle: &BlockLifecycle,
+    ) -> Result<ReorgEvent> {
+        let mut replaced = Vec::new();
+        if replaces_tracked(tracker, lifecycle)
+            && let Some(stored) = tracker.hash_at(lifecycle.block_number)
+        {
+            replaced.push((
+                lifecycle.block_number,
+                stored.to_string(),
+                lifecycle.block_hash.clone(),
+            ));
+        }
+        let mut height = lifecycle.block_number.saturating_sub(1);
+        let mut expected = lifecycle.parent_hash.clone();
+
+        let common_ancestor = loop {
+            let Some(stored) = tracker.hash_at(height).map(str::to_string) else {
+                break None;
+            };
+            if stored.eq_ignore_ascii_case(&expected) {
+                break Some(height);
+            }
+            replaced.push((height, stored, expected));
+
+            if height == 0 {
+                break None;
+            }
+            height -= 1;
+            expected = match self.source.get_block_hash(height).await? {
+                Some(hash) => format!("{:?}", hash),
+                None => break None,
+            };
+        };
+
+        replaced.reverse();
+        for (height, _, hash) in &replaced {
+            tracker.insert(*height, hash.clone());
+        }
+
+        let (orphaned, replacement): (Vec<_>, Vec<_>) = replaced
+            .into_iter()
+            .map(|(_, orphaned, replacement)| (orphaned, replacement))
+            .unzip();
+
+        Ok(ReorgEvent {
+            detected_at: lifecycle.block_number,
+            common_ancestor,
+            depth: orphaned.len(),
+            orphaned,
+            replacement,
+        })
+    }
+
+    /// Detect MEV in the last `blocks` blocks, flagging those whose estimated
+    /// MEV reaches `threshold` ETH
+    pub async fn detect_mev(&self, blocks: u64, threshold: f64) -> Result<MevSummary> {
+        let latest = self.source.get_latest_block_number().await?;
         let start = latest.saturating_sub(blocks);
 
-        println!(
-            "Analyzing {} blocks for MEV (threshold: {} ETH)...\n",
-            blocks, threshold
-        );
-
-        let mut total_mev = 0.0;
-        let mut blocks_with_mev = 0;
-
-        for block_num in start..=latest {
-            if let Some(block) = self.client.get_block(&block_num.to_string()).await? {
-                let lifecycle = self.analyze_block(&block).await?;
-
-                if lifecycle.mev.estimated_mev_eth >= threshold {
-                    blocks_with_mev += 1;
-                    total_mev += lifecycle.mev.estimated_mev_eth;
-
-                    println!(
-                        "{} Block {}: {:.4} ETH MEV detected",
-                        "s".yellow(),
-                        block_num,
-                        lifecycle.mev.estimated_mev_eth
-                    );
-
-                    if !lifecycle.mev.sandwich_attacks.is_empty() {
-                        println!(
-                            "   └─ {} sandwich attacks",
-                            lifecycle.mev.sandwich_attacks.len()
-                        );
-                    }
-                    if !lifecycle.mev.arbitrage_ops.is_empty() {
-                        println!(
-                            "   └─ {} arbitrage opportunities",
-                            lifecycle.mev.arbitrage_ops.len()
-                        );
-                    }
+        let mut summary = MevSummary::default();
+
+        self.analyze_blocks(start, latest, |_, lifecycle| {
+            let Some(lifecycle) = lifecycle else {
+                return Ok(());
+            };
+            summary.blocks_analyzed += 1;
+
+            if lifecycle.mev.estimated_mev_eth >= threshold {
+                self.emit(AnalysisEvent::MevDetected(&lifecycle));
+                summary.total_mev_eth += lifecycle.mev.estimated_mev_eth;
+                summary.blocks_flagged += 1;
+                self.collect(&mut summary.flagged, lifecycle)?;
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(summary)
+    }
+
+    /// Fetch blocks `start..=end` concurrently and analyze them in block order.
+    ///
+    /// Each fetched block is kept as the parent of the next one, so the previous
+    /// block is only requested once, for `start`. `on_block` receives `None` for
+    /// blocks the node doesn't return; an error from it stops the analysis.
+    async fn analyze_blocks<F>(&self, start: u64, end: u64, mut on_block: F) -> Result<()>
+    where
+        F: FnMut(u64, Option<BlockLifecycle>) -> Result<()>,
+    {
+        let mut prev_block = self.previous_block(start).await?;
+        let mut blocks = self
+            .source
+            .stream_blocks((start..=end).collect(), self.max_in_flight);
+
+        while let Some(result) = blocks.next().await {
+            let fetched = result?;
+            match fetched.block {
+                Some(block) => {
+                    let mut lifecycle =
+                        self.analyze_block(&block, prev_block.as_ref(), &fetched.receipts);
+                    lifecycle.provider_disagreements = fetched.disagreements;
+                    on_block(fetched.number, Some(lifecycle))?;
+                    prev_block = Some(block);
+                }
+                None => {
+                    on_block(fetched.number, None)?;
+                    prev_block = None;
                 }
             }
         }
 
-        println!("\n{}", "═══════════════════════════════════════".cyan());
-        println!("Blocks analyzed: {}", blocks);
-        println!("Blocks with MEV: {}", blocks_with_mev);
-        println!("Total MEV extracted: {:.4} ETH", total_mev);
-        println!(
-            "Average MEV per block: {:.4} ETH",
-            total_mev / blocks as f64
-        );
-        println!("{}", "═══════════════════════════════════════".cyan());
-
         Ok(())
     }
 
+    /// Parent of block `number`, used for block time
+    async fn previous_block(&self, number: u64) -> Result<Option<Block<Transaction>>> {
+        match number.checked_sub(1) {
+            Some(parent) => self.source.get_block(parent).await,
+            None => Ok(None),
+        }
+    }
+
     /// Core block analysis logic
-    async fn analyze_block(&self, block: &Block<Transaction>) -> Result<BlockLifecycle> {
+    fn analyze_block(
+        &self,
+        block: &Block<Transaction>,
+        prev_block: Option<&Block<Transaction>>,
+        receipts: &[TransactionReceipt],
+    ) -> BlockLifecycle {
         let block_number = block.number.unwrap_or_default().as_u64();
         let block_hash = format!("{:?}", block.hash.unwrap_or_default());
+        let parent_hash = format!("{:?}", block.parent_hash);
         let timestamp = block.timestamp.as_u64();
 
-        // Get previous block for timing
-        let prev_block = self.client.get_previous_block(block_number).await?;
-        let block_time = if let Some(prev) = &prev_block {
-            (timestamp - prev.timestamp.as_u64()) as f64
+        let block_time = if let Some(prev) = prev_block {
+            timestamp.saturating_sub(prev.timestamp.as_u64()) as f64
         } else {
             0.0
         };
@@ -180,71 +533,100 @@ impl BlockAnalyzer {
         let timing = TimingMetrics {
             block_time,
             timestamp,
-            propagation_delay: None, // Would need network data
+            propagation_delay: None, // Needs the local arrival time, see `record_arrival`
+            slot: slot_at(timestamp),
+            received_at: None,
+            timestamp_delay: None,
         };
 
         // Gas metrics
-        let gas = self.calculate_gas_metrics(block);
+        let gas = self.calculate_gas_metrics(block, receipts);
 
         // Transaction metrics
-        let transactions = self.analyze_transactions(block);
+        let transactions = self.analyze_transactions(block, receipts);
 
         // MEV indicators
-        let mev = self.detect_mev_indicators(block);
+        let mev = self.detect_mev_indicators(block, receipts);
 
         // PBS metrics
-        let pbs = self.analyze_pbs(block);
+        let pbs = self.analyze_pbs(block, receipts, &gas);
 
-        Ok(BlockLifecycle {
+        let mut lifecycle = BlockLifecycle {
             block_number,
             block_hash,
+            parent_hash,
             timestamp,
             proposer: format!("{:?}", block.author.unwrap_or_default()),
-            builder: pbs.builder_address.clone(),
+            builder: pbs
+                .builder_match
+                .as_ref()
+                .map(|m| m.name.clone())
+                .or_else(|| pbs.builder_address.clone()),
             timing,
             gas,
             transactions,
             mev,
             pbs,
-        })
+            provider_disagreements: Vec::new(),
+            labels: Vec::new(),
+        };
+
+        lifecycle.labels = self.labels.label_all(lifecycle.reported_addresses());
+        lifecycle
     }
 
-    fn calculate_gas_metrics(&self, block: &Block<Transaction>) -> GasMetrics {
+    fn calculate_gas_metrics(
+        &self,
+        block: &Block<Transaction>,
+        receipts: &[TransactionReceipt],
+    ) -> GasMetrics {
         let gas_used = block.gas_used.as_u64();
         let gas_limit = block.gas_limit.as_u64();
         let utilization = (gas_used as f64 / gas_limit as f64) * 100.0;
 
         let base_fee_gwei = block
             .base_fee_per_gas
-            .map(|bf| wei_to_gwei(bf))
+            .map(wei_to_gwei)
             .unwrap_or(0.0);
 
-        // Calculate average priority fee
+        // Tip actually paid per gas: effective gas price minus base fee, for
+        // every transaction type. Receipts give the gas each one really used.
+        let receipts: HashMap<H256, &TransactionReceipt> = receipts
+            .iter()
+            .map(|receipt| (receipt.transaction_hash, receipt))
+            .collect();
+        let base_fee = block.base_fee_per_gas.unwrap_or_default();
+
         let mut total_priority_fee = U256::zero();
-        let mut priority_fee_count = 0;
+        let mut tips_gwei = Vec::with_capacity(block.transactions.len());
 
         for tx in &block.transactions {
-            if let Some(max_priority) = tx.max_priority_fee_per_gas {
-                total_priority_fee += max_priority;
-                priority_fee_count += 1;
+            let receipt = receipts.get(&tx.hash).copied();
+            let Some(tip) = paid_tip(tx, receipt, base_fee) else {
+                continue;
+            };
+
+            tips_gwei.push(wei_to_gwei(tip));
+
+            if let Some(tx_gas_used) = receipt.and_then(|r| r.gas_used) {
+                total_priority_fee += tip * tx_gas_used;
             }
         }
 
-        let avg_priority_fee_gwei = if priority_fee_count > 0 {
-            wei_to_gwei(total_priority_fee / priority_fee_count)
-        } else {
+        tips_gwei.sort_by(f64::total_cmp);
+        let avg_priority_fee_gwei = if tips_gwei.is_empty() {
             0.0
-        };
-
-        // Calculate fees burned (base fee * gas used)
-        let fees_burned_eth = if let Some(base_fee) = block.base_fee_per_gas {
-            wei_to_eth(base_fee * gas_used)
         } else {
-            0.0
+            tips_gwei.iter().sum::<f64>() / tips_gwei.len() as f64
         };
+        let median_priority_fee_gwei = percentile(&tips_gwei, 50.0);
+        let priority_fee_percentiles = FeePercentiles::from_sorted(&tips_gwei);
 
-        // Calculate priority fees to proposer
-        let priority_fees_eth = wei_to_eth(total_priority_fee);
+        // Calculate fees burned (base fee * gas used)
+        let fees_burned = block
+            .base_fee_per_gas
+            .map(|base_fee| base_fee * gas_used)
+            .unwrap_or_default();
 
         GasMetrics {
             gas_used,
@@ -252,12 +634,20 @@ impl BlockAnalyzer {
             utilization,
             base_fee_gwei,
             avg_priority_fee_gwei,
-            fees_burned_eth,
-            priority_fees_eth,
+            median_priority_fee_gwei,
+            priority_fee_percentiles,
+            fees_burned_eth: wei_to_eth(fees_burned),
+            priority_fees_eth: wei_to_eth(total_priority_fee),
+            fees_burned_wei: Some(fees_burned.to_string()),
+            priority_fees_wei: Some(total_priority_fee.to_string()),
         }
     }
 
-    fn analyze_transactions(&self, block: &Block<Transaction>) -> TransactionMetrics {
+    fn analyze_transactions(
+        &self,
+        block: &Block<Transaction>,
+        receipts: &[TransactionReceipt],
+    ) -> TransactionMetrics {
         let total_count = block.transactions.len();
         let mut type_breakdown = TypeBreakdown {
             legacy: 0,
@@ -266,8 +656,6 @@ impl BlockAnalyzer {
             eip4844_blob: 0,
         };
 
-        let failed_count = 0;
-
         for tx in &block.transactions {
             match tx.transaction_type {
                 Some(t) if t == U64::from(0) => type_breakdown.legacy += 1,
@@ -281,11 +669,31 @@ impl BlockAnalyzer {
         // Analyze transaction ordering
         let ordering = self.analyze_tx_ordering(&block.transactions);
 
+        let gas_usage: Vec<TransactionGas> = receipts
+            .iter()
+            .map(|receipt| {
+                let tx = block.transactions.get(receipt.transaction_index.as_usize());
+                TransactionGas {
+                    tx_hash: format!("{:?}", receipt.transaction_hash),
+                    tx_index: receipt.transaction_index.as_u64(),
+                    gas_used: receipt.gas_used.unwrap_or_default().as_u64(),
+                    effective_gas_price_gwei: receipt
+                        .effective_gas_price
+                        .or_else(|| tx?.gas_price)
+                        .map(wei_to_gwei)
+                        .unwrap_or(0.0),
+                    success: receipt.status != Some(U64::zero()),
+                }
+            })
+            .collect();
+        let failed_count = gas_usage.iter().filter(|tx| !tx.success).count();
+
         TransactionMetrics {
             total_count,
             type_breakdown,
             ordering,
-            failed_count, // Would need receipts to determine
+            failed_count,
+            gas_usage,
         }
     }
 
@@ -298,11 +706,10 @@ impl BlockAnalyzer {
             if let (Some(prev_fee), Some(curr_fee)) = (
                 transactions[i - 1].max_priority_fee_per_gas,
                 transactions[i].max_priority_fee_per_gas,
-            ) {
-                if curr_fee > prev_fee {
-                    sorted_by_priority = false;
-                    anomalies += 1;
-                }
+            ) && curr_fee > prev_fee
+            {
+                sorted_by_priority = false;
+                anomalies += 1;
             }
         }
 
@@ -313,13 +720,28 @@ impl BlockAnalyzer {
         }
     }
 
-    fn detect_mev_indicators(&self, block: &Block<Transaction>) -> MevIndicators {
-        let sandwich_attacks = Vec::new();
-        let arbitrage_ops = Vec::new();
-        let liquidations = 0;
+    fn detect_mev_indicators(
+        &self,
+        block: &Block<Transaction>,
+        receipts: &[TransactionReceipt],
+    ) -> MevIndicators {
+        let mut sandwich_attacks = Vec::new();
+        let mut arbitrage_ops = Vec::new();
+        let mut liquidations = Vec::new();
+        let mut other_findings = Vec::new();
         let mut estimated_mev_eth = 0.0;
         let mut mev_bot_addresses = Vec::new();
 
+        let context = BlockContext::new(block, receipts);
+        for finding in self.detectors.detect(&context) {
+            match finding {
+                MevFinding::Sandwich(sandwich) => sandwich_attacks.push(sandwich),
+                MevFinding::Arbitrage(arbitrage) => arbitrage_ops.push(arbitrage),
+                MevFinding::Liquidation(liquidation) => liquidations.push(liquidation),
+                MevFinding::Other(finding) => other_findings.push(finding),
+            }
+        }
+
         // Simple heuristics for MEV detection
         let txs = &block.transactions;
 
@@ -329,90 +751,167 @@ impl BlockAnalyzer {
             let addr = format!("{:?}", tx.from);
             address_positions
                 .entry(addr)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(i);
         }
 
         for (addr, positions) in address_positions {
             if positions.len() >= 2 {
                 // Potential sandwich if address appears multiple times
-                if self.client.is_known_mev_bot(&addr) {
+                if self.labels.is_searcher(&addr) {
                     mev_bot_addresses.push(addr.clone());
                 }
             }
         }
 
-        // Estimate MEV based on priority fees of potential MEV transactions
+        // Estimate MEV based on the tips searchers actually paid
+        let receipts: HashMap<H256, &TransactionReceipt> = receipts
+            .iter()
+            .map(|receipt| (receipt.transaction_hash, receipt))
+            .collect();
+        let base_fee = block.base_fee_per_gas.unwrap_or_default();
         for tx in txs {
-            if let Some(priority_fee) = tx.max_priority_fee_per_gas {
-                let addr = format!("{:?}", tx.from);
-                if self.client.is_known_mev_bot(&addr) {
-                    estimated_mev_eth += wei_to_eth(priority_fee * tx.gas);
-                }
+            if !self.labels.is_searcher(&format!("{:?}", tx.from)) {
+                continue;
+            }
+            let receipt = receipts.get(&tx.hash).copied();
+            if let (Some(tip), Some(tx_gas_used)) = (
+                paid_tip(tx, receipt, base_fee),
+                receipt.and_then(|r| r.gas_used),
+            ) {
+                estimated_mev_eth += wei_to_eth(tip * tx_gas_used);
             }
         }
 
+        estimated_mev_eth += sandwich_attacks
+            .iter()
+            .map(|s| s.estimated_profit_eth.max(0.0))
+            .sum::<f64>();
+        estimated_mev_eth += arbitrage_ops
+            .iter()
+            .map(|a| a.estimated_profit_eth)
+            .sum::<f64>();
+        estimated_mev_eth += other_findings
+            .iter()
+            .map(|f| f.estimated_profit_eth.max(0.0))
+            .sum::<f64>();
+
         MevIndicators {
             sandwich_attacks,
             arbitrage_ops,
             liquidations,
             estimated_mev_eth,
             mev_bot_addresses,
+            other_findings,
         }
     }
 
-    fn analyze_pbs(&self, block: &Block<Transaction>) -> PbsMetrics {
+    fn analyze_pbs(
+        &self,
+        block: &Block<Transaction>,
+        receipts: &[TransactionReceipt],
+        gas: &GasMetrics,
+    ) -> PbsMetrics {
         let extra_data = String::from_utf8_lossy(&block.extra_data.0).to_string();
 
-        // Detect PBS builders from extra_data
-        let known_builders = vec!["flashbots", "builder0x69", "rsync", "beaverbuild"];
-        let is_pbs_block = known_builders
-            .iter()
-            .any(|b| extra_data.to_lowercase().contains(b));
+        // In PBS blocks the fee recipient is the builder
+        let fee_recipient = block.author.unwrap_or_default();
+        let fee_recipient_hex = format!("{:?}", fee_recipient);
+        let builder_match = self.builders.identify(&fee_recipient_hex, &extra_data);
 
-        let builder_address = if is_pbs_block {
-            Some(extra_data.clone())
-        } else {
-            None
+        let failed: HashSet<H256> = receipts
+            .iter()
+            .filter(|r| r.status == Some(U64::zero()))
+            .map(|r| r.transaction_hash)
+            .collect();
+        let succeeded = |tx: &&Transaction| !failed.contains(&tx.hash);
+
+        // The builder pays the proposer with a plain transfer, normally the
+        // block's last transaction
+        let payment = block
+            .transactions
+            .iter()
+            .rev()
+            .filter(succeeded)
+            .find(|tx| tx.from == fee_recipient)
+            .filter(|tx| !tx.value.is_zero() && tx.to.is_some_and(|to| to != fee_recipient));
+
+        let mut pbs = PbsMetrics {
+            is_pbs_block: builder_match.is_some(),
+            builder_address: builder_match.as_ref().map(|_| fee_recipient_hex.clone()),
+            builder_match,
+            builder_payment_eth: None,
+            extra_data,
+            payment_tx: None,
+            proposer_fee_recipient: None,
+            builder_margin_eth: None,
+            builder_payment_wei: None,
+            builder_margin_wei: None,
         };
 
-        PbsMetrics {
-            is_pbs_block,
-            builder_address,
-            builder_payment_eth: None, // Would need to parse coinbase tx
-            extra_data,
+        if let Some(payment) = payment {
+            // Transfers made by contracts (`block.coinbase.transfer`) only show
+            // up in traces, so only top-level transfers are counted here
+            let direct_transfers: U256 = block
+                .transactions
+                .iter()
+                .filter(succeeded)
+                .filter(|tx| tx.to == Some(fee_recipient) && tx.from != fee_recipient)
+                .map(|tx| tx.value)
+                .fold(U256::zero(), |total, value| total + value);
+
+            let payment_eth = wei_to_eth(payment.value);
+
+            pbs.is_pbs_block = true;
+            pbs.builder_address = Some(fee_recipient_hex);
+            pbs.builder_payment_eth = Some(payment_eth);
+            pbs.payment_tx = Some(format!("{:?}", payment.hash));
+            pbs.proposer_fee_recipient = payment.to.map(|to| format!("{:?}", to));
+            pbs.builder_margin_eth =
+                Some(gas.priority_fees_eth + wei_to_eth(direct_transfers) - payment_eth);
+
+            let priority_fees = gas
+                .priority_fees_wei
+                .as_deref()
+                .and_then(|wei| U256::from_dec_str(wei).ok())
+                .unwrap_or_default();
+            let earned = priority_fees + direct_transfers;
+            pbs.builder_payment_wei = Some(payment.value.to_string());
+            pbs.builder_margin_wei = Some(if earned >= payment.value {
+                (earned - payment.value).to_string()
+            } else {
+                format!("-{}", payment.value - earned)
+            });
         }
+
+        pbs
     }
+}
 
-    #[allow(dead_code)]
-    async fn print_transaction_details(
-        &self,
-        block: &Block<Transaction>,
-        lifecycle: &BlockLifecycle,
-    ) -> Result<()> {
-        println!("\n{}", "TRANSACTION DETAILS".green().bold());
-        println!("{}", "─".repeat(50));
-
-        for (i, tx) in block.transactions.iter().take(10).enumerate() {
-            println!("\nTx #{}: {}", i + 1, format!("{:?}", tx.hash).yellow());
-            println!("  From: {:?}", tx.from);
-            println!("  To: {:?}", tx.to);
-            println!("  Value: {} ETH", wei_to_eth(tx.value));
-            if let Some(max_fee) = tx.max_fee_per_gas {
-                println!("  Max Fee: {} gwei", wei_to_gwei(max_fee));
-            }
-            if let Some(priority) = tx.max_priority_fee_per_gas {
-                println!("  Priority Fee: {} gwei", wei_to_gwei(priority));
-            }
-        }
+/// Whether `lifecycle` is a different block at a height already tracked
+fn replaces_tracked(tracker: &ReorgTracker, lifecycle: &BlockLifecycle) -> bool {
+    tracker
+        .hash_at(lifecycle.block_number)
+        .is_some_and(|stored| !stored.eq_ignore_ascii_case(&lifecycle.block_hash))
+}
 
-        if block.transactions.len() > 10 {
-            println!(
-                "\n... and {} more transactions",
-                block.transactions.len() - 10
-            );
-        }
+/// Tip per gas a transaction actually paid: its effective gas price (or, without
+/// a receipt, its gas price) above the base fee
+fn paid_tip(
+    tx: &Transaction,
+    receipt: Option<&TransactionReceipt>,
+    base_fee: U256,
+) -> Option<U256> {
+    let price = receipt
+        .and_then(|r| r.effective_gas_price)
+        .or(tx.gas_price)?;
+    Some(price.saturating_sub(base_fee))
+}
 
-        Ok(())
-    }
+/// Current wall-clock time as fractional unix seconds
+fn unix_now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
 }
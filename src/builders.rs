@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::types::{BuilderMatch, MatchConfidence, MatchSource};
+
+/// Registry shipped with the binary, used unless another file is loaded
+const DEFAULT_REGISTRY: &str = include_str!("../config/builders.toml");
+
+/// On-disk registry format (TOML or JSON)
+#[derive(Debug, Deserialize)]
+struct RegistryFile {
+    #[serde(default, rename = "builder")]
+    builders: Vec<BuilderEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BuilderEntry {
+    /// Canonical builder name
+    pub name: String,
+
+    /// Fee-recipient addresses the builder signs blocks with
+    #[serde(default)]
+    pub fee_recipients: Vec<String>,
+
+    /// Case-insensitive substrings of the block's extra_data
+    #[serde(default)]
+    pub extra_data: Vec<String>,
+}
+
+/// Maps fee recipients and extra_data patterns to canonical builder names
+#[derive(Debug, Clone)]
+pub struct BuilderRegistry {
+    builders: Vec<BuilderEntry>,
+    by_fee_recipient: HashMap<String, usize>,
+}
+
+impl Default for BuilderRegistry {
+    fn default() -> Self {
+        Self::from_toml_str(DEFAULT_REGISTRY).expect("built-in builder registry is valid")
+    }
+}
+
+impl BuilderRegistry {
+    pub fn new(mut builders: Vec<BuilderEntry>) -> Self {
+        for builder in &mut builders {
+            for pattern in &mut builder.extra_data {
+                *pattern = pattern.to_lowercase();
+            }
+        }
+
+        let by_fee_recipient = builders
+            .iter()
+            .enumerate()
+            .flat_map(|(i, builder)| {
+                builder
+                    .fee_recipients
+                    .iter()
+                    .map(move |address| (address.to_lowercase(), i))
+            })
+            .collect();
+
+        Self {
+            builders,
+            by_fee_recipient,
+        }
+    }
+
+    /// Load a registry file; `.json` files are read as JSON, anything else as TOML
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read builder registry {}", path))?;
+
+        let is_json = Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+        if is_json {
+            Self::from_json_str(&text)
+        } else {
+            Self::from_toml_str(&text)
+        }
+        .with_context(|| format!("Invalid builder registry {}", path))
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let file: RegistryFile = toml::from_str(text)?;
+        Ok(Self::new(file.builders))
+    }
+
+    pub fn from_json_str(text: &str) -> Result<Self> {
+        let file: RegistryFile = serde_json::from_str(text)?;
+        Ok(Self::new(file.builders))
+    }
+
+    pub fn len(&self) -> usize {
+        self.builders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.builders.is_empty()
+    }
+
+    /// Identify the builder of a block.
+    ///
+    /// The fee recipient is authoritative; extra_data is set by the builder
+    /// itself and can be copied, so a match on it alone is less certain.
+    pub fn identify(&self, fee_recipient: &str, extra_data: &str) -> Option<BuilderMatch> {
+        let by_address = self.by_fee_recipient.get(&fee_recipient.to_lowercase()).copied();
+
+        let extra_data = extra_data.to_lowercase();
+        let by_extra_data = self.builders.iter().position(|builder| {
+            builder
+                .extra_data
+                .iter()
+                .any(|pattern| !pattern.is_empty() && extra_data.contains(pattern.as_str()))
+        });
+
+        let (index, source, confidence) = match (by_address, by_extra_data) {
+            (Some(a), Some(e)) if a == e => (a, MatchSource::Both, MatchConfidence::High),
+            (Some(a), Some(_)) => (a, MatchSource::FeeRecipient, MatchConfidence::Low),
+            (Some(a), None) => (a, MatchSource::FeeRecipient, MatchConfidence::High),
+            (None, Some(e)) => (e, MatchSource::ExtraData, MatchConfidence::Medium),
+            (None, None) => return None,
+        };
+
+        Some(BuilderMatch {
+            name: self.builders[index].name.clone(),
+            source,
+            confidence,
+        })
+    }
+}
@@ -0,0 +1,512 @@
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use ethers::types::{Block, H256, Trace, Transaction, TransactionReceipt};
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::source::{BlockSource, BlockStream, FetchedBlock, HeadStream};
+use crate::types::ProviderDisagreement;
+
+/// Default cache size limit (2 GiB)
+pub const DEFAULT_MAX_CACHE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// How long a finalized block number is trusted before asking the source again
+const FINALIZED_REFRESH: Duration = Duration::from_secs(60);
+
+/// One cached block, stored in `<dir>/<number>-<hash>/`
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    hash: H256,
+    bytes: u64,
+    last_used: SystemTime,
+}
+
+/// Summary of what a cache holds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub blocks: usize,
+    pub bytes: u64,
+    pub first_block: Option<u64>,
+    pub last_block: Option<u64>,
+}
+
+/// On-disk store of finalized blocks with their receipts and traces.
+///
+/// Each block lives in a directory named after its number and hash, holding
+/// `block.json`, `receipts.json` and `traces.json`. When the total size goes
+/// over the limit, the least recently used blocks are evicted.
+#[derive(Debug)]
+pub struct BlockCache {
+    dir: PathBuf,
+    max_bytes: Option<u64>,
+    entries: Mutex<BTreeMap<u64, CacheEntry>>,
+}
+
+impl BlockCache {
+    /// Open (or create) the cache in `dir`, without a size limit
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create cache directory {}", dir.display()))?;
+
+        let mut entries = BTreeMap::new();
+        let listing = std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read cache directory {}", dir.display()))?;
+        for item in listing {
+            let path = item?.path();
+            let Some((number, hash)) = parse_entry_name(&path) else {
+                continue;
+            };
+            // Entries are only visible once their block is written
+            let Ok(meta) = std::fs::metadata(path.join("block.json")) else {
+                continue;
+            };
+
+            entries.insert(
+                number,
+                CacheEntry {
+                    hash,
+                    bytes: dir_size(&path)?,
+                    last_used: meta.modified()?,
+                },
+            );
+        }
+
+        Ok(Self {
+            dir,
+            max_bytes: None,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Evict least recently used blocks once the cache grows past `max_bytes`
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether block `number` is cached
+    pub fn contains(&self, number: u64) -> bool {
+        self.entries.lock().unwrap().contains_key(&number)
+    }
+
+    /// Hash of the cached block at `number`
+    pub fn hash(&self, number: u64) -> Option<H256> {
+        self.entries.lock().unwrap().get(&number).map(|e| e.hash)
+    }
+
+    pub fn block(&self, number: u64) -> Result<Option<Block<Transaction>>> {
+        let block: Option<Block<Transaction>> = self.read(number, "block.json")?;
+        if block.is_some() {
+            self.touch(number);
+        }
+        Ok(block)
+    }
+
+    /// Receipts of the cached block with this number and hash
+    pub fn receipts(&self, number: u64, hash: H256) -> Result<Option<Vec<TransactionReceipt>>> {
+        if self.hash(number) != Some(hash) {
+            return Ok(None);
+        }
+        self.read(number, "receipts.json")
+    }
+
+    pub fn traces(&self, number: u64) -> Result<Option<Vec<Trace>>> {
+        self.read(number, "traces.json")
+    }
+
+    /// Store a block; blocks without a number or hash are ignored
+    pub fn put_block(&self, block: &Block<Transaction>) -> Result<()> {
+        let (Some(number), Some(hash)) = (block.number, block.hash) else {
+            return Ok(());
+        };
+        let number = number.as_u64();
+
+        self.write(number, hash, "block.json", block)?;
+        self.evict()
+    }
+
+    /// Store the receipts of a block that is already cached
+    pub fn put_receipts(
+        &self,
+        block: &Block<Transaction>,
+        receipts: &[TransactionReceipt],
+    ) -> Result<()> {
+        let (Some(number), Some(hash)) = (block.number, block.hash) else {
+            return Ok(());
+        };
+        let number = number.as_u64();
+        if self.hash(number) != Some(hash) {
+            return Ok(());
+        }
+
+        self.write(number, hash, "receipts.json", &receipts)?;
+        self.evict()
+    }
+
+    /// Store the traces of a block that is already cached
+    pub fn put_traces(&self, number: u64, traces: &[Trace]) -> Result<()> {
+        let Some(hash) = self.hash(number) else {
+            return Ok(());
+        };
+
+        self.write(number, hash, "traces.json", &traces)?;
+        self.evict()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        CacheStats {
+            blocks: entries.len(),
+            bytes: entries.values().map(|e| e.bytes).sum(),
+            first_block: entries.keys().next().copied(),
+            last_block: entries.keys().next_back().copied(),
+        }
+    }
+
+    /// Evict least recently used blocks until at most `max_bytes` remain.
+    /// Returns the number of blocks removed.
+    pub fn prune_to_size(&self, max_bytes: u64) -> Result<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        let mut total: u64 = entries.values().map(|e| e.bytes).sum();
+
+        let mut by_age: Vec<_> = entries
+            .iter()
+            .map(|(&number, entry)| (entry.last_used, number))
+            .collect();
+        by_age.sort();
+
+        let mut removed = 0;
+        for (_, number) in by_age {
+            if total <= max_bytes {
+                break;
+            }
+            if let Some(entry) = entries.remove(&number) {
+                self.remove_entry(number, &entry)?;
+                total -= entry.bytes;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Remove every block below `number`; returns the number removed
+    pub fn prune_below(&self, number: u64) -> Result<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        let kept = entries.split_off(&number);
+        let removed = std::mem::replace(&mut *entries, kept);
+
+        for (number, entry) in &removed {
+            self.remove_entry(*number, entry)?;
+        }
+        Ok(removed.len())
+    }
+
+    /// Remove every cached block
+    pub fn clear(&self) -> Result<usize> {
+        self.prune_below(u64::MAX)
+    }
+
+    fn entry_dir(&self, number: u64, hash: H256) -> PathBuf {
+        self.dir.join(format!("{}-{:?}", number, hash))
+    }
+
+    fn remove_entry(&self, number: u64, entry: &CacheEntry) -> Result<()> {
+        let path = self.entry_dir(number, entry.hash);
+        match std::fs::remove_dir_all(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("Failed to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn read<T: DeserializeOwned>(&self, number: u64, file: &str) -> Result<Option<T>> {
+        let Some(hash) = self.hash(number) else {
+            return Ok(None);
+        };
+
+        let path = self.entry_dir(number, hash).join(file);
+        let text = match std::fs::read(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+
+        let value = serde_json::from_slice(&text)
+            .with_context(|| format!("Invalid cache file {}", path.display()))?;
+        Ok(Some(value))
+    }
+
+    fn write<T: Serialize>(&self, number: u64, hash: H256, file: &str, value: &T) -> Result<()> {
+        let dir = self.entry_dir(number, hash);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        // Write then rename so an interrupted run never leaves a torn file
+        let path = dir.join(file);
+        let partial = path.with_extension("tmp");
+        let json = serde_json::to_vec(value)?;
+        std::fs::write(&partial, &json)
+            .with_context(|| format!("Failed to write {}", partial.display()))?;
+        std::fs::rename(&partial, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.entry(number).or_insert(CacheEntry {
+            hash,
+            bytes: 0,
+            last_used: SystemTime::now(),
+        });
+        entry.bytes = dir_size(&dir)?;
+        entry.last_used = SystemTime::now();
+        Ok(())
+    }
+
+    /// Mark a block as recently used, on disk too so the order survives restarts
+    fn touch(&self, number: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(&number) {
+            entry.last_used = SystemTime::now();
+            let path = self.entry_dir(number, entry.hash).join("block.json");
+            if let Ok(file) = File::options().write(true).open(path) {
+                let _ = file.set_modified(entry.last_used);
+            }
+        }
+    }
+
+    fn evict(&self) -> Result<()> {
+        match self.max_bytes {
+            Some(max_bytes) => self.prune_to_size(max_bytes).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `(number, hash)` from an entry directory named `<number>-<hash>`
+fn parse_entry_name(path: &Path) -> Option<(u64, H256)> {
+    let name = path.file_name()?.to_str()?;
+    let (number, hash) = name.split_once('-')?;
+    Some((number.parse().ok()?, hash.parse().ok()?))
+}
+
+fn dir_size(dir: &Path) -> Result<u64> {
+    let mut bytes = 0;
+    for item in std::fs::read_dir(dir)? {
+        bytes += item?.metadata()?.len();
+    }
+    Ok(bytes)
+}
+
+/// Source that answers from a [`BlockCache`] and fills it with finalized
+/// blocks read from `inner`, so repeated analyses of old blocks make no
+/// RPC calls
+pub struct CachedSource<S> {
+    inner: S,
+    cache: BlockCache,
+    finalized: Mutex<Option<(u64, Instant)>>,
+}
+
+impl<S: BlockSource> CachedSource<S> {
+    pub fn new(inner: S, cache: BlockCache) -> Self {
+        Self {
+            inner,
+            cache,
+            finalized: Mutex::new(None),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn cache(&self) -> &BlockCache {
+        &self.cache
+    }
+
+    /// Whether `number` is final, asking the inner source at most once per
+    /// [`FINALIZED_REFRESH`] for blocks past the last known finalized block
+    async fn is_finalized(&self, number: u64) -> Result<bool> {
+        let known = *self.finalized.lock().unwrap();
+        if let Some((finalized, checked_at)) = known
+            && (number <= finalized || checked_at.elapsed() < FINALIZED_REFRESH)
+        {
+            return Ok(number <= finalized);
+        }
+
+        let finalized = self.inner.get_finalized_block_number().await?;
+        *self.finalized.lock().unwrap() = Some((finalized, Instant::now()));
+        Ok(number <= finalized)
+    }
+
+    async fn store_block(&self, block: &Block<Transaction>) -> Result<()> {
+        match block.number {
+            Some(number) if self.is_finalized(number.as_u64()).await? => {
+                self.cache.put_block(block)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Cached block with its receipts, fetching the receipts if only the
+    /// block was cached. `None` if the block was evicted meanwhile.
+    async fn cached_block(&self, number: u64) -> Result<Option<FetchedBlock>> {
+        let Some(block) = self.cache.block(number)? else {
+            return Ok(None);
+        };
+        let receipts = self.get_block_receipts(&block).await?;
+
+        Ok(Some(FetchedBlock {
+            number,
+            block: Some(block),
+            receipts,
+            disagreements: Vec::new(),
+        }))
+    }
+
+    /// Block and receipts through the cache, one call at a time
+    async fn fetch_block(&self, number: u64) -> Result<FetchedBlock> {
+        let block = self.get_block(number).await?;
+        let receipts = match &block {
+            Some(block) => self.get_block_receipts(block).await?,
+            None => Vec::new(),
+        };
+
+        Ok(FetchedBlock {
+            number,
+            block,
+            receipts,
+            disagreements: Vec::new(),
+        })
+    }
+
+    async fn store_fetched(&self, fetched: &FetchedBlock) -> Result<()> {
+        // Blocks other providers disagree about are never trusted as final
+        if let Some(block) = &fetched.block
+            && fetched.disagreements.is_empty()
+        {
+            self.store_block(block).await?;
+            self.cache.put_receipts(block, &fetched.receipts)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S: BlockSource> BlockSource for CachedSource<S> {
+    async fn get_block(&self, number: u64) -> Result<Option<Block<Transaction>>> {
+        if let Some(block) = self.cache.block(number)? {
+            return Ok(Some(block));
+        }
+
+        let block = self.inner.get_block(number).await?;
+        if let Some(block) = &block {
+            self.store_block(block).await?;
+        }
+        Ok(block)
+    }
+
+    async fn get_block_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<TransactionReceipt>> {
+        if let (Some(number), Some(hash)) = (block.number, block.hash)
+            && let Some(receipts) = self.cache.receipts(number.as_u64(), hash)?
+        {
+            return Ok(receipts);
+        }
+
+        let receipts = self.inner.get_block_receipts(block).await?;
+        self.cache.put_receipts(block, &receipts)?;
+        Ok(receipts)
+    }
+
+    async fn get_block_traces(&self, number: u64) -> Result<Vec<Trace>> {
+        if let Some(traces) = self.cache.traces(number)? {
+            return Ok(traces);
+        }
+
+        let traces = self.inner.get_block_traces(number).await?;
+        self.cache.put_traces(number, &traces)?;
+        Ok(traces)
+    }
+
+    async fn get_latest_block_number(&self) -> Result<u64> {
+        self.inner.get_latest_block_number().await
+    }
+
+    async fn get_finalized_block_number(&self) -> Result<u64> {
+        self.inner.get_finalized_block_number().await
+    }
+
+    async fn get_block_hash(&self, number: u64) -> Result<Option<H256>> {
+        match self.cache.hash(number) {
+            Some(hash) => Ok(Some(hash)),
+            None => self.inner.get_block_hash(number).await,
+        }
+    }
+
+    async fn subscribe_new_heads(&self) -> Result<Option<HeadStream<'_>>> {
+        self.inner.subscribe_new_heads().await
+    }
+
+    fn quorum_enabled(&self) -> bool {
+        self.inner.quorum_enabled()
+    }
+
+    async fn cross_check(&self, blocks: &[(u64, H256)]) -> HashMap<u64, Vec<ProviderDisagreement>> {
+        self.inner.cross_check(blocks).await
+    }
+
+    /// Cached blocks are read from disk; the rest are streamed from the
+    /// inner source, which keeps its own batching and concurrency
+    fn stream_blocks(&self, numbers: Vec<u64>, max_in_flight: usize) -> BlockStream<'_> {
+        let cached: HashSet<u64> = numbers
+            .iter()
+            .copied()
+            .filter(|&number| self.cache.contains(number))
+            .collect();
+        let missing: Vec<u64> = numbers
+            .iter()
+            .copied()
+            .filter(|number| !cached.contains(number))
+            .collect();
+        let fetched = self.inner.stream_blocks(missing, max_in_flight);
+
+        Box::pin(stream::unfold(
+            (numbers.into_iter(), fetched, cached),
+            move |(mut numbers, mut fetched, cached)| async move {
+                let number = numbers.next()?;
+                let result = if cached.contains(&number) {
+                    match self.cached_block(number).await {
+                        Ok(Some(block)) => Ok(block),
+                        // Evicted since the stream started
+                        Ok(None) => self.fetch_block(number).await,
+                        Err(err) => Err(err),
+                    }
+                } else {
+                    match fetched.next().await {
+                        Some(Ok(block)) => self.store_fetched(&block).await.map(|_| block),
+                        Some(Err(err)) => Err(err),
+                        None => Err(anyhow!("Block {} missing from stream", number)),
+                    }
+                };
+
+                Some((result, (numbers, fetched, cached)))
+            },
+        ))
+    }
+}
@@ -0,0 +1,194 @@
+use ethers::types::{Address, H256, I256, Log, Transaction, TransactionReceipt, U64, U256};
+use ethers::utils::keccak256;
+
+/// Wrapped Ether (mainnet), the unit MEV profits are converted to
+pub fn weth() -> Address {
+    "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"
+        .parse()
+        .expect("valid WETH address")
+}
+
+/// AMM families whose `Swap` events are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dex {
+    UniswapV2,
+    UniswapV3,
+}
+
+impl Dex {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dex::UniswapV2 => "Uniswap V2",
+            Dex::UniswapV3 => "Uniswap V3",
+        }
+    }
+}
+
+/// One pool swap decoded from a receipt log
+#[derive(Debug, Clone)]
+pub struct Swap {
+    /// Position of the transaction in the block
+    pub tx_index: usize,
+    pub tx_hash: H256,
+
+    /// Transaction sender and target contract
+    pub tx_from: Address,
+    pub tx_to: Option<Address>,
+
+    /// Pool that emitted the event (V2 pair, V3 pool or a fork of either)
+    pub pool: Address,
+    pub dex: Dex,
+
+    /// Whether token0 went into the pool
+    pub zero_for_one: bool,
+    pub amount_in: U256,
+    pub amount_out: U256,
+
+    /// Tokens matched from the transaction's `Transfer` logs, when found
+    pub token_in: Option<Address>,
+    pub token_out: Option<Address>,
+
+    /// Receiver of the output tokens
+    pub recipient: Address,
+}
+
+/// Event topic for a Solidity event signature
+pub fn topic(signature: &str) -> H256 {
+    H256::from(keccak256(signature))
+}
+
+/// Decode the swaps of every successful transaction, in block order
+pub fn decode_swaps(transactions: &[Transaction], receipts: &[TransactionReceipt]) -> Vec<Swap> {
+    let v2_swap = topic("Swap(address,uint256,uint256,uint256,uint256,address)");
+    let v3_swap = topic("Swap(address,address,int256,int256,uint160,uint128,int24)");
+    let transfer = topic("Transfer(address,address,uint256)");
+
+    let mut swaps = Vec::new();
+
+    for receipt in receipts {
+        if receipt.status == Some(U64::zero()) {
+            continue;
+        }
+        let tx_index = receipt.transaction_index.as_usize();
+        let tx = transactions
+            .get(tx_index)
+            .filter(|tx| tx.hash == receipt.transaction_hash);
+
+        let transfers: Vec<(Address, Address, Address, U256)> = receipt
+            .logs
+            .iter()
+            .filter(|log| {
+                log.topics.len() == 3 && log.topics[0] == transfer && log.data.len() == 32
+            })
+            .map(|log| {
+                (
+                    log.address,
+                    Address::from(log.topics[1]),
+                    Address::from(log.topics[2]),
+                    U256::from_big_endian(&log.data),
+                )
+            })
+            .collect();
+
+        for log in &receipt.logs {
+            let Some(first) = log.topics.first() else {
+                continue;
+            };
+
+            let decoded = if *first == v2_swap {
+                decode_v2(log)
+            } else if *first == v3_swap {
+                decode_v3(log)
+            } else {
+                None
+            };
+
+            let Some((dex, zero_for_one, amount_in, amount_out, recipient)) = decoded else {
+                continue;
+            };
+
+            // The pool receives the input token and sends out the output token
+            let token_in = transfers
+                .iter()
+                .find(|(_, _, to, value)| *to == log.address && *value == amount_in)
+                .map(|(token, ..)| *token);
+            let token_out = transfers
+                .iter()
+                .find(|(_, from, _, value)| *from == log.address && *value == amount_out)
+                .map(|(token, ..)| *token);
+
+            swaps.push(Swap {
+                tx_index,
+                tx_hash: receipt.transaction_hash,
+                tx_from: tx.map(|tx| tx.from).unwrap_or(receipt.from),
+                tx_to: tx.and_then(|tx| tx.to).or(receipt.to),
+                pool: log.address,
+                dex,
+                zero_for_one,
+                amount_in,
+                amount_out,
+                token_in,
+                token_out,
+                recipient,
+            });
+        }
+    }
+
+    swaps
+}
+
+/// `Swap(sender, amount0In, amount1In, amount0Out, amount1Out, to)`
+fn decode_v2(log: &Log) -> Option<(Dex, bool, U256, U256, Address)> {
+    if log.topics.len() != 3 || log.data.len() != 4 * 32 {
+        return None;
+    }
+
+    let word = |i: usize| U256::from_big_endian(&log.data[i * 32..(i + 1) * 32]);
+    let (amount0_in, amount1_in, amount0_out, amount1_out) = (word(0), word(1), word(2), word(3));
+    let recipient = Address::from(log.topics[2]);
+
+    if !amount0_in.is_zero() && !amount1_out.is_zero() {
+        Some((Dex::UniswapV2, true, amount0_in, amount1_out, recipient))
+    } else if !amount1_in.is_zero() && !amount0_out.is_zero() {
+        Some((Dex::UniswapV2, false, amount1_in, amount0_out, recipient))
+    } else {
+        None
+    }
+}
+
+/// `Swap(sender, recipient, amount0, amount1, sqrtPriceX96, liquidity, tick)`,
+/// where positive amounts flow into the pool
+fn decode_v3(log: &Log) -> Option<(Dex, bool, U256, U256, Address)> {
+    if log.topics.len() != 3 || log.data.len() != 5 * 32 {
+        return None;
+    }
+
+    let word = |i: usize| I256::from_raw(U256::from_big_endian(&log.data[i * 32..(i + 1) * 32]));
+    let (amount0, amount1) = (word(0), word(1));
+    let recipient = Address::from(log.topics[2]);
+
+    if amount0.is_positive() && amount1.is_negative() {
+        Some((
+            Dex::UniswapV3,
+            true,
+            amount0.into_raw(),
+            amount1.unsigned_abs(),
+            recipient,
+        ))
+    } else if amount1.is_positive() && amount0.is_negative() {
+        Some((
+            Dex::UniswapV3,
+            false,
+            amount1.into_raw(),
+            amount0.unsigned_abs(),
+            recipient,
+        ))
+    } else {
+        None
+    }
+}
+
+/// Lossy conversion of a raw token amount for price arithmetic
+pub fn to_f64(value: U256) -> f64 {
+    value.to_string().parse().unwrap_or(0.0)
+}
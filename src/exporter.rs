@@ -1,98 +1,814 @@
 use anyhow::Result;
+use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef};
+use arrow::json::ReaderBuilder;
+use arrow::json::reader::Decoder;
+use async_trait::async_trait;
 use csv::Writer;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
 use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
 
-use crate::types::BlockLifecycle;
+use crate::postgres::PostgresStore;
+use crate::sink::Sink;
+use crate::sqlite::SqliteStore;
+use crate::types::{
+    ArbitrageOp, BlockLifecycle, DetectorFinding, Liquidation, ProviderDisagreement, ReorgEvent,
+    SandwichAttack,
+};
+
+/// Rows per Parquet row group
+const PARQUET_BATCH_SIZE: usize = 1024;
+
+/// ETH amounts as 18-decimal fixed-point values, exact to the wei
+fn eth_type() -> DataType {
+    DataType::Decimal128(38, 18)
+}
+
+/// MEV profit estimates: derived from swap price ratios rather than wei
+/// transfers, so kept in floating point
+fn estimate_type() -> DataType {
+    DataType::Float64
+}
+
+/// Raw token amounts (uint256) as decimal strings: the largest uint256 has
+/// 78 digits, more than the 76 an Arrow decimal can hold
+fn token_amount_type() -> DataType {
+    DataType::Utf8
+}
 
 pub struct Exporter;
 
 impl Exporter {
     /// Export block lifecycle data to CSV for Python analysis
     pub fn export_to_csv(results: &[BlockLifecycle], path: &str) -> Result<()> {
-        let file = File::create(path)?;
-        let mut wtr = Writer::from_writer(file);
-
-        // Write header
-        wtr.write_record(&[
-            "block_number",
-            "block_hash",
-            "timestamp",
-            "proposer",
-            "builder",
-            "block_time",
-            "gas_used",
-            "gas_limit",
-            "gas_utilization",
-            "base_fee_gwei",
-            "avg_priority_fee_gwei",
-            "fees_burned_eth",
-            "priority_fees_eth",
-            "tx_count",
-            "tx_legacy",
-            "tx_eip2930",
-            "tx_eip1559",
-            "tx_eip4844",
-            "tx_failed",
-            "tx_ordering_anomalies",
-            "mev_sandwich_attacks",
-            "mev_arbitrage_ops",
-            "mev_liquidations",
-            "mev_estimated_eth",
-            "mev_bot_count",
-            "is_pbs_block",
-            "builder_address",
-            "extra_data",
-        ])?;
-
-        if results.is_empty() {
-            println!("⚠️ No blocks collected. CSV contains only header.");
-            wtr.flush()?;
-            return Ok(());
+        write_file(FileFormat::Csv, results, path)
+    }
+
+    /// Export reorgs seen during live monitoring to CSV, one row per reorg
+    pub fn export_reorgs_to_csv(reorgs: &[ReorgEvent], path: &str) -> Result<()> {
+        write_file(FileFormat::Csv, reorgs, path)
+    }
+
+    /// Export block lifecycle data to Parquet, keeping nested data (sandwiches,
+    /// arbitrage paths, liquidations, bot addresses) as list columns
+    pub fn export_to_parquet(results: &[BlockLifecycle], path: &str) -> Result<()> {
+        write_file(FileFormat::Parquet, results, path)
+    }
+
+    /// Export reorgs seen during live monitoring to Parquet, one row per reorg
+    pub fn export_reorgs_to_parquet(reorgs: &[ReorgEvent], path: &str) -> Result<()> {
+        write_file(FileFormat::Parquet, reorgs, path)
+    }
+
+    /// Upsert blocks into the SQLite database at `path`, creating it if needed
+    pub fn export_to_sqlite(results: &[BlockLifecycle], path: &str) -> Result<()> {
+        SqliteStore::open(path)?.write_blocks(results)
+    }
+
+    /// Record reorgs in the SQLite database at `path`
+    pub fn export_reorgs_to_sqlite(reorgs: &[ReorgEvent], path: &str) -> Result<()> {
+        SqliteStore::open(path)?.write_reorgs(reorgs)
+    }
+
+    /// Upsert blocks into the PostgreSQL database at `url`, migrating its
+    /// schema if needed
+    pub async fn export_to_postgres(results: &[BlockLifecycle], url: &str) -> Result<()> {
+        PostgresStore::connect(url)
+            .await?
+            .write_blocks(results)
+            .await
+    }
+
+    /// Record reorgs in the PostgreSQL database at `url`
+    pub async fn export_reorgs_to_postgres(reorgs: &[ReorgEvent], url: &str) -> Result<()> {
+        PostgresStore::connect(url)
+            .await?
+            .write_reorgs(reorgs)
+            .await
+    }
+
+    /// Export complete records (e.g. nested `BlockLifecycle`s) as one JSON array
+    pub fn export_to_json<T: Serialize>(items: &[T], path: &str) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, items)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Export complete records as JSON Lines, one record per line
+    pub fn export_to_jsonl<T: Serialize>(items: &[T], path: &str) -> Result<()> {
+        let mut writer = JsonLinesWriter::create(path)?;
+        for item in items {
+            writer.write(item);
         }
+        writer.finish()?;
+        Ok(())
+    }
+}
 
-        // Write data
-        for lifecycle in results {
-            wtr.write_record(&[
-                lifecycle.block_number.to_string(),
-                lifecycle.block_hash.clone(),
-                lifecycle.timestamp.to_string(),
-                lifecycle.proposer.clone(),
-                lifecycle.builder.clone().unwrap_or_else(|| "".to_string()),
-                lifecycle.timing.block_time.to_string(),
-                lifecycle.gas.gas_used.to_string(),
-                lifecycle.gas.gas_limit.to_string(),
-                lifecycle.gas.utilization.to_string(),
-                lifecycle.gas.base_fee_gwei.to_string(),
-                lifecycle.gas.avg_priority_fee_gwei.to_string(),
-                lifecycle.gas.fees_burned_eth.to_string(),
-                lifecycle.gas.priority_fees_eth.to_string(),
-                lifecycle.transactions.total_count.to_string(),
-                lifecycle.transactions.type_breakdown.legacy.to_string(),
-                lifecycle.transactions.type_breakdown.eip2930.to_string(),
-                lifecycle.transactions.type_breakdown.eip1559.to_string(),
-                lifecycle
-                    .transactions
-                    .type_breakdown
-                    .eip4844_blob
-                    .to_string(),
-                lifecycle.transactions.failed_count.to_string(),
-                lifecycle.transactions.ordering.anomalies.to_string(),
-                lifecycle.mev.sandwich_attacks.len().to_string(),
-                lifecycle.mev.arbitrage_ops.len().to_string(),
-                lifecycle.mev.liquidations.to_string(),
-                lifecycle.mev.estimated_mev_eth.to_string(),
-                lifecycle.mev.mev_bot_addresses.len().to_string(),
-                lifecycle.pbs.is_pbs_block.to_string(),
-                lifecycle
-                    .pbs
-                    .builder_address
-                    .clone()
-                    .unwrap_or_else(|| "".to_string()),
-                lifecycle.pbs.extra_data.clone(),
-            ])?;
+/// Writes records to a JSON Lines file as they are produced, flushing each
+/// line so a partial run still leaves every finished record on disk.
+///
+/// The first write error is kept and returned by [`finish`](Self::finish),
+/// so progress callbacks can write without handling errors themselves.
+pub struct JsonLinesWriter {
+    writer: BufWriter<File>,
+    written: usize,
+    error: Option<anyhow::Error>,
+}
+
+impl JsonLinesWriter {
+    pub fn create(path: &str) -> Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            written: 0,
+            error: None,
+        })
+    }
+
+    /// Append one record; does nothing once a write has failed
+    pub fn write<T: Serialize>(&mut self, item: &T) {
+        if self.error.is_some() {
+            return;
         }
 
-        wtr.flush()?;
+        let result = serde_json::to_writer(&mut self.writer, item)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(self.writer.write_all(b"\n")?))
+            .and_then(|_| Ok(self.writer.flush()?));
+        match result {
+            Ok(()) => self.written += 1,
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    /// Number of records written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Flush and report the first write error, if any
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.writer.flush()?;
         Ok(())
     }
+
+    /// Flush and report the first write error, if any. Returns the number of
+    /// records written.
+    pub fn finish(&mut self) -> Result<usize> {
+        self.flush()?;
+        Ok(self.written)
+    }
+}
+
+/// Record type with a CSV and a Parquet layout
+trait ExportRecord: Serialize {
+    const CSV_COLUMNS: &'static [&'static str];
+
+    type Row<'a>: Serialize
+    where
+        Self: 'a;
+
+    fn csv_record(&self) -> Vec<String>;
+
+    fn schema() -> SchemaRef;
+
+    fn row(&self) -> Self::Row<'_>;
+}
+
+const BLOCK_CSV_COLUMNS: &[&str] = &[
+    "block_number",
+    "block_hash",
+    "timestamp",
+    "proposer",
+    "builder",
+    "block_time",
+    "slot",
+    "received_at",
+    "propagation_delay",
+    "timestamp_delay",
+    "gas_used",
+    "gas_limit",
+    "gas_utilization",
+    "base_fee_gwei",
+    "avg_priority_fee_gwei",
+    "median_priority_fee_gwei",
+    "priority_fee_p10_gwei",
+    "priority_fee_p25_gwei",
+    "priority_fee_p75_gwei",
+    "priority_fee_p90_gwei",
+    "fees_burned_eth",
+    "priority_fees_eth",
+    "tx_count",
+    "tx_legacy",
+    "tx_eip2930",
+    "tx_eip1559",
+    "tx_eip4844",
+    "tx_failed",
+    "tx_ordering_anomalies",
+    "mev_sandwich_attacks",
+    "mev_arbitrage_ops",
+    "mev_liquidations",
+    "mev_other_findings",
+    "mev_estimated_eth",
+    "mev_bot_count",
+    "is_pbs_block",
+    "builder_address",
+    "builder_match_source",
+    "builder_confidence",
+    "builder_payment_eth",
+    "proposer_fee_recipient",
+    "builder_margin_eth",
+    "extra_data",
+    "provider_disagreements",
+    "labels",
+];
+
+impl ExportRecord for BlockLifecycle {
+    const CSV_COLUMNS: &'static [&'static str] = BLOCK_CSV_COLUMNS;
+
+    type Row<'a> = BlockRow<'a>;
+
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.block_number.to_string(),
+            self.block_hash.clone(),
+            self.timestamp.to_string(),
+            self.proposer.clone(),
+            self.builder.clone().unwrap_or_default(),
+            self.timing.block_time.to_string(),
+            optional(self.timing.slot),
+            optional(self.timing.received_at),
+            optional(self.timing.propagation_delay),
+            optional(self.timing.timestamp_delay),
+            self.gas.gas_used.to_string(),
+            self.gas.gas_limit.to_string(),
+            self.gas.utilization.to_string(),
+            self.gas.base_fee_gwei.to_string(),
+            self.gas.avg_priority_fee_gwei.to_string(),
+            self.gas.median_priority_fee_gwei.to_string(),
+            self.gas.priority_fee_percentiles.p10.to_string(),
+            self.gas.priority_fee_percentiles.p25.to_string(),
+            self.gas.priority_fee_percentiles.p75.to_string(),
+            self.gas.priority_fee_percentiles.p90.to_string(),
+            self.gas.fees_burned_eth.to_string(),
+            self.gas.priority_fees_eth.to_string(),
+            self.transactions.total_count.to_string(),
+            self.transactions.type_breakdown.legacy.to_string(),
+            self.transactions.type_breakdown.eip2930.to_string(),
+            self.transactions.type_breakdown.eip1559.to_string(),
+            self.transactions.type_breakdown.eip4844_blob.to_string(),
+            self.transactions.failed_count.to_string(),
+            self.transactions.ordering.anomalies.to_string(),
+            self.mev.sandwich_attacks.len().to_string(),
+            self.mev.arbitrage_ops.len().to_string(),
+            self.mev.liquidations.len().to_string(),
+            self.mev.other_findings.len().to_string(),
+            self.mev.estimated_mev_eth.to_string(),
+            self.mev.mev_bot_addresses.len().to_string(),
+            self.pbs.is_pbs_block.to_string(),
+            self.pbs.builder_address.clone().unwrap_or_default(),
+            optional(self.pbs.builder_match.as_ref().map(|m| m.source)),
+            optional(self.pbs.builder_match.as_ref().map(|m| m.confidence)),
+            optional(self.pbs.builder_payment_eth),
+            optional(self.pbs.proposer_fee_recipient.as_ref()),
+            optional(self.pbs.builder_margin_eth),
+            self.pbs.extra_data.clone(),
+            self.provider_disagreements.len().to_string(),
+            self.labels
+                .iter()
+                .map(|label| format!("{}={}", label.address, label.category))
+                .collect::<Vec<_>>()
+                .join(";"),
+        ]
+    }
+
+    fn schema() -> SchemaRef {
+        block_schema()
+    }
+
+    fn row(&self) -> BlockRow<'_> {
+        BlockRow::from(self)
+    }
+}
+
+impl ExportRecord for ReorgEvent {
+    const CSV_COLUMNS: &'static [&'static str] = &[
+        "detected_at",
+        "common_ancestor",
+        "depth",
+        "orphaned_hashes",
+        "replacement_hashes",
+    ];
+
+    type Row<'a> = ReorgRow<'a>;
+
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.detected_at.to_string(),
+            optional(self.common_ancestor),
+            self.depth.to_string(),
+            self.orphaned.join(";"),
+            self.replacement.join(";"),
+        ]
+    }
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("detected_at", DataType::UInt64, false),
+            Field::new("common_ancestor", DataType::UInt64, true),
+            Field::new("depth", DataType::UInt64, false),
+            list_of("orphaned_hashes", DataType::Utf8),
+            list_of("replacement_hashes", DataType::Utf8),
+        ]))
+    }
+
+    fn row(&self) -> ReorgRow<'_> {
+        ReorgRow {
+            detected_at: self.detected_at,
+            common_ancestor: self.common_ancestor,
+            depth: self.depth,
+            orphaned_hashes: &self.orphaned,
+            replacement_hashes: &self.replacement,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileFormat {
+    Csv,
+    Parquet,
+    Json,
+    Jsonl,
+}
+
+/// One open export file of blocks or reorgs
+enum RecordFile {
+    Csv(Box<Writer<File>>),
+    Parquet(Box<ParquetFile>),
+    Json(JsonArrayWriter),
+    Jsonl(JsonLinesWriter),
+}
+
+impl RecordFile {
+    /// Create the file at `path` and write its header, if the format has one
+    fn create<T: ExportRecord>(format: FileFormat, path: &str) -> Result<Self> {
+        Ok(match format {
+            FileFormat::Csv => {
+                let mut writer = Writer::from_path(path)?;
+                writer.write_record(T::CSV_COLUMNS)?;
+                writer.flush()?;
+                Self::Csv(Box::new(writer))
+            }
+            FileFormat::Parquet => Self::Parquet(Box::new(ParquetFile::create(T::schema(), path)?)),
+            FileFormat::Json => Self::Json(JsonArrayWriter::create(path)?),
+            FileFormat::Jsonl => Self::Jsonl(JsonLinesWriter::create(path)?),
+        })
+    }
+
+    fn write<T: ExportRecord>(&mut self, items: &[T]) -> Result<()> {
+        match self {
+            Self::Csv(writer) => {
+                for item in items {
+                    writer.write_record(item.csv_record())?;
+                }
+                writer.flush()?;
+            }
+            Self::Parquet(file) => {
+                let rows: Vec<_> = items.iter().map(T::row).collect();
+                file.write(&rows)?;
+            }
+            Self::Json(writer) => writer.write(items)?,
+            Self::Jsonl(writer) => {
+                for item in items {
+                    writer.write(item);
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self {
+            Self::Csv(writer) => writer.flush()?,
+            Self::Parquet(file) => file.finish()?,
+            Self::Json(writer) => writer.finish()?,
+            Self::Jsonl(writer) => writer.flush()?,
+        }
+        Ok(())
+    }
+}
+
+fn write_file<T: ExportRecord>(format: FileFormat, items: &[T], path: &str) -> Result<()> {
+    let mut file = RecordFile::create::<T>(format, path)?;
+    file.write(items)?;
+    file.finish()
+}
+
+/// Streams blocks to an export file as they're analyzed. Reorgs go to a
+/// second file next to it (see [`reorgs_path`]), created on the first reorg.
+///
+/// CSV and JSON Lines files hold every written block at all times; JSON
+/// arrays and Parquet files are only complete once the sink is finished.
+pub struct FileSink {
+    format: FileFormat,
+    path: String,
+    blocks: RecordFile,
+    reorgs: Option<RecordFile>,
+}
+
+impl FileSink {
+    fn create(format: FileFormat, path: &str) -> Result<Self> {
+        Ok(Self {
+            format,
+            path: path.to_string(),
+            blocks: RecordFile::create::<BlockLifecycle>(format, path)?,
+            reorgs: None,
+        })
+    }
+
+    pub fn csv(path: &str) -> Result<Self> {
+        Self::create(FileFormat::Csv, path)
+    }
+
+    pub fn parquet(path: &str) -> Result<Self> {
+        Self::create(FileFormat::Parquet, path)
+    }
+
+    pub fn json(path: &str) -> Result<Self> {
+        Self::create(FileFormat::Json, path)
+    }
+
+    pub fn jsonl(path: &str) -> Result<Self> {
+        Self::create(FileFormat::Jsonl, path)
+    }
+}
+
+#[async_trait]
+impl Sink for FileSink {
+    async fn write_blocks(&mut self, blocks: &[BlockLifecycle]) -> Result<()> {
+        self.blocks.write(blocks)
+    }
+
+    async fn write_reorgs(&mut self, reorgs: &[ReorgEvent]) -> Result<()> {
+        let file = match &mut self.reorgs {
+            Some(file) => file,
+            None => self.reorgs.insert(RecordFile::create::<ReorgEvent>(
+                self.format,
+                &reorgs_path(&self.path),
+            )?),
+        };
+        file.write(reorgs)
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.blocks.finish()?;
+        if let Some(reorgs) = &mut self.reorgs {
+            reorgs.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes records into a JSON array one batch at a time; the array is closed
+/// by [`finish`](Self::finish)
+struct JsonArrayWriter {
+    writer: BufWriter<File>,
+    written: usize,
+}
+
+impl JsonArrayWriter {
+    fn create(path: &str) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(b"[")?;
+        Ok(Self { writer, written: 0 })
+    }
+
+    fn write<T: Serialize>(&mut self, items: &[T]) -> Result<()> {
+        for item in items {
+            let separator: &[u8] = if self.written == 0 { b"\n" } else { b",\n" };
+            self.writer.write_all(separator)?;
+            serde_json::to_writer_pretty(&mut self.writer, item)?;
+            self.written += 1;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.write_all(b"\n]\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Parquet file written in row groups of [`PARQUET_BATCH_SIZE`] rows
+struct ParquetFile {
+    writer: ArrowWriter<File>,
+    decoder: Decoder,
+}
+
+impl ParquetFile {
+    fn create(schema: SchemaRef, path: &str) -> Result<Self> {
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(PARQUET_BATCH_SIZE)
+            .build();
+        Ok(Self {
+            writer: ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(props))?,
+            decoder: ReaderBuilder::new(schema).build_decoder()?,
+        })
+    }
+
+    fn write<R: Serialize>(&mut self, rows: &[R]) -> Result<()> {
+        for chunk in rows.chunks(PARQUET_BATCH_SIZE) {
+            self.decoder.serialize(chunk)?;
+            if let Some(batch) = self.decoder.flush()? {
+                self.writer.write(&batch)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Schema of the block Parquet export
+pub fn block_schema() -> SchemaRef {
+    let sandwich = Fields::from(vec![
+        Field::new("frontrun_tx", DataType::Utf8, false),
+        Field::new("victim_tx", DataType::Utf8, false),
+        Field::new("backrun_tx", DataType::Utf8, false),
+        Field::new("estimated_profit_eth", estimate_type(), true),
+        Field::new("dex", DataType::Utf8, false),
+        Field::new("pool", DataType::Utf8, false),
+        Field::new("attacker", DataType::Utf8, false),
+    ]);
+    let arbitrage = Fields::from(vec![
+        Field::new("tx_hash", DataType::Utf8, false),
+        list_of("path", DataType::Utf8),
+        Field::new("estimated_profit_eth", estimate_type(), true),
+        list_of("dexes_involved", DataType::Utf8),
+    ]);
+    let liquidation = Fields::from(vec![
+        Field::new("tx_hash", DataType::Utf8, false),
+        Field::new("protocol", DataType::Utf8, false),
+        Field::new("liquidator", DataType::Utf8, false),
+        Field::new("borrower", DataType::Utf8, false),
+        Field::new("collateral_asset", DataType::Utf8, false),
+        Field::new("collateral_amount", token_amount_type(), true),
+        Field::new("debt_asset", DataType::Utf8, false),
+        Field::new("debt_repaid", token_amount_type(), true),
+    ]);
+    let finding = Fields::from(vec![
+        Field::new("detector", DataType::Utf8, false),
+        list_of("tx_hashes", DataType::Utf8),
+        Field::new("description", DataType::Utf8, false),
+        Field::new("estimated_profit_eth", estimate_type(), true),
+    ]);
+    let disagreement = Fields::from(vec![
+        Field::new("endpoint", DataType::Utf8, false),
+        Field::new("expected_hash", DataType::Utf8, false),
+        Field::new("reported_hash", DataType::Utf8, false),
+    ]);
+    let label = Fields::from(vec![
+        Field::new("address", DataType::Utf8, false),
+        Field::new("category", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, true),
+    ]);
+
+    Arc::new(Schema::new(vec![
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("block_hash", DataType::Utf8, false),
+        Field::new("parent_hash", DataType::Utf8, false),
+        Field::new("timestamp", DataType::UInt64, false),
+        Field::new("proposer", DataType::Utf8, false),
+        Field::new("builder", DataType::Utf8, true),
+        Field::new("block_time", DataType::Float64, false),
+        Field::new("slot", DataType::UInt64, true),
+        Field::new("received_at", DataType::Float64, true),
+        Field::new("propagation_delay", DataType::Float64, true),
+        Field::new("timestamp_delay", DataType::Float64, true),
+        Field::new("gas_used", DataType::UInt64, false),
+        Field::new("gas_limit", DataType::UInt64, false),
+        Field::new("gas_utilization", DataType::Float64, false),
+        Field::new("base_fee_gwei", DataType::Float64, false),
+        Field::new("avg_priority_fee_gwei", DataType::Float64, false),
+        Field::new("median_priority_fee_gwei", DataType::Float64, false),
+        Field::new("priority_fee_p10_gwei", DataType::Float64, false),
+        Field::new("priority_fee_p25_gwei", DataType::Float64, false),
+        Field::new("priority_fee_p75_gwei", DataType::Float64, false),
+        Field::new("priority_fee_p90_gwei", DataType::Float64, false),
+        Field::new("fees_burned_eth", eth_type(), true),
+        Field::new("priority_fees_eth", eth_type(), true),
+        Field::new("tx_count", DataType::UInt64, false),
+        Field::new("tx_legacy", DataType::UInt64, false),
+        Field::new("tx_eip2930", DataType::UInt64, false),
+        Field::new("tx_eip1559", DataType::UInt64, false),
+        Field::new("tx_eip4844", DataType::UInt64, false),
+        Field::new("tx_failed", DataType::UInt64, false),
+        Field::new("tx_ordering_anomalies", DataType::UInt64, false),
+        list_of("mev_sandwich_attacks", DataType::Struct(sandwich)),
+        list_of("mev_arbitrage_ops", DataType::Struct(arbitrage)),
+        list_of("mev_liquidations", DataType::Struct(liquidation)),
+        list_of("mev_other_findings", DataType::Struct(finding)),
+        Field::new("mev_estimated_eth", estimate_type(), true),
+        list_of("mev_bot_addresses", DataType::Utf8),
+        Field::new("is_pbs_block", DataType::Boolean, false),
+        Field::new("builder_address", DataType::Utf8, true),
+        Field::new("builder_match_source", DataType::Utf8, true),
+        Field::new("builder_confidence", DataType::Utf8, true),
+        Field::new("builder_payment_eth", eth_type(), true),
+        Field::new("proposer_fee_recipient", DataType::Utf8, true),
+        Field::new("builder_margin_eth", eth_type(), true),
+        Field::new("extra_data", DataType::Utf8, false),
+        list_of("provider_disagreements", DataType::Struct(disagreement)),
+        list_of("labels", DataType::Struct(label)),
+    ]))
+}
+
+/// Non-null list column of non-null `item`s
+fn list_of(name: &str, item: DataType) -> Field {
+    Field::new(
+        name,
+        DataType::List(Arc::new(Field::new("item", item, false))),
+        false,
+    )
+}
+
+/// One block as a row of [`block_schema`]. Nested MEV and label types
+/// serialize with the field names the schema expects.
+#[derive(Serialize)]
+struct BlockRow<'a> {
+    block_number: u64,
+    block_hash: &'a str,
+    parent_hash: &'a str,
+    timestamp: u64,
+    proposer: &'a str,
+    builder: Option<&'a str>,
+    block_time: f64,
+    slot: Option<u64>,
+    received_at: Option<f64>,
+    propagation_delay: Option<f64>,
+    timestamp_delay: Option<f64>,
+    gas_used: u64,
+    gas_limit: u64,
+    gas_utilization: f64,
+    base_fee_gwei: f64,
+    avg_priority_fee_gwei: f64,
+    median_priority_fee_gwei: f64,
+    priority_fee_p10_gwei: f64,
+    priority_fee_p25_gwei: f64,
+    priority_fee_p75_gwei: f64,
+    priority_fee_p90_gwei: f64,
+    fees_burned_eth: Option<String>,
+    priority_fees_eth: Option<String>,
+    tx_count: usize,
+    tx_legacy: usize,
+    tx_eip2930: usize,
+    tx_eip1559: usize,
+    tx_eip4844: usize,
+    tx_failed: usize,
+    tx_ordering_anomalies: usize,
+    mev_sandwich_attacks: &'a [SandwichAttack],
+    mev_arbitrage_ops: &'a [ArbitrageOp],
+    mev_liquidations: &'a [Liquidation],
+    mev_other_findings: &'a [DetectorFinding],
+    mev_estimated_eth: f64,
+    mev_bot_addresses: &'a [String],
+    is_pbs_block: bool,
+    builder_address: Option<&'a str>,
+    builder_match_source: Option<String>,
+    builder_confidence: Option<String>,
+    builder_payment_eth: Option<String>,
+    proposer_fee_recipient: Option<&'a str>,
+    builder_margin_eth: Option<String>,
+    extra_data: &'a str,
+    provider_disagreements: &'a [ProviderDisagreement],
+    labels: Vec<LabelRow<'a>>,
+}
+
+#[derive(Serialize)]
+struct LabelRow<'a> {
+    address: &'a str,
+    category: String,
+    name: Option<&'a str>,
+}
+
+impl<'a> From<&'a BlockLifecycle> for BlockRow<'a> {
+    fn from(lifecycle: &'a BlockLifecycle) -> Self {
+        let gas = &lifecycle.gas;
+        let txs = &lifecycle.transactions;
+        let mev = &lifecycle.mev;
+        let pbs = &lifecycle.pbs;
+
+        Self {
+            block_number: lifecycle.block_number,
+            block_hash: &lifecycle.block_hash,
+            parent_hash: &lifecycle.parent_hash,
+            timestamp: lifecycle.timestamp,
+            proposer: &lifecycle.proposer,
+            builder: lifecycle.builder.as_deref(),
+            block_time: lifecycle.timing.block_time,
+            slot: lifecycle.timing.slot,
+            received_at: lifecycle.timing.received_at,
+            propagation_delay: lifecycle.timing.propagation_delay,
+            timestamp_delay: lifecycle.timing.timestamp_delay,
+            gas_used: gas.gas_used,
+            gas_limit: gas.gas_limit,
+            gas_utilization: gas.utilization,
+            base_fee_gwei: gas.base_fee_gwei,
+            avg_priority_fee_gwei: gas.avg_priority_fee_gwei,
+            median_priority_fee_gwei: gas.median_priority_fee_gwei,
+            priority_fee_p10_gwei: gas.priority_fee_percentiles.p10,
+            priority_fee_p25_gwei: gas.priority_fee_percentiles.p25,
+            priority_fee_p75_gwei: gas.priority_fee_percentiles.p75,
+            priority_fee_p90_gwei: gas.priority_fee_percentiles.p90,
+            fees_burned_eth: gas.fees_burned_wei.as_deref().map(wei_to_eth_decimal),
+            priority_fees_eth: gas.priority_fees_wei.as_deref().map(wei_to_eth_decimal),
+            tx_count: txs.total_count,
+            tx_legacy: txs.type_breakdown.legacy,
+            tx_eip2930: txs.type_breakdown.eip2930,
+            tx_eip1559: txs.type_breakdown.eip1559,
+            tx_eip4844: txs.type_breakdown.eip4844_blob,
+            tx_failed: txs.failed_count,
+            tx_ordering_anomalies: txs.ordering.anomalies,
+            mev_sandwich_attacks: &mev.sandwich_attacks,
+            mev_arbitrage_ops: &mev.arbitrage_ops,
+            mev_liquidations: &mev.liquidations,
+            mev_other_findings: &mev.other_findings,
+            mev_estimated_eth: mev.estimated_mev_eth,
+            mev_bot_addresses: &mev.mev_bot_addresses,
+            is_pbs_block: pbs.is_pbs_block,
+            builder_address: pbs.builder_address.as_deref(),
+            builder_match_source: pbs.builder_match.as_ref().map(|m| m.source.to_string()),
+            builder_confidence: pbs.builder_match.as_ref().map(|m| m.confidence.to_string()),
+            builder_payment_eth: pbs.builder_payment_wei.as_deref().map(wei_to_eth_decimal),
+            proposer_fee_recipient: pbs.proposer_fee_recipient.as_deref(),
+            builder_margin_eth: pbs.builder_margin_wei.as_deref().map(wei_to_eth_decimal),
+            extra_data: &pbs.extra_data,
+            provider_disagreements: &lifecycle.provider_disagreements,
+            labels: lifecycle
+                .labels
+                .iter()
+                .map(|label| LabelRow {
+                    address: &label.address,
+                    category: label.category.to_string(),
+                    name: label.name.as_deref(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ReorgRow<'a> {
+    detected_at: u64,
+    common_ancestor: Option<u64>,
+    depth: usize,
+    orphaned_hashes: &'a [String],
+    replacement_hashes: &'a [String],
+}
+
+/// Wei amount (a decimal string, possibly negative) as ETH with all 18
+/// decimals, e.g. `-1500000000000000000` as `-1.500000000000000000`
+fn wei_to_eth_decimal(wei: &str) -> String {
+    let (sign, digits) = match wei.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", wei),
+    };
+    let digits = format!("{:0>19}", digits);
+    let (whole, fraction) = digits.split_at(digits.len() - 18);
+    format!("{}{}.{}", sign, whole, fraction)
+}
+
+/// Path of the reorg file written next to a block export
+/// (`data/live.csv` becomes `data/live_reorgs.csv`)
+pub fn reorgs_path(path: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}_reorgs.{}", stem, ext.to_string_lossy()),
+        None => format!("{}_reorgs", stem),
+    };
+
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+/// Empty cell for missing values
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
 }
@@ -0,0 +1,121 @@
+use ethers::providers::JsonRpcError;
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Numbers the temporary files recordings are written through
+static PARTIAL_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Whether JSON-RPC traffic is saved to, or served from, a fixture directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Send calls to the node and save every answer
+    Record,
+
+    /// Answer calls from saved fixtures without touching the network
+    Replay,
+}
+
+/// One recorded call, stored as `<method>-<key>.json`
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedCall {
+    method: String,
+    params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+
+    /// JSON-RPC error object (`code`, `message`, `data`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<Value>,
+}
+
+/// Directory of recorded JSON-RPC calls, keyed by method and parameters.
+///
+/// Batches are recorded call by call, so a replay may group the calls
+/// differently from the recording (e.g. with another `--batch-size`).
+#[derive(Debug, Clone)]
+pub struct RpcFixtures {
+    dir: PathBuf,
+    mode: FixtureMode,
+}
+
+impl RpcFixtures {
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: FixtureMode::Record,
+        }
+    }
+
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: FixtureMode::Replay,
+        }
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File a call is stored in
+    pub fn path(&self, method: &str, params: &Value) -> PathBuf {
+        let hash = keccak256(format!("{}:{}", method, params));
+        self.dir
+            .join(format!("{}-{}.json", method, hex::encode(&hash[..8])))
+    }
+
+    /// Save the answer to a call, replacing any earlier recording
+    pub fn save(
+        &self,
+        method: &str,
+        params: &Value,
+        answer: &Result<Value, JsonRpcError>,
+    ) -> std::io::Result<()> {
+        let call = RecordedCall {
+            method: method.to_string(),
+            params: params.clone(),
+            result: answer.as_ref().ok().cloned(),
+            error: answer.as_ref().err().map(|err| {
+                json!({"code": err.code, "message": err.message, "data": err.data})
+            }),
+        };
+
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(method, params);
+
+        // Write then rename so concurrent recordings never leave a torn file
+        let partial = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            PARTIAL_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&partial, serde_json::to_vec_pretty(&call)?)?;
+        std::fs::rename(partial, path)
+    }
+
+    /// Recorded answer to a call, `None` if it was never recorded
+    pub fn load(
+        &self,
+        method: &str,
+        params: &Value,
+    ) -> std::io::Result<Option<Result<Value, JsonRpcError>>> {
+        let text = match std::fs::read(self.path(method, params)) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let call: RecordedCall = serde_json::from_slice(&text)?;
+        Ok(Some(match call.error {
+            Some(error) => Err(serde_json::from_value(error)?),
+            None => Ok(call.result.unwrap_or(Value::Null)),
+        }))
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::types::{AddressLabel, LabelCategory};
+
+/// Label set shipped with the binary, used unless another file is loaded
+const DEFAULT_LABELS: &str = include_str!("../config/labels.toml");
+
+/// On-disk label file format (TOML or JSON)
+#[derive(Debug, Deserialize)]
+struct LabelFile {
+    #[serde(default, rename = "label")]
+    labels: Vec<AddressLabel>,
+}
+
+/// Known addresses (searchers, builders, routers, exchanges), looked up by address
+#[derive(Debug, Clone)]
+pub struct LabelSet {
+    labels: HashSet<AddressLabel>,
+}
+
+impl Default for LabelSet {
+    fn default() -> Self {
+        Self::from_toml_str(DEFAULT_LABELS).expect("built-in label set is valid")
+    }
+}
+
+impl LabelSet {
+    pub fn new(labels: Vec<AddressLabel>) -> Self {
+        let labels = labels
+            .into_iter()
+            .map(|label| AddressLabel {
+                address: label.address.to_lowercase(),
+                ..label
+            })
+            .collect();
+
+        Self { labels }
+    }
+
+    /// Load a label file; `.json` files are read as JSON, anything else as TOML
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read label file {}", path))?;
+
+        let is_json = Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+        if is_json {
+            Self::from_json_str(&text)
+        } else {
+            Self::from_toml_str(&text)
+        }
+        .with_context(|| format!("Invalid label file {}", path))
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let file: LabelFile = toml::from_str(text)?;
+        Ok(Self::new(file.labels))
+    }
+
+    pub fn from_json_str(text: &str) -> Result<Self> {
+        let file: LabelFile = serde_json::from_str(text)?;
+        Ok(Self::new(file.labels))
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Label of `address` (any case), if known
+    pub fn get(&self, address: &str) -> Option<&AddressLabel> {
+        self.labels.get(address.to_lowercase().as_str())
+    }
+
+    /// Whether `address` is a known MEV searcher
+    pub fn is_searcher(&self, address: &str) -> bool {
+        self.get(address)
+            .is_some_and(|label| label.category == LabelCategory::Searcher)
+    }
+
+    /// Labels of the known addresses among `addresses`, without duplicates
+    pub fn label_all<'a, I>(&self, addresses: I) -> Vec<AddressLabel>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut seen = HashSet::new();
+
+        addresses
+            .into_iter()
+            .filter_map(|address| self.get(address))
+            .filter(|label| seen.insert(label.address.as_str()))
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,184 @@
+use ethers::types::{Address, H256, Log, Transaction, TransactionReceipt, U64, U256};
+
+use crate::dex::topic;
+use crate::types::Liquidation;
+
+/// Aave V2 LendingPool (mainnet)
+const AAVE_V2_POOL: &str = "0x7d2768de32b0b80b7a3454c06bdac94a69ddc7a9";
+
+/// Aave V3 Pool (mainnet)
+const AAVE_V3_POOL: &str = "0x87870bca3f3fd6335c3f4ce8392d69350b4fa4e2";
+
+/// DAI, the debt asset of every Maker vault
+const DAI: &str = "0x6b175474e89094c44da98b954eedeac495271d0f";
+
+/// Decode the liquidations of every successful transaction, in block order
+pub fn decode_liquidations(
+    transactions: &[Transaction],
+    receipts: &[TransactionReceipt],
+) -> Vec<Liquidation> {
+    let aave = topic("LiquidationCall(address,address,address,uint256,uint256,address,bool)");
+    let compound_v2 = topic("LiquidateBorrow(address,address,uint256,address,uint256)");
+    let compound_v3 = topic("AbsorbCollateral(address,address,address,uint256,uint256)");
+    let compound_v3_debt = topic("AbsorbDebt(address,address,uint256,uint256)");
+    let maker = topic("Bark(bytes32,address,uint256,uint256,uint256,address,uint256)");
+
+    let mut liquidations = Vec::new();
+
+    for receipt in receipts {
+        if receipt.status == Some(U64::zero()) {
+            continue;
+        }
+        let tx_index = receipt.transaction_index.as_usize();
+        let sender = transactions
+            .get(tx_index)
+            .filter(|tx| tx.hash == receipt.transaction_hash)
+            .map(|tx| tx.from)
+            .unwrap_or(receipt.from);
+
+        for log in &receipt.logs {
+            let Some(first) = log.topics.first() else {
+                continue;
+            };
+
+            let decoded = if *first == aave {
+                decode_aave(log)
+            } else if *first == compound_v2 {
+                decode_compound_v2(log)
+            } else if *first == compound_v3 {
+                decode_compound_v3(log, receipt, compound_v3_debt)
+            } else if *first == maker {
+                decode_maker(log, sender)
+            } else {
+                None
+            };
+
+            if let Some(mut liquidation) = decoded {
+                liquidation.tx_hash = format!("{:?}", receipt.transaction_hash);
+                liquidations.push(liquidation);
+            }
+        }
+    }
+
+    liquidations
+}
+
+fn word(log: &Log, i: usize) -> U256 {
+    U256::from_big_endian(&log.data[i * 32..(i + 1) * 32])
+}
+
+fn address_word(log: &Log, i: usize) -> Address {
+    Address::from_slice(&log.data[i * 32 + 12..(i + 1) * 32])
+}
+
+fn hex(address: Address) -> String {
+    format!("{:?}", address)
+}
+
+/// `LiquidationCall(collateralAsset, debtAsset, user, debtToCover,
+/// liquidatedCollateralAmount, liquidator, receiveAToken)`, same in V2 and V3
+fn decode_aave(log: &Log) -> Option<Liquidation> {
+    if log.topics.len() != 4 || log.data.len() != 4 * 32 {
+        return None;
+    }
+
+    let pool = hex(log.address);
+    let protocol = if pool == AAVE_V2_POOL {
+        "Aave V2"
+    } else if pool == AAVE_V3_POOL {
+        "Aave V3"
+    } else {
+        "Aave"
+    };
+
+    Some(Liquidation {
+        tx_hash: String::new(),
+        protocol: protocol.to_string(),
+        liquidator: hex(address_word(log, 2)),
+        borrower: hex(Address::from(log.topics[3])),
+        collateral_asset: hex(Address::from(log.topics[1])),
+        collateral_amount: word(log, 1).to_string(),
+        debt_asset: hex(Address::from(log.topics[2])),
+        debt_repaid: word(log, 0).to_string(),
+    })
+}
+
+/// `LiquidateBorrow(liquidator, borrower, repayAmount, cTokenCollateral,
+/// seizeTokens)`, emitted by the cToken of the repaid market
+fn decode_compound_v2(log: &Log) -> Option<Liquidation> {
+    if log.topics.len() != 1 || log.data.len() != 5 * 32 {
+        return None;
+    }
+
+    Some(Liquidation {
+        tx_hash: String::new(),
+        protocol: "Compound V2".to_string(),
+        liquidator: hex(address_word(log, 0)),
+        borrower: hex(address_word(log, 1)),
+        collateral_asset: hex(address_word(log, 3)),
+        collateral_amount: word(log, 4).to_string(),
+        debt_asset: hex(log.address),
+        debt_repaid: word(log, 2).to_string(),
+    })
+}
+
+/// `AbsorbCollateral(absorber, borrower, asset, collateralAbsorbed, usdValue)`.
+/// The debt written off comes from the `AbsorbDebt(absorber, borrower,
+/// basePaidOut, usdValue)` the same Comet emits for the borrower.
+fn decode_compound_v3(
+    log: &Log,
+    receipt: &TransactionReceipt,
+    absorb_debt: H256,
+) -> Option<Liquidation> {
+    if log.topics.len() != 4 || log.data.len() != 2 * 32 {
+        return None;
+    }
+
+    let debt_repaid = receipt
+        .logs
+        .iter()
+        .find(|debt| {
+            debt.address == log.address
+                && debt.topics.len() == 3
+                && debt.topics[0] == absorb_debt
+                && debt.topics[2] == log.topics[2]
+                && debt.data.len() == 2 * 32
+        })
+        .map(|debt| word(debt, 0).to_string())
+        .unwrap_or_default();
+
+    Some(Liquidation {
+        tx_hash: String::new(),
+        protocol: "Compound V3".to_string(),
+        liquidator: hex(Address::from(log.topics[1])),
+        borrower: hex(Address::from(log.topics[2])),
+        collateral_asset: hex(Address::from(log.topics[3])),
+        collateral_amount: word(log, 0).to_string(),
+        debt_asset: hex(log.address),
+        debt_repaid,
+    })
+}
+
+/// `Bark(ilk, urn, ink, art, due, clip, id)` from the Maker Dog. The keeper
+/// that called `bark` is the transaction sender; the collateral is named by
+/// its ilk (e.g. `ETH-A`) and the debt is DAI in rad (45 decimals).
+fn decode_maker(log: &Log, sender: Address) -> Option<Liquidation> {
+    if log.topics.len() != 4 || log.data.len() != 4 * 32 {
+        return None;
+    }
+
+    let ilk = String::from_utf8_lossy(log.topics[1].as_bytes())
+        .trim_end_matches('\0')
+        .to_string();
+
+    Some(Liquidation {
+        tx_hash: String::new(),
+        protocol: "Maker".to_string(),
+        liquidator: hex(sender),
+        borrower: hex(Address::from(log.topics[2])),
+        collateral_asset: ilk,
+        collateral_amount: word(log, 0).to_string(),
+        debt_asset: DAI.to_string(),
+        debt_repaid: word(log, 2).to_string(),
+    })
+}
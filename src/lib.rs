@@ -1,4 +1,19 @@
 pub mod types;
 pub mod rpc;
+pub mod source;
+pub mod transport;
+pub mod fixtures;
+pub mod cache;
+pub mod retry;
+pub mod reorg;
+pub mod builders;
+pub mod labels;
+pub mod dex;
+pub mod mev;
+pub mod lending;
 pub mod analyzer;
-pub mod exporter;   
\ No newline at end of file
+pub mod exporter;
+mod store;
+pub mod sqlite;
+pub mod postgres;
+pub mod sink;   
\ No newline at end of file
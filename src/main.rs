@@ -1,20 +1,33 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use colored::Colorize;
 use dotenv::dotenv;
+use ethers::types::{Block, Transaction, TransactionReceipt, U64};
 use std::env;
+use std::time::Duration;
 
-mod analyzer;
-mod exporter;
-mod rpc;
-mod types;
+use blockstream_inspector::analyzer::{AnalysisEvent, BlockAnalyzer, DEFAULT_MAX_IN_FLIGHT};
+use blockstream_inspector::builders::BuilderRegistry;
+use blockstream_inspector::cache::{BlockCache, CachedSource, DEFAULT_MAX_CACHE_BYTES};
+use blockstream_inspector::labels::LabelSet;
+use blockstream_inspector::mev::DetectorRegistry;
+use blockstream_inspector::types::BlockLifecycle;
+use blockstream_inspector::exporter::{self, FileSink};
+use blockstream_inspector::fixtures::RpcFixtures;
+use blockstream_inspector::postgres::{self, PostgresStore};
+use blockstream_inspector::retry::{RateLimit, RetryPolicy};
+use blockstream_inspector::rpc::{
+    DEFAULT_BATCH_SIZE, DEFAULT_MAX_LAG, EthereumRpcClient, RpcConfig, wei_to_eth, wei_to_gwei,
+};
+use blockstream_inspector::sink::{FlushPolicy, SinkWriter};
+use blockstream_inspector::source::BlockSource;
+use blockstream_inspector::sqlite::SqliteStore;
 
-use analyzer::BlockAnalyzer;
-use exporter::Exporter;
+const MIB: u64 = 1024 * 1024;
 
 #[derive(Parser)]
 #[command(name = "BlockStream Inspector")]
 #[command(about = "BlockStream Inspector — An advanced analytics tool for examining Ethereum block production, propagation timing, transaction ordering, and MEV extraction trends", long_about = None)]
-
 struct Cli {
     //NB I will make use of my own RPC Later in this project
     /// RPC endpoint URL (e.g., http://localhost:8545 or Infura/Alchemy URL)
@@ -24,9 +37,98 @@ struct Cli {
     //     env = "ALCHEMY_RPC_URL",
     //     default_value = "http://localhost:8545"
     // )]
-    /// RPC endpoint URL (e.g., Alchemy/Infura URL)
-    #[arg(short, long)]
-    rpc: Option<String>, // optional so build from ALCHEMY_API_KEY if missing
+    /// RPC endpoint URL (e.g., Alchemy/Infura URL). Repeat or comma-separate
+    /// several URLs to fail over between them in the given order
+    #[arg(short, long, value_delimiter = ',')]
+    rpc: Vec<String>, // optional so build from ALCHEMY_API_KEY if missing
+
+    /// Cross-check block hashes across all RPC endpoints and report disagreements
+    #[arg(long, global = true)]
+    quorum: bool,
+
+    /// WebSocket URL (ws://, wss://) or IPC socket path; `live` then follows
+    /// newHeads instead of polling
+    #[arg(long, global = true, env = "RPC_WS_URL")]
+    ws: Option<String>,
+
+    /// Blocks the active endpoint may fall behind the others before failing over
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_LAG)]
+    max_lag: u64,
+
+    /// Maximum number of concurrent block requests
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_IN_FLIGHT)]
+    max_in_flight: usize,
+
+    /// Number of calls sent per JSON-RPC batch
+    #[arg(long, global = true, default_value_t = DEFAULT_BATCH_SIZE)]
+    batch_size: usize,
+
+    /// Retries for rate-limited or failed RPC requests (0 disables retrying)
+    #[arg(long, global = true, default_value = "5")]
+    max_retries: u32,
+
+    /// Maximum RPC calls per second
+    #[arg(long, global = true, env = "RPC_REQUESTS_PER_SECOND")]
+    rps: Option<f64>,
+
+    /// Maximum provider compute units per second (e.g. Alchemy CU/s)
+    #[arg(long, global = true, env = "RPC_COMPUTE_UNITS_PER_SECOND")]
+    cups: Option<f64>,
+
+    /// Builder registry file (TOML or JSON) replacing the built-in builder list
+    #[arg(long, global = true, env = "BUILDER_REGISTRY")]
+    builders: Option<String>,
+
+    /// Address label file (TOML or JSON) replacing the built-in labels
+    #[arg(long, global = true, env = "ADDRESS_LABELS")]
+    labels: Option<String>,
+
+    /// Save every JSON-RPC request and response to this fixture directory
+    #[arg(long, global = true, conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Answer JSON-RPC calls from a recorded fixture directory, offline
+    #[arg(long, global = true)]
+    replay: Option<String>,
+
+    /// Directory of the local cache of finalized blocks
+    #[arg(
+        long,
+        global = true,
+        env = "BLOCK_CACHE_DIR",
+        default_value = "data/cache"
+    )]
+    cache_dir: String,
+
+    /// Always fetch from the node, without reading or filling the block cache
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Size limit of the block cache in MiB; least recently used blocks are evicted
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_CACHE_BYTES / MIB)]
+    cache_max_mb: u64,
+
+    /// Turn off MEV detectors by name (sandwich, arbitrage, liquidation)
+    #[arg(long = "disable-detector", global = true, value_delimiter = ',')]
+    disable_detectors: Vec<String>,
+
+    /// Turn MEV detectors back on by name
+    #[arg(long = "enable-detector", global = true, value_delimiter = ',')]
+    enable_detectors: Vec<String>,
+
+    /// Write exported blocks in batches of this many (1 writes each block as
+    /// soon as it's analyzed)
+    #[arg(long, global = true, default_value_t = 1)]
+    flush_blocks: usize,
+
+    /// Also write buffered blocks once they've waited this many seconds
+    #[arg(long, global = true)]
+    flush_secs: Option<f64>,
+
+    /// Detector option as detector.key=value, e.g. sandwich.min_profit_eth=0.01
+    /// or liquidation.protocols=aave,maker. Repeatable
+    #[arg(long = "detector-opt", global = true)]
+    detector_opts: Vec<String>,
 
     #[command(subcommand)]
     command: Commands,
@@ -55,9 +157,13 @@ enum Commands {
         #[arg(short, long)]
         end: u64,
 
-        /// Export to CSV
+        /// Export results to this file
         #[arg(short = 'o', long)]
         output: Option<String>,
+
+        /// File format of --output
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
     },
 
     /// Live monitoring mode
@@ -66,9 +172,13 @@ enum Commands {
         #[arg(short, long, default_value = "10")]
         count: u64,
 
-        /// Export to CSV
+        /// Export results to this file
         #[arg(short = 'o', long)]
         output: Option<String>,
+
+        /// File format of --output
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
     },
 
     /// Detect MEV opportunities in recent blocks
@@ -80,6 +190,57 @@ enum Commands {
         /// Minimum profit threshold in ETH
         #[arg(short, long, default_value = "0.1")]
         threshold: f64,
+
+        /// Export the flagged blocks to this file
+        #[arg(short = 'o', long)]
+        output: Option<String>,
+
+        /// File format of --output
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+    },
+
+    /// Inspect or prune the local block cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+/// File format of exported results
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Csv,
+    /// Typed columns, with nested MEV data kept as lists
+    Parquet,
+    /// Complete nested block records as one JSON array
+    Json,
+    /// One complete block record per line, written as each block is analyzed
+    Jsonl,
+    /// Upsert into a SQLite database, accumulating across runs
+    Sqlite,
+    /// Upsert into a PostgreSQL database; `--output` is its connection URL
+    Postgres,
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Show how many blocks are cached and how much space they take
+    Stats,
+
+    /// Remove cached blocks
+    Prune {
+        /// Evict least recently used blocks until the cache fits in this many MiB
+        #[arg(long)]
+        max_mb: Option<u64>,
+
+        /// Remove blocks below this number
+        #[arg(long)]
+        below: Option<u64>,
+
+        /// Remove every cached block
+        #[arg(long, conflicts_with_all = ["max_mb", "below"])]
+        all: bool,
     },
 }
 
@@ -90,38 +251,384 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
 
+    // The cache is managed offline, without an RPC endpoint
+    if let Commands::Cache { action } = &cli.command {
+        return manage_cache(&cli.cache_dir, action);
+    }
+
     // Determine the RPC URL
-    let rpc_url = cli.rpc.clone()
-    .or_else(|| env::var("ALCHEMY_RPC_URL").ok())
-    .or_else(|| env::var("ALCHEMY_API_KEY").ok().map(|key| format!("https://eth-mainnet.g.alchemy.com/v2/{}", key)))
-    .unwrap_or_else(|| {
-        eprintln!("ERROR: No valid Alchemy RPC key found. Please set ALCHEMY_RPC_URL or ALCHEMY_API_KEY.");
-        std::process::exit(1);
-    });
+    let rpc_urls = if !cli.rpc.is_empty() {
+        cli.rpc.clone()
+    } else if cli.replay.is_some() {
+        // Never contacted while replaying
+        vec!["http://replay.invalid".to_string()]
+    } else {
+        let rpc_url = env::var("ALCHEMY_RPC_URL").ok()
+        .or_else(|| env::var("ALCHEMY_API_KEY").ok().map(|key| format!("https://eth-mainnet.g.alchemy.com/v2/{}", key)))
+        .unwrap_or_else(|| {
+            eprintln!("ERROR: No valid Alchemy RPC key found. Please set ALCHEMY_RPC_URL or ALCHEMY_API_KEY.");
+            std::process::exit(1);
+        });
+        vec![rpc_url]
+    };
+
+    println!("Using RPC URL: {}", rpc_urls.join(", "));
+
+    let config = RpcConfig {
+        batch_size: cli.batch_size,
+        retry: RetryPolicy {
+            max_retries: cli.max_retries,
+            ..RetryPolicy::default()
+        },
+        rate_limit: RateLimit {
+            requests_per_second: cli.rps,
+            compute_units_per_second: cli.cups,
+        },
+        quorum: cli.quorum,
+        max_lag: cli.max_lag,
+        pubsub_url: cli.ws.clone(),
+        fixtures: match (&cli.record, &cli.replay) {
+            (Some(dir), _) => Some(RpcFixtures::record(dir)),
+            (_, Some(dir)) => Some(RpcFixtures::replay(dir)),
+            _ => None,
+        },
+    };
+
+    let builders = match &cli.builders {
+        Some(path) => BuilderRegistry::load(path)?,
+        None => BuilderRegistry::default(),
+    };
 
-    println!("Using RPC URL: {}", rpc_url);
+    let labels = match &cli.labels {
+        Some(path) => LabelSet::load(path)?,
+        None => LabelSet::default(),
+    };
 
-    let analyzer = BlockAnalyzer::new(&rpc_url).await?;
+    let mut detectors = DetectorRegistry::default();
+    for name in &cli.disable_detectors {
+        detectors.disable(name)?;
+    }
+    for name in &cli.enable_detectors {
+        detectors.enable(name)?;
+    }
+    for option in &cli.detector_opts {
+        detectors.configure_from(option)?;
+    }
+
+    println!("Connecting to Ethereum node at {}...", rpc_urls.join(", "));
+    let client = EthereumRpcClient::with_config(&rpc_urls, config).await?;
+    println!("✓ Connected successfully!\n");
+
+    // Recording and replaying must see every call, so they bypass the cache
+    if cli.no_cache || cli.record.is_some() || cli.replay.is_some() {
+        run(client, cli, builders, labels, detectors).await
+    } else {
+        let cache = BlockCache::open(&cli.cache_dir)?.with_max_bytes(cli.cache_max_mb * MIB);
+        let source = CachedSource::new(client, cache);
+        run(source, cli, builders, labels, detectors).await
+    }
+}
+
+async fn run<S: BlockSource>(
+    source: S,
+    cli: Cli,
+    builders: BuilderRegistry,
+    labels: LabelSet,
+    detectors: DetectorRegistry,
+) -> Result<()> {
+    let policy = FlushPolicy {
+        max_blocks: cli.flush_blocks,
+        interval: cli
+            .flush_secs
+            .map(Duration::try_from_secs_f64)
+            .transpose()?,
+    };
+    let analyzer = BlockAnalyzer::with_source(source)
+        .with_max_in_flight(cli.max_in_flight)
+        .with_builder_registry(builders)
+        .with_labels(labels)
+        .with_detectors(detectors);
 
     match cli.command {
         Commands::Block { number, verbose } => {
-            analyzer.analyze_single_block(&number, verbose).await?;
+            let details = analyzer.analyze_single_block(&number).await?;
+            println!("{}", details.lifecycle);
+
+            if verbose {
+                print_transaction_details(&details.block, &details.receipts);
+            }
         }
-        Commands::Range { start, end, output } => {
-            let results = analyzer.analyze_range(start, end).await?;
+        Commands::Range {
+            start,
+            end,
+            output,
+            format,
+        } => {
+            println!(
+                "Analyzing blocks {} to {} ({} blocks)...\n",
+                start,
+                end,
+                end - start + 1
+            );
 
-            if let Some(path) = output {
-                Exporter::export_to_csv(&results, &path)?;
-                println!("✓ Exported {} blocks to {}", results.len(), path);
+            let analyzer = with_sink(analyzer, output.as_deref(), format, policy)
+                .await?
+                .with_progress(|event| match event {
+                    AnalysisEvent::BlockAnalyzed(lifecycle) => println!(
+                        "  Block {}: ✓ {} txs, {:.2} gwei base fee",
+                        lifecycle.block_number,
+                        lifecycle.transactions.total_count,
+                        lifecycle.gas.base_fee_gwei
+                    ),
+                    AnalysisEvent::BlockMissing(number) => {
+                        println!("  Block {}: ✗ Not found", number)
+                    }
+                    _ => {}
+                });
+            let analyzed = analyzer.analyze_range(start, end).await;
+            if analyzed.is_ok() {
+                println!("\n✓ Analysis complete!");
             }
+
+            finish_sink(&analyzer, output.as_deref(), format, analyzed).await?;
         }
-        Commands::Live { count, output } => {
-            analyzer.monitor_live(count, output).await?;
+        Commands::Live {
+            count,
+            output,
+            format,
+        } => {
+            println!("Monitoring live blocks...\n");
+
+            let analyzer = with_sink(analyzer, output.as_deref(), format, policy)
+                .await?
+                .with_progress(|event| match event {
+                    AnalysisEvent::Subscribed => println!("Subscribed to newHeads\n"),
+                    AnalysisEvent::SubscriptionClosed => {
+                        println!("⚠️ newHeads subscription closed, falling back to polling\n")
+                    }
+                    AnalysisEvent::SubscriptionFailed(err) => {
+                        println!(
+                            "⚠️ newHeads subscription failed ({:#}), polling instead\n",
+                            err
+                        )
+                    }
+                    AnalysisEvent::Reorg(reorg) => println!("{}", reorg),
+                    AnalysisEvent::BlockAnalyzed(lifecycle) => println!("{}", lifecycle),
+                    _ => {}
+                });
+
+            // Blocks are already on disk; Ctrl-C only needs to close the export
+            let monitored = tokio::select! {
+                result = analyzer.monitor_live(count) => result.map(drop),
+                _ = tokio::signal::ctrl_c() => {
+                    println!("\nInterrupted, finishing export...");
+                    Ok(())
+                }
+            };
+
+            finish_sink(&analyzer, output.as_deref(), format, monitored).await?;
+        }
+        Commands::Mev {
+            blocks,
+            threshold,
+            output,
+            format,
+        } => {
+            println!(
+                "Analyzing {} blocks for MEV (threshold: {} ETH)...\n",
+                blocks, threshold
+            );
+
+            let analyzer = with_sink(analyzer, output.as_deref(), format, policy)
+                .await?
+                .with_progress(|event| {
+                    if let AnalysisEvent::MevDetected(lifecycle) = event {
+                        print_mev_block(lifecycle);
+                    }
+                });
+            let detected = analyzer.detect_mev(blocks, threshold).await;
+
+            if let Ok(summary) = &detected {
+                println!("\n{}", "═══════════════════════════════════════".cyan());
+                println!("Blocks analyzed: {}", summary.blocks_analyzed);
+                println!("Blocks with MEV: {}", summary.blocks_flagged);
+                println!("Total MEV extracted: {:.4} ETH", summary.total_mev_eth);
+                println!(
+                    "Average MEV per block: {:.4} ETH",
+                    summary.average_mev_eth()
+                );
+                println!("{}", "═══════════════════════════════════════".cyan());
+            }
+
+            finish_sink(&analyzer, output.as_deref(), format, detected).await?;
+        }
+        Commands::Cache { .. } => unreachable!("handled before connecting"),
+    }
+
+    Ok(())
+}
+
+fn manage_cache(dir: &str, action: &CacheAction) -> Result<()> {
+    let cache = BlockCache::open(dir)?;
+
+    match action {
+        CacheAction::Stats => {
+            let stats = cache.stats();
+            println!("Cache directory: {}", cache.dir().display());
+            println!("Blocks cached: {}", stats.blocks);
+            println!("Size: {:.1} MiB", stats.bytes as f64 / MIB as f64);
+            if let (Some(first), Some(last)) = (stats.first_block, stats.last_block) {
+                println!("Block range: {} - {}", first, last);
+            }
         }
-        Commands::Mev { blocks, threshold } => {
-            analyzer.detect_mev(blocks, threshold).await?;
+        CacheAction::Prune { max_mb, below, all } => {
+            if !all && below.is_none() && max_mb.is_none() {
+                anyhow::bail!("Nothing to prune: pass --max-mb, --below or --all");
+            }
+
+            let mut removed = 0;
+            if *all {
+                removed += cache.clear()?;
+            }
+            if let Some(below) = below {
+                removed += cache.prune_below(*below)?;
+            }
+            if let Some(max_mb) = max_mb {
+                removed += cache.prune_to_size(max_mb * MIB)?;
+            }
+            println!("✓ Removed {} cached blocks", removed);
         }
     }
 
     Ok(())
 }
+
+/// Stream analyzed blocks to `output`, when given
+async fn with_sink<S: BlockSource>(
+    analyzer: BlockAnalyzer<S>,
+    output: Option<&str>,
+    format: Format,
+    policy: FlushPolicy,
+) -> Result<BlockAnalyzer<S>> {
+    let Some(path) = output else {
+        return Ok(analyzer);
+    };
+
+    let sink = match format {
+        Format::Csv => SinkWriter::spawn(FileSink::csv(path)?, policy),
+        Format::Parquet => SinkWriter::spawn(FileSink::parquet(path)?, policy),
+        Format::Json => SinkWriter::spawn(FileSink::json(path)?, policy),
+        Format::Jsonl => SinkWriter::spawn(FileSink::jsonl(path)?, policy),
+        Format::Sqlite => SinkWriter::spawn(SqliteStore::open(path)?, policy),
+        Format::Postgres => SinkWriter::spawn(PostgresStore::connect(path).await?, policy),
+    };
+    Ok(analyzer.with_sink(sink))
+}
+
+/// Write out whatever the sink still buffers and report what was exported.
+///
+/// Runs whether or not the analysis succeeded, so a failed run still leaves
+/// a complete export of the blocks before the failure; the analysis error
+/// takes precedence over the sink's.
+async fn finish_sink<S: BlockSource, T>(
+    analyzer: &BlockAnalyzer<S>,
+    output: Option<&str>,
+    format: Format,
+    analyzed: Result<T>,
+) -> Result<T> {
+    let (Some(sink), Some(path)) = (analyzer.sink(), output) else {
+        return analyzed;
+    };
+
+    let stats = match (sink.finish().await, &analyzed) {
+        (Ok(stats), _) => stats,
+        (Err(err), Ok(_)) => return Err(err),
+        (Err(err), Err(_)) => {
+            println!("\n⚠️ Export incomplete: {:#}", err);
+            return analyzed;
+        }
+    };
+    println!();
+    if stats.blocks == 0 {
+        println!("⚠️ No blocks collected. The export contains no rows.");
+    }
+    println!(
+        "✓ Exported {} blocks to {}",
+        stats.blocks,
+        postgres::redact_password(path)
+    );
+
+    if stats.reorgs > 0 {
+        // Databases have their own reorgs table
+        let reorgs_path = match format {
+            Format::Sqlite | Format::Postgres => postgres::redact_password(path),
+            _ => exporter::reorgs_path(path),
+        };
+        println!("✓ Exported {} reorgs to {}", stats.reorgs, reorgs_path);
+    }
+    analyzed
+}
+
+fn print_mev_block(lifecycle: &BlockLifecycle) {
+    println!(
+        "{} Block {}: {:.4} ETH MEV detected",
+        "s".yellow(),
+        lifecycle.block_number,
+        lifecycle.mev.estimated_mev_eth
+    );
+
+    if !lifecycle.mev.sandwich_attacks.is_empty() {
+        println!(
+            "   └─ {} sandwich attacks",
+            lifecycle.mev.sandwich_attacks.len()
+        );
+    }
+    if !lifecycle.mev.arbitrage_ops.is_empty() {
+        println!(
+            "   └─ {} arbitrage opportunities",
+            lifecycle.mev.arbitrage_ops.len()
+        );
+    }
+    if !lifecycle.mev.liquidations.is_empty() {
+        println!(
+            "   └─ {} liquidations",
+            lifecycle.mev.liquidations.len()
+        );
+    }
+}
+
+fn print_transaction_details(block: &Block<Transaction>, receipts: &[TransactionReceipt]) {
+    println!("\n{}", "TRANSACTION DETAILS".green().bold());
+    println!("{}", "─".repeat(50));
+
+    for (i, tx) in block.transactions.iter().take(10).enumerate() {
+        println!("\nTx #{}: {}", i + 1, format!("{:?}", tx.hash).yellow());
+        println!("  From: {:?}", tx.from);
+        println!("  To: {:?}", tx.to);
+        println!("  Value: {} ETH", wei_to_eth(tx.value));
+        if let Some(max_fee) = tx.max_fee_per_gas {
+            println!("  Max Fee: {} gwei", wei_to_gwei(max_fee));
+        }
+        if let Some(priority) = tx.max_priority_fee_per_gas {
+            println!("  Priority Fee: {} gwei", wei_to_gwei(priority));
+        }
+        if let Some(receipt) = receipts.iter().find(|r| r.transaction_hash == tx.hash) {
+            if let Some(gas_used) = receipt.gas_used {
+                println!("  Gas Used: {}", gas_used);
+            }
+            if let Some(price) = receipt.effective_gas_price {
+                println!("  Effective Gas Price: {} gwei", wei_to_gwei(price));
+            }
+            if receipt.status == Some(U64::zero()) {
+                println!("  Status: {}", "failed".red());
+            }
+        }
+    }
+
+    if block.transactions.len() > 10 {
+        println!(
+            "\n... and {} more transactions",
+            block.transactions.len() - 10
+        );
+    }
+}
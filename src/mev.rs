@@ -0,0 +1,468 @@
+use anyhow::{Result, bail};
+use ethers::types::{Address, Block, Log, Transaction, TransactionReceipt, U64};
+use std::collections::HashMap;
+
+use crate::dex::{Swap, decode_swaps, to_f64, weth};
+use crate::lending::decode_liquidations;
+use crate::types::{ArbitrageOp, DetectorFinding, Liquidation, SandwichAttack};
+
+/// Everything a detector can inspect about one block
+pub struct BlockContext<'a> {
+    pub block: &'a Block<Transaction>,
+    pub receipts: &'a [TransactionReceipt],
+
+    /// Uniswap V2/V3 swaps, decoded once for all detectors
+    pub swaps: Vec<Swap>,
+}
+
+impl<'a> BlockContext<'a> {
+    pub fn new(block: &'a Block<Transaction>, receipts: &'a [TransactionReceipt]) -> Self {
+        Self {
+            block,
+            receipts,
+            swaps: decode_swaps(&block.transactions, receipts),
+        }
+    }
+
+    /// Logs of the successful transactions, in block order
+    pub fn logs(&self) -> impl Iterator<Item = &'a Log> + 'a {
+        self.receipts
+            .iter()
+            .filter(|receipt| receipt.status != Some(U64::zero()))
+            .flat_map(|receipt| receipt.logs.iter())
+    }
+}
+
+/// Something a detector found in a block
+#[derive(Debug, Clone)]
+pub enum MevFinding {
+    Sandwich(SandwichAttack),
+    Arbitrage(ArbitrageOp),
+    Liquidation(Liquidation),
+
+    /// Findings outside the built-in categories
+    Other(DetectorFinding),
+}
+
+/// A heuristic run over every analyzed block
+pub trait MevDetector: Send + Sync {
+    /// Name used to enable, disable and configure the detector
+    fn name(&self) -> &str;
+
+    /// Apply a `key=value` option
+    fn configure(&mut self, key: &str, _value: &str) -> Result<()> {
+        bail!("Detector '{}' has no option '{}'", self.name(), key)
+    }
+
+    fn detect(&self, block: &BlockContext) -> Vec<MevFinding>;
+}
+
+struct RegisteredDetector {
+    detector: Box<dyn MevDetector>,
+    enabled: bool,
+}
+
+/// Ordered set of detectors, each of which can be switched off
+pub struct DetectorRegistry {
+    detectors: Vec<RegisteredDetector>,
+}
+
+impl Default for DetectorRegistry {
+    /// The built-in sandwich, arbitrage and liquidation detectors
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(SandwichDetector::default()));
+        registry.register(Box::new(ArbitrageDetector::default()));
+        registry.register(Box::new(LiquidationDetector::default()));
+        registry
+    }
+}
+
+impl DetectorRegistry {
+    /// Registry without any detectors
+    pub fn new() -> Self {
+        Self {
+            detectors: Vec::new(),
+        }
+    }
+
+    /// Add an enabled detector, replacing any registered under the same name
+    pub fn register(&mut self, detector: Box<dyn MevDetector>) {
+        self.detectors
+            .retain(|d| d.detector.name() != detector.name());
+        self.detectors.push(RegisteredDetector {
+            detector,
+            enabled: true,
+        });
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.detectors.iter().map(|d| d.detector.name()).collect()
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.detectors
+            .iter()
+            .any(|d| d.enabled && d.detector.name() == name)
+    }
+
+    pub fn enable(&mut self, name: &str) -> Result<()> {
+        self.find(name)?.enabled = true;
+        Ok(())
+    }
+
+    pub fn disable(&mut self, name: &str) -> Result<()> {
+        self.find(name)?.enabled = false;
+        Ok(())
+    }
+
+    pub fn configure(&mut self, name: &str, key: &str, value: &str) -> Result<()> {
+        self.find(name)?.detector.configure(key, value)
+    }
+
+    /// Apply an option written as `detector.key=value`
+    pub fn configure_from(&mut self, option: &str) -> Result<()> {
+        let Some((name, key, value)) = option
+            .split_once('=')
+            .and_then(|(path, value)| Some((path.split_once('.')?, value)))
+            .map(|((name, key), value)| (name, key, value))
+        else {
+            bail!(
+                "Detector option '{}' is not of the form detector.key=value",
+                option
+            );
+        };
+
+        self.configure(name.trim(), key.trim(), value.trim())
+    }
+
+    /// Run every enabled detector over the block, in registration order
+    pub fn detect(&self, block: &BlockContext) -> Vec<MevFinding> {
+        self.detectors
+            .iter()
+            .filter(|d| d.enabled)
+            .flat_map(|d| d.detector.detect(block))
+            .collect()
+    }
+
+    fn find(&mut self, name: &str) -> Result<&mut RegisteredDetector> {
+        let known = self.names().join(", ");
+        match self
+            .detectors
+            .iter_mut()
+            .find(|d| d.detector.name() == name)
+        {
+            Some(detector) => Ok(detector),
+            None => bail!("Unknown MEV detector '{}' (known: {})", name, known),
+        }
+    }
+}
+
+fn parse_profit(detector: &str, key: &str, value: &str) -> Result<f64> {
+    match value.parse() {
+        Ok(profit) => Ok(profit),
+        Err(_) => bail!(
+            "Invalid {}.{} '{}': expected ETH amount",
+            detector,
+            key,
+            value
+        ),
+    }
+}
+
+/// Built-in sandwich detector; `min_profit_eth` drops cheaper sandwiches
+#[derive(Debug, Clone, Default)]
+pub struct SandwichDetector {
+    pub min_profit_eth: Option<f64>,
+}
+
+impl MevDetector for SandwichDetector {
+    fn name(&self) -> &str {
+        "sandwich"
+    }
+
+    fn configure(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "min_profit_eth" => self.min_profit_eth = Some(parse_profit(self.name(), key, value)?),
+            _ => bail!("Detector '{}' has no option '{}'", self.name(), key),
+        }
+        Ok(())
+    }
+
+    fn detect(&self, block: &BlockContext) -> Vec<MevFinding> {
+        detect_sandwiches(&block.swaps)
+            .into_iter()
+            .filter(|s| {
+                self.min_profit_eth
+                    .is_none_or(|min| s.estimated_profit_eth >= min)
+            })
+            .map(MevFinding::Sandwich)
+            .collect()
+    }
+}
+
+/// Built-in atomic arbitrage detector; `min_profit_eth` drops smaller cycles
+#[derive(Debug, Clone, Default)]
+pub struct ArbitrageDetector {
+    pub min_profit_eth: Option<f64>,
+}
+
+impl MevDetector for ArbitrageDetector {
+    fn name(&self) -> &str {
+        "arbitrage"
+    }
+
+    fn configure(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "min_profit_eth" => self.min_profit_eth = Some(parse_profit(self.name(), key, value)?),
+            _ => bail!("Detector '{}' has no option '{}'", self.name(), key),
+        }
+        Ok(())
+    }
+
+    fn detect(&self, block: &BlockContext) -> Vec<MevFinding> {
+        detect_arbitrage(&block.swaps)
+            .into_iter()
+            .filter(|a| {
+                self.min_profit_eth
+                    .is_none_or(|min| a.estimated_profit_eth >= min)
+            })
+            .map(MevFinding::Arbitrage)
+            .collect()
+    }
+}
+
+/// Built-in liquidation detector; `protocols` (comma-separated, e.g.
+/// `aave,maker`) keeps only liquidations whose protocol starts with one of them
+#[derive(Debug, Clone, Default)]
+pub struct LiquidationDetector {
+    pub protocols: Option<Vec<String>>,
+}
+
+impl MevDetector for LiquidationDetector {
+    fn name(&self) -> &str {
+        "liquidation"
+    }
+
+    fn configure(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "protocols" => {
+                self.protocols = Some(
+                    value
+                        .split(',')
+                        .map(|p| p.trim().to_lowercase())
+                        .filter(|p| !p.is_empty())
+                        .collect(),
+                )
+            }
+            _ => bail!("Detector '{}' has no option '{}'", self.name(), key),
+        }
+        Ok(())
+    }
+
+    fn detect(&self, block: &BlockContext) -> Vec<MevFinding> {
+        decode_liquidations(&block.block.transactions, block.receipts)
+            .into_iter()
+            .filter(|l| {
+                let protocol = l.protocol.to_lowercase();
+                self.protocols
+                    .as_ref()
+                    .is_none_or(|wanted| wanted.iter().any(|p| protocol.starts_with(p.as_str())))
+            })
+            .map(MevFinding::Liquidation)
+            .collect()
+    }
+}
+
+/// Group swaps by pool, keeping block order within each pool and ordering
+/// pools by their first swap
+fn by_pool(swaps: &[Swap]) -> Vec<Vec<&Swap>> {
+    let mut index: HashMap<Address, usize> = HashMap::new();
+    let mut pools: Vec<Vec<&Swap>> = Vec::new();
+
+    for swap in swaps {
+        let i = *index.entry(swap.pool).or_insert_with(|| {
+            pools.push(Vec::new());
+            pools.len() - 1
+        });
+        pools[i].push(swap);
+    }
+
+    pools
+}
+
+/// Whether `a` and `b` come from the same searcher: the same sender, or the
+/// same bot contract as long as the victim didn't call it too (routers)
+fn same_attacker(a: &Swap, b: &Swap, victim: Option<&Swap>) -> bool {
+    if a.tx_from == b.tx_from {
+        return true;
+    }
+
+    a.tx_to.is_some() && a.tx_to == b.tx_to && victim.is_none_or(|v| v.tx_to != a.tx_to)
+}
+
+/// Detect sandwiches: an attacker swaps in a pool, a victim swaps in the same
+/// direction after it, then the attacker swaps back in the same pool.
+pub fn detect_sandwiches(swaps: &[Swap]) -> Vec<SandwichAttack> {
+    let mut sandwiches = Vec::new();
+
+    for pool in by_pool(swaps) {
+        let mut used = vec![false; pool.len()];
+
+        for f in 0..pool.len() {
+            if used[f] {
+                continue;
+            }
+            let front = pool[f];
+            let mut victim: Option<&Swap> = None;
+
+            for b in f + 1..pool.len() {
+                let swap = pool[b];
+                if swap.tx_index == front.tx_index || used[b] {
+                    continue;
+                }
+
+                if same_attacker(front, swap, victim) {
+                    if let Some(victim) = victim
+                        && swap.zero_for_one != front.zero_for_one
+                    {
+                        used[f] = true;
+                        used[b] = true;
+                        sandwiches.push(SandwichAttack {
+                            frontrun_tx: format!("{:?}", front.tx_hash),
+                            victim_tx: format!("{:?}", victim.tx_hash),
+                            backrun_tx: format!("{:?}", swap.tx_hash),
+                            estimated_profit_eth: sandwich_profit_eth(front, swap),
+                            dex: front.dex.name().to_string(),
+                            pool: format!("{:?}", front.pool),
+                            attacker: format!("{:?}", front.tx_from),
+                        });
+                        break;
+                    }
+                    continue;
+                }
+
+                if victim.is_none() && swap.zero_for_one == front.zero_for_one {
+                    victim = Some(swap);
+                }
+            }
+        }
+    }
+
+    sandwiches
+}
+
+/// Profit of the token the attacker started with (back-run output minus
+/// front-run input), in ETH. Only priced when one side of the pool is WETH.
+fn sandwich_profit_eth(front: &Swap, back: &Swap) -> f64 {
+    let profit = to_f64(back.amount_out) - to_f64(front.amount_in);
+    let weth = Some(weth());
+
+    let profit_wei = if front.token_in == weth {
+        profit
+    } else if front.token_out == weth && !back.amount_out.is_zero() {
+        // Price the start token at the back-run rate (WETH in per token out)
+        profit * to_f64(back.amount_in) / to_f64(back.amount_out)
+    } else {
+        return 0.0;
+    };
+
+    profit_wei / 1e18
+}
+
+/// Detect atomic arbitrage: consecutive swaps inside one transaction whose
+/// tokens chain into a cycle (A → … → A) and return more of A than went in.
+pub fn detect_arbitrage(swaps: &[Swap]) -> Vec<ArbitrageOp> {
+    let mut arbitrages = Vec::new();
+
+    for tx in swaps.chunk_by(|a, b| a.tx_index == b.tx_index) {
+        let mut start = 0;
+
+        while start < tx.len() {
+            let Some(cycle) = find_cycle(&tx[start..]) else {
+                start += 1;
+                continue;
+            };
+            let (first, last) = (cycle[0], cycle[cycle.len() - 1]);
+
+            if last.amount_out > first.amount_in {
+                let mut path: Vec<String> = cycle
+                    .iter()
+                    .filter_map(|swap| swap.token_in)
+                    .map(|token| format!("{:?}", token))
+                    .collect();
+                path.push(path[0].clone());
+
+                let mut dexes_involved: Vec<String> = Vec::new();
+                for swap in &cycle {
+                    let name = swap.dex.name().to_string();
+                    if !dexes_involved.contains(&name) {
+                        dexes_involved.push(name);
+                    }
+                }
+
+                arbitrages.push(ArbitrageOp {
+                    tx_hash: format!("{:?}", first.tx_hash),
+                    path,
+                    estimated_profit_eth: arbitrage_profit_eth(&cycle, tx),
+                    dexes_involved,
+                });
+            }
+
+            start += cycle.len();
+        }
+    }
+
+    arbitrages
+}
+
+/// Shortest run of swaps from the first one where each swap spends the
+/// previous output and the last one returns the first input token
+fn find_cycle(swaps: &[Swap]) -> Option<Vec<&Swap>> {
+    let start_token = swaps.first()?.token_in?;
+    let mut expected = Some(start_token);
+    let mut cycle = Vec::new();
+
+    for swap in swaps {
+        if swap.token_in.is_none() || swap.token_in != expected {
+            return None;
+        }
+        cycle.push(swap);
+
+        if cycle.len() >= 2 && swap.token_out == Some(start_token) {
+            return Some(cycle);
+        }
+        expected = swap.token_out;
+    }
+
+    None
+}
+
+/// Cycle profit in ETH. Profits in another token are priced at the rate of a
+/// swap between that token and WETH in the same transaction, if there is one.
+fn arbitrage_profit_eth(cycle: &[&Swap], tx: &[Swap]) -> f64 {
+    let first = cycle[0];
+    let last = cycle[cycle.len() - 1];
+    let profit = to_f64(last.amount_out) - to_f64(first.amount_in);
+
+    let weth = Some(weth());
+    let token = first.token_in;
+
+    let profit_wei = if token == weth {
+        profit
+    } else if let Some(swap) = tx
+        .iter()
+        .find(|s| s.token_in == token && s.token_out == weth)
+    {
+        profit * to_f64(swap.amount_out) / to_f64(swap.amount_in)
+    } else if let Some(swap) = tx
+        .iter()
+        .find(|s| s.token_in == weth && s.token_out == token)
+    {
+        profit * to_f64(swap.amount_in) / to_f64(swap.amount_out)
+    } else {
+        return 0.0;
+    };
+
+    profit_wei / 1e18
+}
@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+/// Number of recent block hashes kept for reorg detection
+pub const DEFAULT_REORG_WINDOW: usize = 64;
+
+/// Sliding window of the most recent canonical block hashes
+#[derive(Debug, Clone)]
+pub struct ReorgTracker {
+    hashes: BTreeMap<u64, String>,
+    capacity: usize,
+}
+
+impl Default for ReorgTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_REORG_WINDOW)
+    }
+}
+
+impl ReorgTracker {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            hashes: BTreeMap::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Stored hash at `number`, if it is still inside the window
+    pub fn hash_at(&self, number: u64) -> Option<&str> {
+        self.hashes.get(&number).map(String::as_str)
+    }
+
+    /// Whether a block at `number` with `parent_hash` contradicts the stored
+    /// chain. Blocks whose parent is outside the window never do.
+    pub fn is_reorg(&self, number: u64, parent_hash: &str) -> bool {
+        number
+            .checked_sub(1)
+            .and_then(|parent| self.hash_at(parent))
+            .is_some_and(|stored| !stored.eq_ignore_ascii_case(parent_hash))
+    }
+
+    /// Record `hash` as the canonical block at `number`.
+    ///
+    /// Anything stored above `number` belonged to a chain that no longer
+    /// leads here and is dropped.
+    pub fn insert(&mut self, number: u64, hash: String) {
+        self.hashes.split_off(&number);
+        self.hashes.insert(number, hash);
+
+        while self.hashes.len() > self.capacity {
+            self.hashes.pop_first();
+        }
+    }
+
+    /// Lowest block number still tracked
+    pub fn oldest(&self) -> Option<u64> {
+        self.hashes.keys().next().copied()
+    }
+}
@@ -0,0 +1,167 @@
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Exponential backoff settings for retrying failed RPC calls
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+
+    /// Upper bound for any single delay, including `Retry-After`
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (0-based), with jitter.
+    ///
+    /// The exponential delay is split in half: one half is always waited, the
+    /// other is randomized so concurrent requests don't retry in lockstep.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+
+        let half = exponential / 2;
+        let jitter = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter)
+    }
+
+    /// Delay requested by the server, capped at `max_backoff`
+    pub fn retry_after(&self, headers: &HeaderMap) -> Option<Duration> {
+        let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+        let delay = match value.parse::<u64>() {
+            Ok(seconds) => Duration::from_secs(seconds),
+            Err(_) => {
+                let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+                (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                    .to_std()
+                    .unwrap_or_default()
+            }
+        };
+
+        Some(delay.min(self.max_backoff))
+    }
+}
+
+/// Client-side request budget, shared by every call made through a transport
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    /// Maximum JSON-RPC calls per second (each batch entry counts)
+    pub requests_per_second: Option<f64>,
+
+    /// Maximum provider compute units per second (see [`compute_units`])
+    pub compute_units_per_second: Option<f64>,
+}
+
+/// Token bucket allowing bursts of up to one second's worth of budget
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take `amount` tokens and return how long the caller must wait for them.
+    /// The balance may go negative so later callers queue up behind this one.
+    fn reserve(&mut self, amount: f64) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated = now;
+
+        self.tokens -= amount;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    requests: Option<Mutex<Bucket>>,
+    compute_units: Option<Mutex<Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: &RateLimit) -> Self {
+        let bucket = |rate: Option<f64>| rate.filter(|r| *r > 0.0).map(|r| Mutex::new(Bucket::new(r)));
+
+        Self {
+            requests: bucket(limit.requests_per_second),
+            compute_units: bucket(limit.compute_units_per_second),
+        }
+    }
+
+    /// Wait until the budget allows `methods` to be sent
+    pub async fn acquire(&self, methods: &[&str]) {
+        let delay = self.reserve(methods);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn reserve(&self, methods: &[&str]) -> Duration {
+        let mut delay = Duration::ZERO;
+
+        if let Some(bucket) = &self.requests {
+            let wait = bucket.lock().unwrap().reserve(methods.len() as f64);
+            delay = delay.max(wait);
+        }
+
+        if let Some(bucket) = &self.compute_units {
+            let units: u64 = methods.iter().map(|m| compute_units(m)).sum();
+            let wait = bucket.lock().unwrap().reserve(units as f64);
+            delay = delay.max(wait);
+        }
+
+        delay
+    }
+}
+
+/// Approximate compute-unit cost of a JSON-RPC method, following Alchemy's pricing
+pub fn compute_units(method: &str) -> u64 {
+    match method {
+        "eth_chainId" | "net_version" => 0,
+        "eth_blockNumber" => 10,
+        "eth_getTransactionReceipt" => 15,
+        "eth_getBlockByNumber" | "eth_getBlockByHash" => 16,
+        "eth_call" => 26,
+        "eth_getLogs" => 75,
+        "trace_block" | "debug_traceBlockByNumber" => 500,
+        "eth_getBlockReceipts" => 500,
+        _ => 20,
+    }
+}
+
+/// JSON-RPC error codes providers use to signal rate limiting
+pub fn is_rate_limit_code(code: i64) -> bool {
+    matches!(code, 429 | -32005 | -32029 | -32090)
+}
@@ -1,30 +1,205 @@
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use ethers::{
-    providers::{Http, Middleware, Provider},
-    types::{Block, Transaction, H256, U256},
+    providers::{Ipc, JsonRpcError, Middleware, Provider, Ws},
+    types::{Block, BlockNumber, Trace, Transaction, TransactionReceipt, H256, U256},
 };
+use futures::future::join_all;
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::fixtures::{FixtureMode, RpcFixtures};
+use crate::retry::{RateLimit, RetryPolicy};
+use crate::source::{BlockSource, BlockStream, FINALITY_DEPTH, FetchedBlock, HeadStream};
+use crate::transport::{HttpTransport, TransportError, endpoint_label};
+use crate::types::ProviderDisagreement;
+
+/// Default number of calls packed into one JSON-RPC batch
+pub const DEFAULT_BATCH_SIZE: usize = 50;
+
+/// Default number of blocks the active endpoint may trail the others by
+pub const DEFAULT_MAX_LAG: u64 = 3;
+
+/// Connection settings for [`EthereumRpcClient`]
+#[derive(Debug, Clone)]
+pub struct RpcConfig {
+    /// Number of calls sent per JSON-RPC batch
+    pub batch_size: usize,
+
+    /// Backoff applied to rate-limited and failed requests
+    pub retry: RetryPolicy,
+
+    /// Client-side request budget
+    pub rate_limit: RateLimit,
+
+    /// Cross-check every fetched block hash against the other endpoints
+    pub quorum: bool,
+
+    /// Switch endpoints when the active one trails the best head by more blocks than this
+    pub max_lag: u64,
+
+    /// WebSocket URL (`ws://`, `wss://`) or IPC socket path for subscriptions
+    pub pubsub_url: Option<String>,
+
+    /// Record every JSON-RPC call to, or replay them from, a fixture directory
+    pub fixtures: Option<RpcFixtures>,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            retry: RetryPolicy::default(),
+            rate_limit: RateLimit::default(),
+            quorum: false,
+            max_lag: DEFAULT_MAX_LAG,
+            pubsub_url: None,
+            fixtures: None,
+        }
+    }
+}
+
+/// Push-capable connection used for subscriptions
+enum PubSub {
+    Ws(Provider<Ws>),
+    Ipc(Provider<Ipc>),
+}
+
+impl PubSub {
+    async fn connect(url: &str) -> Result<Self> {
+        if url.starts_with("ws://") || url.starts_with("wss://") {
+            let ws = Ws::connect(url)
+                .await
+                .context("Failed to connect to WebSocket endpoint")?;
+            Ok(PubSub::Ws(Provider::new(ws)))
+        } else {
+            let ipc = Provider::connect_ipc(url)
+                .await
+                .with_context(|| format!("Failed to connect to IPC socket {}", url))?;
+            Ok(PubSub::Ipc(ipc))
+        }
+    }
+}
 
 pub struct EthereumRpcClient {
-    provider: Arc<Provider<Http>>,
+    provider: Arc<Provider<HttpTransport>>,
+    pubsub: Option<PubSub>,
+
+    /// Why the configured pubsub endpoint couldn't be connected, reported
+    /// when subscribing so callers can fall back to polling
+    pubsub_error: Option<String>,
+    batch_size: usize,
+    quorum: bool,
+    max_lag: u64,
+
+    /// Cleared once the node rejects `eth_getBlockReceipts`
+    block_receipts_supported: AtomicBool,
 }
 
 impl EthereumRpcClient {
     pub async fn new(rpc_url: &str) -> Result<Self> {
-        let provider = Provider::<Http>::try_from(rpc_url)
-            .context("Failed to create provider")?;
+        Self::with_config(&[rpc_url.to_string()], RpcConfig::default()).await
+    }
+
+    /// Connect to one or more endpoints. The first URL is used until it fails
+    /// or falls behind, then calls fail over to the next one.
+    pub async fn with_config(rpc_urls: &[String], config: RpcConfig) -> Result<Self> {
+        anyhow::ensure!(!rpc_urls.is_empty(), "No RPC URL given");
+
+        let urls = rpc_urls
+            .iter()
+            .map(|url| Url::parse(url).with_context(|| format!("Invalid RPC URL: {}", url)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut transport = HttpTransport::with_endpoints(urls)
+            .with_retry_policy(config.retry)
+            .with_rate_limit(&config.rate_limit);
+
+        // Replays never touch the network, subscriptions included
+        let replaying = config
+            .fixtures
+            .as_ref()
+            .is_some_and(|fixtures| fixtures.mode() == FixtureMode::Replay);
+        if let Some(fixtures) = config.fixtures {
+            transport = transport.with_fixtures(fixtures);
+        }
+        let provider = Provider::new(transport);
         
         // Test connection
         provider
             .get_block_number()
             .await
             .context("Failed to connect to Ethereum node")?;
+
+        let (pubsub, pubsub_error) = match &config.pubsub_url {
+            Some(url) if !replaying => match PubSub::connect(url).await {
+                Ok(pubsub) => (Some(pubsub), None),
+                Err(err) => (None, Some(format!("{:#}", err))),
+            },
+            _ => (None, None),
+        };
         
         Ok(Self {
             provider: Arc::new(provider),
+            pubsub,
+            pubsub_error,
+            batch_size: config.batch_size.max(1),
+            quorum: config.quorum,
+            max_lag: config.max_lag,
+            block_receipts_supported: AtomicBool::new(true),
         })
     }
 
+    /// Subscribe to new block headers. Returns `None` when no WebSocket or IPC
+    /// endpoint is configured, in which case callers should poll instead, and
+    /// an error if the configured endpoint couldn't be connected.
+    pub async fn subscribe_new_heads(&self) -> Result<Option<HeadStream<'_>>> {
+        if let Some(err) = &self.pubsub_error {
+            anyhow::bail!("{}", err);
+        }
+
+        let heads: HeadStream<'_> = match &self.pubsub {
+            None => return Ok(None),
+            Some(PubSub::Ws(provider)) => Box::pin(
+                provider
+                    .subscribe_blocks()
+                    .await
+                    .context("Failed to subscribe to newHeads")?,
+            ),
+            Some(PubSub::Ipc(provider)) => Box::pin(
+                provider
+                    .subscribe_blocks()
+                    .await
+                    .context("Failed to subscribe to newHeads")?,
+            ),
+        };
+
+        Ok(Some(heads))
+    }
+
+    /// Whether fetched blocks are cross-checked against the other endpoints
+    pub fn quorum_enabled(&self) -> bool {
+        self.quorum
+    }
+
+    fn transport(&self) -> &HttpTransport {
+        (*self.provider).as_ref()
+    }
+
+    /// Host names of the configured endpoints, in failover order
+    pub fn endpoint_labels(&self) -> Vec<String> {
+        self.transport().endpoints().iter().map(endpoint_label).collect()
+    }
+
+    /// Label of the endpoint currently serving requests
+    pub fn active_endpoint(&self) -> String {
+        endpoint_label(&self.transport().endpoints()[self.transport().active_endpoint()])
+    }
+
     /// Fetch a block by number or latest
     pub async fn get_block(&self, block_id: &str) -> Result<Option<Block<Transaction>>> {
         let block = if block_id == "latest" {
@@ -43,22 +218,308 @@ impl EthereumRpcClient {
         Ok(block)
     }
 
-    #[allow(dead_code)]
+    /// Fetch a block by number
+    pub async fn get_block_by_number(&self, block_number: u64) -> Result<Option<Block<Transaction>>> {
+        self.provider
+            .get_block_with_txs(block_number)
+            .await
+            .context(format!("Failed to fetch block {}", block_number))
+    }
+
+    /// Fetch the hash of the canonical block at `block_number`, without transactions
+    pub async fn get_block_hash(&self, block_number: u64) -> Result<Option<H256>> {
+        let block = self
+            .provider
+            .get_block(block_number)
+            .await
+            .context(format!("Failed to fetch block header {}", block_number))?;
+
+        Ok(block.and_then(|b| b.hash))
+    }
+
+    /// Fetch the `trace_block` call traces of a block (Erigon, Nethermind,
+    /// Reth or a provider exposing the `trace` namespace)
+    pub async fn get_block_traces(&self, block_number: u64) -> Result<Vec<Trace>> {
+        self.provider
+            .trace_block(BlockNumber::Number(block_number.into()))
+            .await
+            .context(format!("Failed to fetch traces of block {}", block_number))
+    }
+
+    /// Fetch blocks by number using batched requests, one result per number
+    pub async fn get_blocks_batch(
+        &self,
+        block_numbers: &[u64],
+    ) -> Result<Vec<Option<Block<Transaction>>>> {
+        let calls: Vec<_> = block_numbers
+            .iter()
+            .map(|n| ("eth_getBlockByNumber", json!([format!("0x{:x}", n), true])))
+            .collect();
+
+        self.batch(&calls).await.with_context(|| match block_numbers {
+            [single] => format!("Failed to fetch block {}", single),
+            [first, .., last] => format!("Failed to fetch blocks {}..={}", first, last),
+            [] => "Failed to fetch blocks".to_string(),
+        })
+    }
+
+    /// Fetch transaction receipts using batched requests, one result per hash
+    pub async fn get_receipts_batch(
+        &self,
+        tx_hashes: &[H256],
+    ) -> Result<Vec<Option<TransactionReceipt>>> {
+        let calls: Vec<_> = tx_hashes
+            .iter()
+            .map(|hash| ("eth_getTransactionReceipt", json!([hash])))
+            .collect();
+
+        self.batch(&calls)
+            .await
+            .context("Failed to fetch transaction receipts")
+    }
+
+    /// Fetch the receipts of every transaction in `block`, in block order
+    pub async fn get_block_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<TransactionReceipt>> {
+        let mut receipts = self.get_receipts_for_blocks(&[block]).await?;
+        Ok(receipts.pop().unwrap_or_default())
+    }
+
+    /// Fetch receipts for several blocks.
+    ///
+    /// Uses one batched `eth_getBlockReceipts` call per block when the node
+    /// supports it and falls back to per-transaction receipts otherwise, for
+    /// blocks it can't answer for, or whose receipts don't line up with their
+    /// transactions. Fails if a transaction has no receipt.
+    async fn get_receipts_for_blocks(
+        &self,
+        blocks: &[&Block<Transaction>],
+    ) -> Result<Vec<Vec<TransactionReceipt>>> {
+        let mut receipts: Vec<Option<Vec<TransactionReceipt>>> = vec![None; blocks.len()];
+
+        let pending: Vec<usize> = (0..blocks.len())
+            .filter(|&i| !blocks[i].transactions.is_empty())
+            .collect();
+
+        if !pending.is_empty() && self.block_receipts_supported.load(Ordering::Relaxed) {
+            let calls: Vec<_> = pending
+                .iter()
+                .map(|&i| {
+                    let id = match blocks[i].hash {
+                        Some(hash) => json!(hash),
+                        None => json!(format!("0x{:x}", blocks[i].number.unwrap_or_default())),
+                    };
+                    ("eth_getBlockReceipts", json!([id]))
+                })
+                .collect();
+
+            match self.batch::<Option<Vec<TransactionReceipt>>>(&calls).await {
+                Ok(results) => {
+                    for (&i, result) in pending.iter().zip(results) {
+                        receipts[i] = result.filter(|r| r.len() == blocks[i].transactions.len());
+                    }
+                }
+                Err(err) => match err.downcast_ref() {
+                    // The node doesn't serve the method; don't ask again
+                    Some(TransportError::JsonRpc(rpc_err))
+                        if is_method_unsupported(rpc_err, "eth_getBlockReceipts") =>
+                    {
+                        self.block_receipts_supported.store(false, Ordering::Relaxed);
+                    }
+                    // Any other answer (e.g. an unknown block) only affects this
+                    // call; its blocks fall back to transaction receipts
+                    Some(TransportError::JsonRpc(_)) => {}
+                    _ => return Err(err.context("Failed to fetch block receipts")),
+                },
+            }
+        }
+
+        let mut all = Vec::with_capacity(blocks.len());
+        for (block, receipts) in blocks.iter().zip(receipts) {
+            let receipts = match receipts {
+                Some(receipts) => receipts,
+                None if block.transactions.is_empty() => Vec::new(),
+                None => {
+                    let hashes: Vec<H256> = block.transactions.iter().map(|tx| tx.hash).collect();
+                    self.get_receipts_batch(&hashes)
+                        .await?
+                        .into_iter()
+                        .collect::<Option<Vec<_>>>()
+                        .with_context(|| {
+                            format!(
+                                "Node returned no receipt for a transaction in block {}",
+                                block.number.unwrap_or_default()
+                            )
+                        })?
+                }
+            };
+            all.push(receipts);
+        }
+
+        Ok(all)
+    }
+
+    /// Split `calls` into batches of `batch_size` and decode every result
+    async fn batch<R: DeserializeOwned>(&self, calls: &[(&str, Value)]) -> Result<Vec<R>> {
+        let mut results = Vec::with_capacity(calls.len());
+
+        for chunk in calls.chunks(self.batch_size) {
+            for value in self.transport().batch_request(chunk).await? {
+                results.push(serde_json::from_value(value)?);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Fetch blocks concurrently with at most `max_in_flight` batches outstanding.
+    /// Blocks are yielded in the same order as `block_numbers`.
+    pub fn stream_blocks<I>(
+        &self,
+        block_numbers: I,
+        max_in_flight: usize,
+    ) -> impl Stream<Item = Result<FetchedBlock>> + '_
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        let block_numbers: Vec<u64> = block_numbers.into_iter().collect();
+        let batches: Vec<Vec<u64>> = block_numbers
+            .chunks(self.batch_size)
+            .map(<[u64]>::to_vec)
+            .collect();
+
+        stream::iter(batches)
+            .map(move |batch| async move { stream::iter(self.fetch_blocks(batch).await) })
+            .buffered(max_in_flight.max(1))
+            .flatten()
+    }
+
+    /// Fetch one batch of blocks. If the batch fails even after retries, the
+    /// blocks are requested one by one so the error names the exact block.
+    async fn fetch_blocks(&self, batch: Vec<u64>) -> Vec<Result<FetchedBlock>> {
+        let blocks = match self.get_blocks_batch(&batch).await {
+            Ok(blocks) => blocks,
+            Err(_) if batch.len() > 1 => {
+                let mut blocks = Vec::with_capacity(batch.len());
+                for &block_num in &batch {
+                    match self.get_block_by_number(block_num).await {
+                        Ok(block) => blocks.push(block),
+                        Err(err) => {
+                            let mut fetched = self.finish_batch(&batch, blocks).await;
+                            fetched.push(Err(err));
+                            return fetched;
+                        }
+                    }
+                }
+                blocks
+            }
+            Err(err) => return vec![Err(err)],
+        };
+
+        self.finish_batch(&batch, blocks).await
+    }
+
+    /// Pair fetched blocks with their numbers and receipts and, in quorum mode,
+    /// cross-check them
+    async fn finish_batch(
+        &self,
+        numbers: &[u64],
+        blocks: Vec<Option<Block<Transaction>>>,
+    ) -> Vec<Result<FetchedBlock>> {
+        let mut disagreements = if self.quorum {
+            let hashes: Vec<(u64, H256)> = numbers
+                .iter()
+                .zip(&blocks)
+                .filter_map(|(n, block)| Some((*n, block.as_ref()?.hash?)))
+                .collect();
+            self.cross_check(&hashes).await
+        } else {
+            HashMap::new()
+        };
+
+        let found: Vec<&Block<Transaction>> = blocks.iter().flatten().collect();
+        let mut receipts = match self.get_receipts_for_blocks(&found).await {
+            Ok(receipts) => receipts.into_iter(),
+            Err(err) => return vec![Err(err)],
+        };
+
+        numbers
+            .iter()
+            .zip(blocks)
+            .map(|(&number, block)| {
+                let receipts = match &block {
+                    Some(_) => receipts.next().unwrap_or_default(),
+                    None => Vec::new(),
+                };
+                Ok(FetchedBlock {
+                    number,
+                    block,
+                    receipts,
+                    disagreements: disagreements.remove(&number).unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// Ask every non-active endpoint for the given blocks and report the ones
+    /// whose hash differs. Endpoints that fail or don't have a block yet are
+    /// skipped: lag is not a disagreement.
+    pub async fn cross_check(
+        &self,
+        blocks: &[(u64, H256)],
+    ) -> HashMap<u64, Vec<ProviderDisagreement>> {
+        let transport = self.transport();
+        let active = transport.active_endpoint();
+        let calls: Vec<_> = blocks
+            .iter()
+            .map(|(n, _)| ("eth_getBlockByNumber", json!([format!("0x{:x}", n), false])))
+            .collect();
+
+        let others = (0..transport.endpoints().len()).filter(|&i| i != active);
+        let answers = join_all(others.map(|i| {
+            let calls = &calls;
+            async move { (i, transport.batch_request_to(i, calls).await) }
+        }))
+        .await;
+
+        let mut disagreements: HashMap<u64, Vec<ProviderDisagreement>> = HashMap::new();
+
+        for (endpoint, answer) in answers {
+            let Ok(values) = answer else { continue };
+
+            for ((number, expected), value) in blocks.iter().zip(values) {
+                let reported = serde_json::from_value::<Option<Block<H256>>>(value)
+                    .ok()
+                    .flatten()
+                    .and_then(|block| block.hash);
+
+                if let Some(reported) = reported.filter(|hash| hash != expected) {
+                    disagreements.entry(*number).or_default().push(ProviderDisagreement {
+                        endpoint: endpoint_label(&transport.endpoints()[endpoint]),
+                        expected_hash: format!("{:?}", expected),
+                        reported_hash: format!("{:?}", reported),
+                    });
+                }
+            }
+        }
+
+        disagreements
+    }
+
     /// Fetch multiple blocks in a range
     pub async fn get_blocks_range(
         &self,
         start: u64,
         end: u64,
+        max_in_flight: usize,
     ) -> Result<Vec<Block<Transaction>>> {
         let mut blocks = Vec::new();
+        let mut fetched = self.stream_blocks(start..=end, max_in_flight);
 
-        for block_num in start..=end {
-            if let Some(block) = self
-                .provider
-                .get_block_with_txs(block_num)
-                .await
-                .context(format!("Failed to fetch block {}", block_num))?
-            {
+        while let Some(result) = fetched.next().await {
+            if let Some(block) = result?.block {
                 blocks.push(block);
             }
         }
@@ -66,27 +527,69 @@ impl EthereumRpcClient {
         Ok(blocks)
     }
 
-    /// Get the latest block number
+    /// Get the latest block number.
+    ///
+    /// With several endpoints, every endpoint is asked and the active one is
+    /// switched to the most advanced if it trails by more than `max_lag` blocks.
     pub async fn get_latest_block_number(&self) -> Result<u64> {
-        Ok(self
-            .provider
-            .get_block_number()
-            .await
-            .context("Failed to get latest block number")?
-            .as_u64())
+        let transport = self.transport();
+        if transport.endpoints().len() == 1 {
+            return Ok(self
+                .provider
+                .get_block_number()
+                .await
+                .context("Failed to get latest block number")?
+                .as_u64());
+        }
+
+        let heads = join_all((0..transport.endpoints().len()).map(|i| async move {
+            let head: Result<ethers::types::U64, _> =
+                transport.request_to(i, "eth_blockNumber", ()).await;
+            head.ok().map(|head| (i, head.as_u64()))
+        }))
+        .await;
+
+        let heads: HashMap<usize, u64> = heads.into_iter().flatten().collect();
+        let (&best_endpoint, &best_head) = heads
+            .iter()
+            .max_by_key(|(i, head)| (**head, std::cmp::Reverse(**i)))
+            .context("Failed to get latest block number from any endpoint")?;
+
+        let active = transport.active_endpoint();
+        let active_head = heads.get(&active).copied();
+        if active_head.is_none_or(|head| head + self.max_lag < best_head) {
+            transport.set_active_endpoint(best_endpoint);
+            return Ok(best_head);
+        }
+
+        Ok(active_head.unwrap_or(best_head))
+    }
+
+    /// Get the number of the latest finalized block, falling back to
+    /// `FINALITY_DEPTH` blocks below the head on nodes without the
+    /// `finalized` tag
+    pub async fn get_finalized_block_number(&self) -> Result<u64> {
+        match self.provider.get_block(BlockNumber::Finalized).await {
+            Ok(Some(Block {
+                number: Some(number),
+                ..
+            })) => Ok(number.as_u64()),
+            _ => Ok(self
+                .get_latest_block_number()
+                .await?
+                .saturating_sub(FINALITY_DEPTH)),
+        }
     }
 
-    #[allow(dead_code)]
     /// Get transaction receipt for detailed gas analysis
     pub async fn get_transaction_receipt(
         &self,
         tx_hash: H256,
-    ) -> Result<Option<ethers::types::TransactionReceipt>> {
-        Ok(self
-            .provider
+    ) -> Result<Option<TransactionReceipt>> {
+        self.provider
             .get_transaction_receipt(tx_hash)
             .await
-            .context("Failed to fetch transaction receipt")?)
+            .context("Failed to fetch transaction receipt")
     }
 
     /// Get previous block for timing comparison
@@ -95,25 +598,68 @@ impl EthereumRpcClient {
             return Ok(None);
         }
 
-        Ok(self
-            .provider
+        self.provider
             .get_block_with_txs(current - 1)
             .await
-            .context("Failed to fetch previous block")?)
+            .context("Failed to fetch previous block")
+    }
+}
+
+#[async_trait]
+impl BlockSource for EthereumRpcClient {
+    async fn get_block(&self, number: u64) -> Result<Option<Block<Transaction>>> {
+        self.get_block_by_number(number).await
+    }
+
+    async fn get_block_receipts(&self, block: &Block<Transaction>) -> Result<Vec<TransactionReceipt>> {
+        EthereumRpcClient::get_block_receipts(self, block).await
+    }
+
+    async fn get_block_traces(&self, number: u64) -> Result<Vec<Trace>> {
+        EthereumRpcClient::get_block_traces(self, number).await
     }
 
-    /// Estimate if address is a known MEV bot
-    pub fn is_known_mev_bot(&self, address: &str) -> bool {
-        // Known MEV bot addresses (partial list for demonstration)
-        let known_bots = vec![
-            "0x0000000000007f150bd6f54c40a34d7c3d5e9f56", // MEV Bot
-            "0xa57bd00134b2850b2a1c55860c9e9ea100fdd6cf", // MEV Bot
-            "0x00000000003b3cc22af3ae1eac0440bcee416b40", // MEV Bot
-            // Add more known addresses
-        ];
+    async fn get_latest_block_number(&self) -> Result<u64> {
+        EthereumRpcClient::get_latest_block_number(self).await
+    }
 
-        known_bots.contains(&address.to_lowercase().as_str())
+    async fn get_finalized_block_number(&self) -> Result<u64> {
+        EthereumRpcClient::get_finalized_block_number(self).await
     }
+
+    async fn get_block_hash(&self, number: u64) -> Result<Option<H256>> {
+        EthereumRpcClient::get_block_hash(self, number).await
+    }
+
+    async fn subscribe_new_heads(&self) -> Result<Option<HeadStream<'_>>> {
+        EthereumRpcClient::subscribe_new_heads(self).await
+    }
+
+    fn quorum_enabled(&self) -> bool {
+        self.quorum
+    }
+
+    async fn cross_check(&self, blocks: &[(u64, H256)]) -> HashMap<u64, Vec<ProviderDisagreement>> {
+        EthereumRpcClient::cross_check(self, blocks).await
+    }
+
+    fn stream_blocks(&self, numbers: Vec<u64>, max_in_flight: usize) -> BlockStream<'_> {
+        Box::pin(EthereumRpcClient::stream_blocks(self, numbers, max_in_flight))
+    }
+}
+
+/// Whether a JSON-RPC error means the node doesn't serve `method` at all, as
+/// opposed to failing this particular call (e.g. "header not found" or
+/// "state not available")
+fn is_method_unsupported(err: &JsonRpcError, method: &str) -> bool {
+    let message = err.message.to_lowercase();
+    let names_method =
+        message.contains("method not found") || message.contains(&method.to_lowercase());
+    err.code == -32601
+        || (names_method
+            && ["not found", "not supported", "unsupported", "does not exist", "not available"]
+                .iter()
+                .any(|phrase| message.contains(phrase)))
 }
 
 /// Helper function to convert U256 to f64 ETH
@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::types::{BlockLifecycle, ReorgEvent};
+
+/// Destination that analyzed blocks are streamed to, e.g. an export file or
+/// a database
+#[async_trait]
+pub trait Sink: Send {
+    /// Write a batch of blocks and make it durable
+    async fn write_blocks(&mut self, blocks: &[BlockLifecycle]) -> Result<()>;
+
+    /// Record reorgs found while monitoring live
+    async fn write_reorgs(&mut self, reorgs: &[ReorgEvent]) -> Result<()>;
+
+    /// Complete the output once nothing more follows (e.g. close a JSON
+    /// array or write a Parquet footer)
+    async fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// When blocks buffered by a [`SinkWriter`] are written to its sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushPolicy {
+    /// Write once this many blocks are buffered
+    pub max_blocks: usize,
+
+    /// Write buffered blocks at the latest this long after the first of them
+    /// arrived, however few there are
+    pub interval: Option<Duration>,
+}
+
+impl Default for FlushPolicy {
+    /// Write every block as soon as it's analyzed
+    fn default() -> Self {
+        Self {
+            max_blocks: 1,
+            interval: None,
+        }
+    }
+}
+
+/// What a [`SinkWriter`] wrote before it finished
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SinkStats {
+    pub blocks: usize,
+    pub reorgs: usize,
+}
+
+enum Record {
+    Block(Box<BlockLifecycle>),
+    Reorg(ReorgEvent),
+    Finish,
+}
+
+/// Feeds a [`Sink`] from a background task, so blocks can be handed over
+/// from synchronous code (such as progress callbacks) without waiting on I/O.
+///
+/// Blocks are buffered and written per [`FlushPolicy`]; a reorg first
+/// writes the blocks before it. The first write error stops the writer:
+/// queueing anything afterwards fails, and [`finish`](Self::finish) returns
+/// the error itself.
+pub struct SinkWriter {
+    records: mpsc::UnboundedSender<Record>,
+    task: Mutex<Option<JoinHandle<Result<SinkStats>>>>,
+
+    /// The write error that stopped the background task
+    failure: Arc<OnceLock<String>>,
+}
+
+impl SinkWriter {
+    pub fn spawn<K: Sink + 'static>(sink: K, policy: FlushPolicy) -> Self {
+        let (records, receiver) = mpsc::unbounded_channel();
+        let failure = Arc::new(OnceLock::new());
+        let stopped = failure.clone();
+        let task = tokio::spawn(async move {
+            let result = run(Box::new(sink), policy, receiver).await;
+            if let Err(err) = &result {
+                let _ = stopped.set(format!("{:#}", err));
+            }
+            result
+        });
+
+        Self {
+            records,
+            task: Mutex::new(Some(task)),
+            failure,
+        }
+    }
+
+    /// Queue a block; fails once the writer has stopped on an error or
+    /// finished
+    pub fn write_block(&self, block: BlockLifecycle) -> Result<()> {
+        self.send(Record::Block(Box::new(block)))
+    }
+
+    /// Queue a reorg; fails once the writer has stopped on an error or
+    /// finished
+    pub fn write_reorg(&self, reorg: ReorgEvent) -> Result<()> {
+        self.send(Record::Reorg(reorg))
+    }
+
+    /// The error that stopped the writer, if a write failed
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.get().map(String::as_str)
+    }
+
+    fn send(&self, record: Record) -> Result<()> {
+        if let Some(failure) = self.failure() {
+            anyhow::bail!("Sink write failed: {}", failure);
+        }
+        self.records
+            .send(record)
+            .ok()
+            .context("Sink writer already finished")
+    }
+
+    /// Write everything still buffered, finish the sink and report what was
+    /// written, or the first error
+    pub async fn finish(&self) -> Result<SinkStats> {
+        let _ = self.records.send(Record::Finish);
+        let task = self
+            .task
+            .lock()
+            .unwrap()
+            .take()
+            .context("Sink writer already finished")?;
+        task.await?
+    }
+}
+
+async fn run(
+    mut sink: Box<dyn Sink>,
+    policy: FlushPolicy,
+    mut records: mpsc::UnboundedReceiver<Record>,
+) -> Result<SinkStats> {
+    let mut stats = SinkStats::default();
+    let mut pending = Vec::new();
+    let mut deadline = None;
+
+    loop {
+        let record = match deadline {
+            Some(until) => tokio::select! {
+                record = records.recv() => record,
+                _ = tokio::time::sleep_until(until) => {
+                    flush(sink.as_mut(), &mut pending, &mut stats).await?;
+                    deadline = None;
+                    continue;
+                }
+            },
+            None => records.recv().await,
+        };
+
+        match record {
+            Some(Record::Block(block)) => {
+                pending.push(*block);
+                if pending.len() >= policy.max_blocks.max(1) {
+                    flush(sink.as_mut(), &mut pending, &mut stats).await?;
+                }
+            }
+            Some(Record::Reorg(reorg)) => {
+                // Orphaned blocks must be written before the reorg marks them
+                flush(sink.as_mut(), &mut pending, &mut stats).await?;
+                sink.write_reorgs(std::slice::from_ref(&reorg)).await?;
+                stats.reorgs += 1;
+            }
+            Some(Record::Finish) | None => break,
+        }
+
+        deadline = match (policy.interval, deadline, pending.is_empty()) {
+            (_, _, true) => None,
+            (Some(interval), None, false) => Some(Instant::now() + interval),
+            (_, deadline, false) => deadline,
+        };
+    }
+
+    flush(sink.as_mut(), &mut pending, &mut stats).await?;
+    sink.finish().await?;
+    Ok(stats)
+}
+
+async fn flush(
+    sink: &mut dyn Sink,
+    pending: &mut Vec<BlockLifecycle>,
+    stats: &mut SinkStats,
+) -> Result<()> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    sink.write_blocks(pending).await?;
+    stats.blocks += pending.len();
+    pending.clear();
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ethers::types::{Block, H256, Trace, Transaction, TransactionReceipt};
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use crate::types::ProviderDisagreement;
+
+/// Depth below the head treated as final by sources that can't report
+/// finality themselves (two epochs)
+pub const FINALITY_DEPTH: u64 = 64;
+
+/// New block headers pushed by an `eth_subscribe("newHeads")` subscription
+pub type HeadStream<'a> = Pin<Box<dyn Stream<Item = Block<H256>> + Send + 'a>>;
+
+/// Blocks yielded by [`BlockSource::stream_blocks`], in request order
+pub type BlockStream<'a> = Pin<Box<dyn Stream<Item = Result<FetchedBlock>> + Send + 'a>>;
+
+/// A block fetched by [`BlockSource::stream_blocks`]
+pub struct FetchedBlock {
+    pub number: u64,
+
+    /// `None` if the source doesn't have this block
+    pub block: Option<Block<Transaction>>,
+
+    /// Receipts of the block's transactions, in block order
+    pub receipts: Vec<TransactionReceipt>,
+
+    /// Endpoints that returned a different hash (quorum mode only)
+    pub disagreements: Vec<ProviderDisagreement>,
+}
+
+/// Where the analyzer reads chain data from: a node, or recorded fixtures
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    /// Block with full transactions, `None` if the source doesn't have it
+    async fn get_block(&self, number: u64) -> Result<Option<Block<Transaction>>>;
+
+    /// Receipts of every transaction in `block`, in block order
+    async fn get_block_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<TransactionReceipt>>;
+
+    /// Call traces of every transaction in the block
+    async fn get_block_traces(&self, number: u64) -> Result<Vec<Trace>>;
+
+    async fn get_latest_block_number(&self) -> Result<u64>;
+
+    /// Highest block that can no longer be reorged out
+    async fn get_finalized_block_number(&self) -> Result<u64> {
+        Ok(self
+            .get_latest_block_number()
+            .await?
+            .saturating_sub(FINALITY_DEPTH))
+    }
+
+    /// Hash of the canonical block at `number`
+    async fn get_block_hash(&self, number: u64) -> Result<Option<H256>> {
+        Ok(self.get_block(number).await?.and_then(|block| block.hash))
+    }
+
+    /// Push stream of new heads; `None` means callers have to poll
+    async fn subscribe_new_heads(&self) -> Result<Option<HeadStream<'_>>> {
+        Ok(None)
+    }
+
+    /// Whether fetched blocks are cross-checked against other providers
+    fn quorum_enabled(&self) -> bool {
+        false
+    }
+
+    /// Blocks whose hash other providers disagree with
+    async fn cross_check(
+        &self,
+        _blocks: &[(u64, H256)],
+    ) -> HashMap<u64, Vec<ProviderDisagreement>> {
+        HashMap::new()
+    }
+
+    /// Fetch blocks and their receipts, yielded in the order of `numbers`.
+    /// Sources that can fetch concurrently use up to `max_in_flight` requests.
+    fn stream_blocks(&self, numbers: Vec<u64>, _max_in_flight: usize) -> BlockStream<'_> {
+        Box::pin(stream::iter(numbers).then(move |number| async move {
+            let block = self.get_block(number).await?;
+            let receipts = match &block {
+                Some(block) => self.get_block_receipts(block).await?,
+                None => Vec::new(),
+            };
+
+            Ok(FetchedBlock {
+                number,
+                block,
+                receipts,
+                disagreements: Vec::new(),
+            })
+        }))
+    }
+}
+
+/// Everything recorded about one block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureBlock {
+    pub block: Block<Transaction>,
+
+    #[serde(default)]
+    pub receipts: Vec<TransactionReceipt>,
+
+    #[serde(default)]
+    pub traces: Vec<Trace>,
+}
+
+impl FixtureBlock {
+    pub fn number(&self) -> u64 {
+        self.block.number.unwrap_or_default().as_u64()
+    }
+}
+
+/// In-memory source serving a fixed set of blocks
+#[derive(Debug, Clone, Default)]
+pub struct FixtureSource {
+    blocks: BTreeMap<u64, FixtureBlock>,
+}
+
+impl FixtureSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a block, replacing any stored at the same number
+    pub fn insert(&mut self, fixture: FixtureBlock) {
+        self.blocks.insert(fixture.number(), fixture);
+    }
+
+    /// Add a block with its receipts
+    pub fn with_block(
+        mut self,
+        block: Block<Transaction>,
+        receipts: Vec<TransactionReceipt>,
+    ) -> Self {
+        self.insert(FixtureBlock {
+            block,
+            receipts,
+            traces: Vec::new(),
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+#[async_trait]
+impl BlockSource for FixtureSource {
+    async fn get_block(&self, number: u64) -> Result<Option<Block<Transaction>>> {
+        Ok(self
+            .blocks
+            .get(&number)
+            .map(|fixture| fixture.block.clone()))
+    }
+
+    async fn get_block_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<TransactionReceipt>> {
+        let number = block.number.unwrap_or_default().as_u64();
+        Ok(self
+            .blocks
+            .get(&number)
+            .map(|fixture| fixture.receipts.clone())
+            .unwrap_or_default())
+    }
+
+    async fn get_block_traces(&self, number: u64) -> Result<Vec<Trace>> {
+        Ok(self
+            .blocks
+            .get(&number)
+            .map(|fixture| fixture.traces.clone())
+            .unwrap_or_default())
+    }
+
+    async fn get_latest_block_number(&self) -> Result<u64> {
+        self.blocks
+            .keys()
+            .next_back()
+            .copied()
+            .context("Fixture source has no blocks")
+    }
+}
+
+/// Source replaying blocks recorded as `<number>.json` files (one
+/// [`FixtureBlock`] each) in a directory. Files are read on demand.
+#[derive(Debug, Clone)]
+pub struct ReplaySource {
+    dir: PathBuf,
+}
+
+impl ReplaySource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Record `fixture` so it can be replayed later
+    pub fn write(&self, fixture: &FixtureBlock) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+
+        let path = self.path(fixture.number());
+        let json = serde_json::to_string_pretty(fixture)?;
+        std::fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn path(&self, number: u64) -> PathBuf {
+        self.dir.join(format!("{}.json", number))
+    }
+
+    fn read(&self, number: u64) -> Result<Option<FixtureBlock>> {
+        let path = self.path(number);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let fixture = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid block fixture {}", path.display()))?;
+        Ok(Some(fixture))
+    }
+}
+
+#[async_trait]
+impl BlockSource for ReplaySource {
+    async fn get_block(&self, number: u64) -> Result<Option<Block<Transaction>>> {
+        Ok(self.read(number)?.map(|fixture| fixture.block))
+    }
+
+    async fn get_block_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<TransactionReceipt>> {
+        let number = block.number.unwrap_or_default().as_u64();
+        Ok(self
+            .read(number)?
+            .map(|fixture| fixture.receipts)
+            .unwrap_or_default())
+    }
+
+    async fn get_block_traces(&self, number: u64) -> Result<Vec<Trace>> {
+        Ok(self
+            .read(number)?
+            .map(|fixture| fixture.traces)
+            .unwrap_or_default())
+    }
+
+    /// Highest recorded block number
+    async fn get_latest_block_number(&self) -> Result<u64> {
+        let entries = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?;
+
+        entries
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name();
+                name.to_str()?.strip_suffix(".json")?.parse::<u64>().ok()
+            })
+            .max()
+            .with_context(|| format!("No recorded blocks in {}", self.dir.display()))
+    }
+}
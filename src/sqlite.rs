@@ -0,0 +1,373 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rusqlite::{Connection, Transaction, params};
+use std::collections::HashMap;
+
+use crate::sink::Sink;
+use crate::store::{builder_name, mev_findings};
+use crate::types::{BlockLifecycle, ReorgEvent};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS builders (
+    name TEXT PRIMARY KEY,
+    fee_recipient TEXT,
+    first_block INTEGER NOT NULL,
+    last_block INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS blocks (
+    hash TEXT PRIMARY KEY,
+    number INTEGER NOT NULL,
+    parent_hash TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    proposer TEXT NOT NULL,
+    builder TEXT REFERENCES builders(name),
+    block_time REAL NOT NULL,
+    slot INTEGER,
+    received_at REAL,
+    propagation_delay REAL,
+    timestamp_delay REAL,
+    gas_used INTEGER NOT NULL,
+    gas_limit INTEGER NOT NULL,
+    gas_utilization REAL NOT NULL,
+    base_fee_gwei REAL NOT NULL,
+    avg_priority_fee_gwei REAL NOT NULL,
+    median_priority_fee_gwei REAL NOT NULL,
+    priority_fee_p10_gwei REAL NOT NULL,
+    priority_fee_p25_gwei REAL NOT NULL,
+    priority_fee_p75_gwei REAL NOT NULL,
+    priority_fee_p90_gwei REAL NOT NULL,
+    fees_burned_eth REAL NOT NULL,
+    priority_fees_eth REAL NOT NULL,
+    tx_count INTEGER NOT NULL,
+    tx_legacy INTEGER NOT NULL,
+    tx_eip2930 INTEGER NOT NULL,
+    tx_eip1559 INTEGER NOT NULL,
+    tx_eip4844 INTEGER NOT NULL,
+    tx_failed INTEGER NOT NULL,
+    tx_ordering_anomalies INTEGER NOT NULL,
+    mev_estimated_eth REAL NOT NULL,
+    mev_bot_addresses TEXT NOT NULL,
+    is_pbs_block INTEGER NOT NULL,
+    builder_address TEXT,
+    builder_match_source TEXT,
+    builder_confidence TEXT,
+    builder_payment_eth REAL,
+    payment_tx TEXT,
+    proposer_fee_recipient TEXT,
+    builder_margin_eth REAL,
+    extra_data TEXT NOT NULL,
+    provider_disagreements INTEGER NOT NULL,
+    labels TEXT NOT NULL,
+    orphaned INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS blocks_number ON blocks(number);
+
+CREATE TABLE IF NOT EXISTS transactions (
+    block_hash TEXT NOT NULL REFERENCES blocks(hash) ON DELETE CASCADE,
+    tx_index INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    gas_used INTEGER NOT NULL,
+    effective_gas_price_gwei REAL NOT NULL,
+    success INTEGER NOT NULL,
+    PRIMARY KEY (block_hash, tx_index)
+);
+CREATE INDEX IF NOT EXISTS transactions_hash ON transactions(tx_hash);
+
+CREATE TABLE IF NOT EXISTS mev_findings (
+    block_hash TEXT NOT NULL REFERENCES blocks(hash) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    tx_hashes TEXT NOT NULL,
+    venue TEXT,
+    actor TEXT,
+    estimated_profit_eth REAL,
+    details TEXT NOT NULL,
+    PRIMARY KEY (block_hash, kind, position)
+);
+
+CREATE TABLE IF NOT EXISTS reorgs (
+    detected_at INTEGER NOT NULL,
+    common_ancestor INTEGER,
+    depth INTEGER NOT NULL,
+    orphaned_hashes TEXT NOT NULL,
+    replacement_hashes TEXT NOT NULL,
+    PRIMARY KEY (detected_at, orphaned_hashes)
+);
+";
+
+/// Persistent SQLite store of analyzed blocks.
+///
+/// Blocks are upserted by hash, so re-analyzing a block replaces its rows
+/// (and its transactions and MEV findings) instead of duplicating them.
+/// Tables: `blocks`, `transactions`, `mev_findings`, `builders`, `reorgs`.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database at `path` and bring its schema up to date
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open SQLite database {}", path))?;
+        Self::init(conn)
+    }
+
+    /// In-memory database, e.g. for tests
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create SQLite schema")?;
+        Ok(Self { conn })
+    }
+
+    /// The underlying connection, for queries
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Insert or replace blocks, in one transaction
+    pub fn write_blocks(&mut self, blocks: &[BlockLifecycle]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for lifecycle in blocks {
+            upsert_block(&tx, lifecycle)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Record reorgs and mark the blocks they orphaned; reorgs already
+    /// stored are skipped
+    pub fn write_reorgs(&mut self, reorgs: &[ReorgEvent]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for reorg in reorgs {
+            tx.execute(
+                "INSERT OR IGNORE INTO reorgs
+                    (detected_at, common_ancestor, depth, orphaned_hashes, replacement_hashes)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    reorg.detected_at,
+                    reorg.common_ancestor,
+                    reorg.depth,
+                    reorg.orphaned.join(";"),
+                    reorg.replacement.join(";"),
+                ],
+            )?;
+
+            for hash in &reorg.orphaned {
+                tx.execute("UPDATE blocks SET orphaned = 1 WHERE hash = ?1", [hash])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for SqliteStore {
+    async fn write_blocks(&mut self, blocks: &[BlockLifecycle]) -> Result<()> {
+        SqliteStore::write_blocks(self, blocks)
+    }
+
+    async fn write_reorgs(&mut self, reorgs: &[ReorgEvent]) -> Result<()> {
+        SqliteStore::write_reorgs(self, reorgs)
+    }
+}
+
+fn upsert_block(tx: &Transaction, lifecycle: &BlockLifecycle) -> Result<()> {
+    let gas = &lifecycle.gas;
+    let txs = &lifecycle.transactions;
+    let mev = &lifecycle.mev;
+    let pbs = &lifecycle.pbs;
+
+    let builder = builder_name(lifecycle);
+    if let Some(name) = &builder {
+        tx.execute(
+            "INSERT INTO builders (name, fee_recipient, first_block, last_block)
+             VALUES (?1, ?2, ?3, ?3)
+             ON CONFLICT(name) DO UPDATE SET
+                fee_recipient = COALESCE(excluded.fee_recipient, fee_recipient),
+                first_block = MIN(first_block, excluded.first_block),
+                last_block = MAX(last_block, excluded.last_block)",
+            params![name, pbs.builder_address, lifecycle.block_number],
+        )?;
+    }
+
+    // Children are rewritten from scratch; a changed block must not keep stale rows
+    tx.execute(
+        "DELETE FROM transactions WHERE block_hash = ?1",
+        [&lifecycle.block_hash],
+    )?;
+    tx.execute(
+        "DELETE FROM mev_findings WHERE block_hash = ?1",
+        [&lifecycle.block_hash],
+    )?;
+
+    tx.execute(
+        "INSERT INTO blocks (
+            hash, number, parent_hash, timestamp, proposer, builder,
+            block_time, slot, received_at, propagation_delay, timestamp_delay,
+            gas_used, gas_limit, gas_utilization, base_fee_gwei,
+            avg_priority_fee_gwei, median_priority_fee_gwei,
+            priority_fee_p10_gwei, priority_fee_p25_gwei,
+            priority_fee_p75_gwei, priority_fee_p90_gwei,
+            fees_burned_eth, priority_fees_eth,
+            tx_count, tx_legacy, tx_eip2930, tx_eip1559, tx_eip4844,
+            tx_failed, tx_ordering_anomalies,
+            mev_estimated_eth, mev_bot_addresses,
+            is_pbs_block, builder_address, builder_match_source, builder_confidence,
+            builder_payment_eth, payment_tx, proposer_fee_recipient, builder_margin_eth,
+            extra_data, provider_disagreements, labels
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+            ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28,
+            ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38, ?39, ?40, ?41,
+            ?42, ?43
+        )
+        ON CONFLICT(hash) DO UPDATE SET
+            number = excluded.number,
+            parent_hash = excluded.parent_hash,
+            timestamp = excluded.timestamp,
+            proposer = excluded.proposer,
+            builder = excluded.builder,
+            block_time = excluded.block_time,
+            slot = excluded.slot,
+            -- Arrival times are only known live; keep them when a range run revisits the block
+            received_at = COALESCE(excluded.received_at, received_at),
+            propagation_delay = COALESCE(excluded.propagation_delay, propagation_delay),
+            timestamp_delay = COALESCE(excluded.timestamp_delay, timestamp_delay),
+            gas_used = excluded.gas_used,
+            gas_limit = excluded.gas_limit,
+            gas_utilization = excluded.gas_utilization,
+            base_fee_gwei = excluded.base_fee_gwei,
+            avg_priority_fee_gwei = excluded.avg_priority_fee_gwei,
+            median_priority_fee_gwei = excluded.median_priority_fee_gwei,
+            priority_fee_p10_gwei = excluded.priority_fee_p10_gwei,
+            priority_fee_p25_gwei = excluded.priority_fee_p25_gwei,
+            priority_fee_p75_gwei = excluded.priority_fee_p75_gwei,
+            priority_fee_p90_gwei = excluded.priority_fee_p90_gwei,
+            fees_burned_eth = excluded.fees_burned_eth,
+            priority_fees_eth = excluded.priority_fees_eth,
+            tx_count = excluded.tx_count,
+            tx_legacy = excluded.tx_legacy,
+            tx_eip2930 = excluded.tx_eip2930,
+            tx_eip1559 = excluded.tx_eip1559,
+            tx_eip4844 = excluded.tx_eip4844,
+            tx_failed = excluded.tx_failed,
+            tx_ordering_anomalies = excluded.tx_ordering_anomalies,
+            mev_estimated_eth = excluded.mev_estimated_eth,
+            mev_bot_addresses = excluded.mev_bot_addresses,
+            is_pbs_block = excluded.is_pbs_block,
+            builder_address = excluded.builder_address,
+            builder_match_source = excluded.builder_match_source,
+            builder_confidence = excluded.builder_confidence,
+            builder_payment_eth = excluded.builder_payment_eth,
+            payment_tx = excluded.payment_tx,
+            proposer_fee_recipient = excluded.proposer_fee_recipient,
+            builder_margin_eth = excluded.builder_margin_eth,
+            extra_data = excluded.extra_data,
+            provider_disagreements = excluded.provider_disagreements,
+            labels = excluded.labels,
+            orphaned = 0",
+        params![
+            lifecycle.block_hash,
+            lifecycle.block_number,
+            lifecycle.parent_hash,
+            lifecycle.timestamp,
+            lifecycle.proposer,
+            builder,
+            lifecycle.timing.block_time,
+            lifecycle.timing.slot,
+            lifecycle.timing.received_at,
+            lifecycle.timing.propagation_delay,
+            lifecycle.timing.timestamp_delay,
+            gas.gas_used,
+            gas.gas_limit,
+            gas.utilization,
+            gas.base_fee_gwei,
+            gas.avg_priority_fee_gwei,
+            gas.median_priority_fee_gwei,
+            gas.priority_fee_percentiles.p10,
+            gas.priority_fee_percentiles.p25,
+            gas.priority_fee_percentiles.p75,
+            gas.priority_fee_percentiles.p90,
+            gas.fees_burned_eth,
+            gas.priority_fees_eth,
+            txs.total_count,
+            txs.type_breakdown.legacy,
+            txs.type_breakdown.eip2930,
+            txs.type_breakdown.eip1559,
+            txs.type_breakdown.eip4844_blob,
+            txs.failed_count,
+            txs.ordering.anomalies,
+            mev.estimated_mev_eth,
+            mev.mev_bot_addresses.join(";"),
+            pbs.is_pbs_block,
+            pbs.builder_address,
+            pbs.builder_match.as_ref().map(|m| m.source.to_string()),
+            pbs.builder_match.as_ref().map(|m| m.confidence.to_string()),
+            pbs.builder_payment_eth,
+            pbs.payment_tx,
+            pbs.proposer_fee_recipient,
+            pbs.builder_margin_eth,
+            pbs.extra_data,
+            lifecycle.provider_disagreements.len(),
+            serde_json::to_string(&lifecycle.labels)?,
+        ],
+    )?;
+
+    let mut insert_tx = tx.prepare_cached(
+        "INSERT INTO transactions
+            (block_hash, tx_index, tx_hash, gas_used, effective_gas_price_gwei, success)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for gas in &txs.gas_usage {
+        insert_tx.execute(params![
+            lifecycle.block_hash,
+            gas.tx_index,
+            gas.tx_hash,
+            gas.gas_used,
+            gas.effective_gas_price_gwei,
+            gas.success,
+        ])?;
+    }
+
+    let mut insert_finding = tx.prepare_cached(
+        "INSERT INTO mev_findings
+            (block_hash, kind, position, tx_hash, tx_hashes, venue, actor,
+             estimated_profit_eth, details)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?;
+    let mut positions = HashMap::new();
+    for finding in mev_findings(mev)? {
+        let position = positions.entry(finding.kind).or_insert(0);
+        insert_finding.execute(params![
+            lifecycle.block_hash,
+            finding.kind,
+            *position,
+            finding
+                .tx_hashes
+                .first()
+                .map(|hash| hash.as_str())
+                .unwrap_or_default(),
+            finding
+                .tx_hashes
+                .iter()
+                .map(|hash| hash.as_str())
+                .collect::<Vec<_>>()
+                .join(";"),
+            finding.venue,
+            finding.actor,
+            finding.estimated_profit_eth,
+            finding.details.to_string(),
+        ])?;
+        *position += 1;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+
+use crate::types::{BlockLifecycle, MevIndicators};
+
+/// Builder the block is attributed to: the registry match if there is one,
+/// else the name parsed from extra_data
+pub(crate) fn builder_name(lifecycle: &BlockLifecycle) -> Option<String> {
+    lifecycle
+        .pbs
+        .builder_match
+        .as_ref()
+        .map(|m| m.name.clone())
+        .or_else(|| lifecycle.builder.clone())
+}
+
+/// One MEV finding, flattened to the columns of the `mev_findings` table
+pub(crate) struct Finding<'a> {
+    pub kind: &'a str,
+    pub tx_hashes: Vec<&'a String>,
+    pub venue: Option<String>,
+    pub actor: Option<&'a String>,
+    pub estimated_profit_eth: Option<f64>,
+
+    /// The full finding as JSON
+    pub details: serde_json::Value,
+}
+
+/// Flatten a block's MEV findings into `mev_findings` rows, in the order
+/// sandwiches, arbitrages, liquidations, other detectors
+pub(crate) fn mev_findings(mev: &MevIndicators) -> Result<Vec<Finding<'_>>> {
+    let mut findings = Vec::new();
+    for sandwich in &mev.sandwich_attacks {
+        findings.push(Finding {
+            kind: "sandwich",
+            tx_hashes: vec![
+                &sandwich.frontrun_tx,
+                &sandwich.victim_tx,
+                &sandwich.backrun_tx,
+            ],
+            venue: Some(sandwich.dex.clone()),
+            actor: Some(&sandwich.attacker),
+            estimated_profit_eth: Some(sandwich.estimated_profit_eth),
+            details: serde_json::to_value(sandwich)?,
+        });
+    }
+    for arbitrage in &mev.arbitrage_ops {
+        findings.push(Finding {
+            kind: "arbitrage",
+            tx_hashes: vec![&arbitrage.tx_hash],
+            venue: Some(arbitrage.dexes_involved.join(";")),
+            actor: None,
+            estimated_profit_eth: Some(arbitrage.estimated_profit_eth),
+            details: serde_json::to_value(arbitrage)?,
+        });
+    }
+    for liquidation in &mev.liquidations {
+        findings.push(Finding {
+            kind: "liquidation",
+            tx_hashes: vec![&liquidation.tx_hash],
+            venue: Some(liquidation.protocol.clone()),
+            actor: Some(&liquidation.liquidator),
+            estimated_profit_eth: None,
+            details: serde_json::to_value(liquidation)?,
+        });
+    }
+    for finding in &mev.other_findings {
+        findings.push(Finding {
+            kind: &finding.detector,
+            tx_hashes: finding.tx_hashes.iter().collect(),
+            venue: None,
+            actor: None,
+            estimated_profit_eth: Some(finding.estimated_profit_eth),
+            details: serde_json::to_value(finding)?,
+        });
+    }
+
+    Ok(findings)
+}
@@ -0,0 +1,541 @@
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::fixtures::{FixtureMode, RpcFixtures};
+use crate::retry::{RateLimit, RateLimiter, RetryPolicy, is_rate_limit_code};
+
+/// JSON-RPC client over HTTP that can also send batched requests.
+///
+/// Rate-limited (429) and 5xx responses, timeouts and connection errors are
+/// retried according to the [`RetryPolicy`], and every call first waits for
+/// the [`RateLimit`] budget. When several endpoints are configured, calls go
+/// to the active one and fail over to the next once its retries run out.
+///
+/// With [`RpcFixtures`] attached, every call is either recorded after it is
+/// answered or served from the recording without any network access.
+#[derive(Debug)]
+pub struct HttpTransport {
+    id: AtomicU64,
+    client: Client,
+    endpoints: Vec<Url>,
+    active: AtomicUsize,
+    retry: RetryPolicy,
+    limiter: RateLimiter,
+    fixtures: Option<RpcFixtures>,
+}
+
+#[derive(Debug, Error)]
+pub enum TransportError {
+    /// The HTTP request itself failed
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with a rate-limit or server error status
+    #[error("HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+
+    /// The node answered with a JSON-RPC error
+    #[error(transparent)]
+    JsonRpc(#[from] JsonRpcError),
+
+    /// The response body could not be decoded
+    #[error("Deserialization error: {err}. Response: {text}")]
+    SerdeJson {
+        err: serde_json::Error,
+        text: String,
+    },
+
+    /// A batch response did not contain an answer for one of the requests
+    #[error("Batch response is missing request id {0}")]
+    MissingResponse(u64),
+
+    /// Every attempt allowed by the retry policy failed
+    #[error("Giving up after {attempts} attempts: {source}")]
+    RetriesExhausted {
+        attempts: u32,
+        source: Box<TransportError>,
+    },
+
+    /// A request named an endpoint index that doesn't exist
+    #[error("No RPC endpoint with index {0}")]
+    UnknownEndpoint(usize),
+
+    /// Replay mode found no recording of a call
+    #[error("No recorded response for {method} {params}")]
+    MissingFixture { method: String, params: String },
+
+    /// A fixture file could not be read or written
+    #[error("Fixture error: {0}")]
+    Fixture(#[from] std::io::Error),
+}
+
+impl TransportError {
+    /// Whether sending the same request again may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TransportError::Http(err) => err.is_timeout() || err.is_connect(),
+            TransportError::Status { status, .. } => *status == 429 || *status >= 500,
+            TransportError::JsonRpc(err) => {
+                is_rate_limit_code(err.code) || err.message.to_lowercase().contains("rate limit")
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether another endpoint should be tried after this error
+    fn should_fail_over(&self) -> bool {
+        matches!(self, TransportError::RetriesExhausted { .. }) || self.is_retryable()
+    }
+}
+
+impl RpcError for TransportError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            TransportError::JsonRpc(err) => Some(err),
+            TransportError::RetriesExhausted { source, .. } => source.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            TransportError::SerdeJson { err, .. } => Some(err),
+            TransportError::RetriesExhausted { source, .. } => source.as_serde_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<TransportError> for ProviderError {
+    fn from(err: TransportError) -> Self {
+        match err {
+            TransportError::Http(err) => ProviderError::HTTPError(err),
+            other => ProviderError::JsonRpcClientError(Box::new(other)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RpcRequest<'a, T> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: T,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    id: u64,
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<JsonRpcError>,
+}
+
+impl RpcResponse {
+    fn into_result(self) -> Result<Value, TransportError> {
+        match self.error {
+            Some(error) => Err(error.into()),
+            None => Ok(self.result.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+impl HttpTransport {
+    pub fn new(url: Url) -> Self {
+        Self::with_endpoints(vec![url])
+    }
+
+    /// Create a transport over several endpoints, the first one starting active
+    pub fn with_endpoints(endpoints: Vec<Url>) -> Self {
+        assert!(!endpoints.is_empty(), "at least one RPC endpoint is required");
+
+        Self {
+            id: AtomicU64::new(1),
+            client: Client::new(),
+            endpoints,
+            active: AtomicUsize::new(0),
+            retry: RetryPolicy::default(),
+            limiter: RateLimiter::default(),
+            fixtures: None,
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, limit: &RateLimit) -> Self {
+        self.limiter = RateLimiter::new(limit);
+        self
+    }
+
+    /// Record every call to, or replay every call from, `fixtures`
+    pub fn with_fixtures(mut self, fixtures: RpcFixtures) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    pub fn endpoints(&self) -> &[Url] {
+        &self.endpoints
+    }
+
+    /// Index of the endpoint calls are currently sent to
+    pub fn active_endpoint(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn set_active_endpoint(&self, index: usize) {
+        if index < self.endpoints.len() {
+            self.active.store(index, Ordering::Relaxed);
+        }
+    }
+
+    /// Send several calls in a single JSON-RPC batch.
+    ///
+    /// Results are returned in the same order as `calls`, regardless of the
+    /// order the node answers in. Any per-call error fails the whole batch.
+    pub async fn batch_request(
+        &self,
+        calls: &[(&str, Value)],
+    ) -> Result<Vec<Value>, TransportError> {
+        self.batch(calls, None).await
+    }
+
+    /// Send a batch to one specific endpoint, without failing over
+    pub async fn batch_request_to(
+        &self,
+        endpoint: usize,
+        calls: &[(&str, Value)],
+    ) -> Result<Vec<Value>, TransportError> {
+        self.batch(calls, Some(endpoint)).await
+    }
+
+    /// Send a single call to one specific endpoint, without failing over
+    pub async fn request_to<T, R>(
+        &self,
+        endpoint: usize,
+        method: &str,
+        params: T,
+    ) -> Result<R, TransportError>
+    where
+        T: Serialize,
+        R: DeserializeOwned,
+    {
+        self.call(method, params, Some(endpoint)).await
+    }
+
+    async fn call<T, R>(
+        &self,
+        method: &str,
+        params: T,
+        endpoint: Option<usize>,
+    ) -> Result<R, TransportError>
+    where
+        T: Serialize,
+        R: DeserializeOwned,
+    {
+        let result = match &self.fixtures {
+            Some(fixtures) => {
+                let params = serde_json::to_value(params).map_err(|err| TransportError::SerdeJson {
+                    err,
+                    text: method.to_string(),
+                })?;
+                let mut answers = self
+                    .fixture_answers(fixtures, &[(method, params)], endpoint)
+                    .await?;
+                answers.pop().ok_or(TransportError::MissingResponse(0))??
+            }
+            None => {
+                let payload = RpcRequest {
+                    jsonrpc: "2.0",
+                    id: self.id.fetch_add(1, Ordering::SeqCst),
+                    method,
+                    params,
+                };
+
+                let body = self.send(&payload, &[method], endpoint).await?;
+                let response: RpcResponse = decode(&body)?;
+                response.into_result()?
+            }
+        };
+
+        R::deserialize(&result).map_err(|err| TransportError::SerdeJson {
+            err,
+            text: result.to_string(),
+        })
+    }
+
+    async fn batch(
+        &self,
+        calls: &[(&str, Value)],
+        endpoint: Option<usize>,
+    ) -> Result<Vec<Value>, TransportError> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if let Some(fixtures) = &self.fixtures {
+            return self
+                .fixture_answers(fixtures, calls, endpoint)
+                .await?
+                .into_iter()
+                .collect();
+        }
+
+        self.batch_answers(calls, endpoint)
+            .await?
+            .into_iter()
+            .collect()
+    }
+
+    /// Send a batch and return each call's own answer, in call order
+    async fn batch_answers(
+        &self,
+        calls: &[(&str, Value)],
+        endpoint: Option<usize>,
+    ) -> Result<Vec<Result<Value, TransportError>>, TransportError> {
+        let first_id = self.id.fetch_add(calls.len() as u64, Ordering::SeqCst);
+        let payload: Vec<_> = calls
+            .iter()
+            .enumerate()
+            .map(|(i, (method, params))| RpcRequest {
+                jsonrpc: "2.0",
+                id: first_id + i as u64,
+                method,
+                params,
+            })
+            .collect();
+
+        let methods: Vec<&str> = calls.iter().map(|(method, _)| *method).collect();
+        let body = self.send(&payload, &methods, endpoint).await?;
+        let responses: Vec<RpcResponse> = decode(&body)?;
+
+        let mut by_id: HashMap<u64, RpcResponse> =
+            responses.into_iter().map(|r| (r.id, r)).collect();
+
+        (0..calls.len() as u64)
+            .map(|i| {
+                let id = first_id + i;
+                let response = by_id.remove(&id).ok_or(TransportError::MissingResponse(id))?;
+                Ok(response.into_result())
+            })
+            .collect()
+    }
+
+    /// Answer calls from the fixture directory, or ask the node and record
+    /// its answers. Only JSON-RPC errors are recorded alongside results;
+    /// transport failures are returned without touching the recording.
+    async fn fixture_answers(
+        &self,
+        fixtures: &RpcFixtures,
+        calls: &[(&str, Value)],
+        endpoint: Option<usize>,
+    ) -> Result<Vec<Result<Value, TransportError>>, TransportError> {
+        let answers = match fixtures.mode() {
+            FixtureMode::Replay => calls
+                .iter()
+                .map(|(method, params)| match fixtures.load(method, params)? {
+                    Some(answer) => Ok(answer.map_err(TransportError::from)),
+                    None => Err(TransportError::MissingFixture {
+                        method: method.to_string(),
+                        params: params.to_string(),
+                    }),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            FixtureMode::Record => {
+                let answers = self.batch_answers(calls, endpoint).await?;
+                for ((method, params), answer) in calls.iter().zip(&answers) {
+                    match answer {
+                        Ok(result) => fixtures.save(method, params, &Ok(result.clone()))?,
+                        Err(TransportError::JsonRpc(err)) => {
+                            fixtures.save(method, params, &Err(err.clone()))?
+                        }
+                        Err(_) => {}
+                    }
+                }
+                answers
+            }
+        };
+
+        Ok(answers)
+    }
+
+    /// POST `payload` to `endpoint`, or to the active endpoint with failover.
+    ///
+    /// On failover the first endpoint that answers becomes the active one.
+    async fn send<T: Serialize + ?Sized>(
+        &self,
+        payload: &T,
+        methods: &[&str],
+        endpoint: Option<usize>,
+    ) -> Result<Vec<u8>, TransportError> {
+        if let Some(index) = endpoint {
+            let url = self
+                .endpoints
+                .get(index)
+                .ok_or(TransportError::UnknownEndpoint(index))?;
+            return self.send_with_retry(url, payload, methods).await;
+        }
+
+        let start = self.active_endpoint();
+        let mut last_err = None;
+
+        for offset in 0..self.endpoints.len() {
+            let index = (start + offset) % self.endpoints.len();
+
+            match self.send_with_retry(&self.endpoints[index], payload, methods).await {
+                Ok(body) => {
+                    if index != start {
+                        self.set_active_endpoint(index);
+                    }
+                    return Ok(body);
+                }
+                Err(err) if err.should_fail_over() => last_err = Some(err),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err.expect("at least one endpoint was tried"))
+    }
+
+    /// POST `payload` to `url`, retrying transient failures until the policy gives up
+    async fn send_with_retry<T: Serialize + ?Sized>(
+        &self,
+        url: &Url,
+        payload: &T,
+        methods: &[&str],
+    ) -> Result<Vec<u8>, TransportError> {
+        let mut attempt = 0;
+
+        loop {
+            self.limiter.acquire(methods).await;
+
+            let (err, retry_after) = match self.post(url, payload).await {
+                Ok(body) => return Ok(body),
+                Err(failure) => failure,
+            };
+
+            if !err.is_retryable() {
+                return Err(err);
+            }
+            if attempt >= self.retry.max_retries {
+                if attempt == 0 {
+                    return Err(err);
+                }
+                return Err(TransportError::RetriesExhausted {
+                    attempts: attempt + 1,
+                    source: Box::new(err),
+                });
+            }
+
+            let delay = retry_after.unwrap_or_else(|| self.retry.backoff(attempt));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Single POST attempt. Failures carry the server's `Retry-After` delay, if any.
+    async fn post<T: Serialize + ?Sized>(
+        &self,
+        url: &Url,
+        payload: &T,
+    ) -> Result<Vec<u8>, (TransportError, Option<Duration>)> {
+        let response = self
+            .client
+            .post(url.clone())
+            .json(payload)
+            .send()
+            .await
+            .map_err(|err| (err.into(), None))?;
+
+        let status = response.status();
+        let retry_after = self.retry.retry_after(response.headers());
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| (err.into(), None))?
+            .to_vec();
+
+        if status.as_u16() == 429 || status.is_server_error() {
+            let err = TransportError::Status {
+                status: status.as_u16(),
+                body: String::from_utf8_lossy(&body).to_string(),
+            };
+            return Err((err, retry_after));
+        }
+
+        // Some providers report rate limiting as a JSON-RPC error with HTTP 200
+        if let Some(err) = rate_limit_error(&body) {
+            return Err((err.into(), retry_after));
+        }
+
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for HttpTransport {
+    type Error = TransportError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, TransportError>
+    where
+        T: std::fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        self.call(method, params, None).await
+    }
+}
+
+fn decode<R: DeserializeOwned>(body: &[u8]) -> Result<R, TransportError> {
+    serde_json::from_slice(body).map_err(|err| TransportError::SerdeJson {
+        err,
+        text: String::from_utf8_lossy(body).to_string(),
+    })
+}
+
+/// Find a rate-limit error in a single or batched JSON-RPC response body
+fn rate_limit_error(body: &[u8]) -> Option<JsonRpcError> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Body {
+        Single(ErrorOnly),
+        Batch(Vec<ErrorOnly>),
+    }
+
+    #[derive(Deserialize)]
+    struct ErrorOnly {
+        error: Option<JsonRpcError>,
+    }
+
+    if !body.windows(7).any(|w| w == b"\"error\"") {
+        return None;
+    }
+
+    let errors = match serde_json::from_slice(body).ok()? {
+        Body::Single(response) => vec![response],
+        Body::Batch(responses) => responses,
+    };
+
+    errors
+        .into_iter()
+        .filter_map(|response| response.error)
+        .find(|err| TransportError::JsonRpc(err.clone()).is_retryable())
+}
+
+/// Host (and port) of an endpoint, safe to print: paths often carry API keys
+pub fn endpoint_label(url: &Url) -> String {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        _ => url.scheme().to_string(),
+    }
+}
@@ -1,11 +1,21 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Unix time of the beacon chain genesis (slot 0) on mainnet
+pub const BEACON_GENESIS_TIME: u64 = 1_606_824_023;
+
+/// Length of a beacon chain slot in seconds
+pub const SECONDS_PER_SLOT: u64 = 12;
 
 /// Complete block lifecycle analysis result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockLifecycle {
     pub block_number: u64,
     pub block_hash: String,
+    #[serde(default)]
+    pub parent_hash: String,
     pub timestamp: u64,
     pub proposer: String,
     pub builder: Option<String>,
@@ -24,6 +34,27 @@ pub struct BlockLifecycle {
     
     // PBS (Proposer-Builder Separation) data
     pub pbs: PbsMetrics,
+
+    // Other RPC providers reporting a different hash for this block (quorum mode)
+    #[serde(default)]
+    pub provider_disagreements: Vec<ProviderDisagreement>,
+
+    // Labels of known addresses reported for this block
+    #[serde(default)]
+    pub labels: Vec<AddressLabel>,
+}
+
+impl BlockLifecycle {
+    /// Every address this report mentions, for labelling
+    pub fn reported_addresses(&self) -> Vec<&str> {
+        let mut addresses = vec![self.proposer.as_str()];
+        addresses.extend(self.pbs.builder_address.as_deref());
+        addresses.extend(self.pbs.proposer_fee_recipient.as_deref());
+        addresses.extend(self.mev.mev_bot_addresses.iter().map(String::as_str));
+        addresses.extend(self.mev.sandwich_attacks.iter().map(|s| s.attacker.as_str()));
+        addresses.extend(self.mev.liquidations.iter().map(|l| l.liquidator.as_str()));
+        addresses
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,8 +65,42 @@ pub struct TimingMetrics {
     /// Timestamp of this block
     pub timestamp: u64,
     
-    /// Estimated propagation delay (if available)
+    /// Seconds between the slot start and the local arrival of the block
     pub propagation_delay: Option<f64>,
+
+    /// Beacon chain slot the block was proposed in
+    #[serde(default)]
+    pub slot: Option<u64>,
+
+    /// Local receive time of the block header (unix seconds)
+    #[serde(default)]
+    pub received_at: Option<f64>,
+
+    /// Seconds between the block `timestamp` and the local arrival of the block
+    #[serde(default)]
+    pub timestamp_delay: Option<f64>,
+}
+
+impl TimingMetrics {
+    /// Record when the block header was received locally and derive the
+    /// propagation delays from it
+    pub fn record_arrival(&mut self, received_at: f64) {
+        self.received_at = Some(received_at);
+        self.timestamp_delay = Some(received_at - self.timestamp as f64);
+
+        if let Some(slot) = slot_at(self.timestamp) {
+            let slot_start = BEACON_GENESIS_TIME + slot * SECONDS_PER_SLOT;
+            self.slot = Some(slot);
+            self.propagation_delay = Some(received_at - slot_start as f64);
+        }
+    }
+}
+
+/// Beacon chain slot containing `timestamp`, or `None` before genesis
+pub fn slot_at(timestamp: u64) -> Option<u64> {
+    timestamp
+        .checked_sub(BEACON_GENESIS_TIME)
+        .map(|elapsed| elapsed / SECONDS_PER_SLOT)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,14 +117,64 @@ pub struct GasMetrics {
     /// Base fee per gas (in gwei)
     pub base_fee_gwei: f64,
     
-    /// Average priority fee (in gwei)
+    /// Average tip per gas actually paid, over all transactions (in gwei)
     pub avg_priority_fee_gwei: f64,
+
+    /// Median tip per gas (in gwei)
+    #[serde(default)]
+    pub median_priority_fee_gwei: f64,
+
+    /// Spread of the tip per gas across transactions (in gwei)
+    #[serde(default)]
+    pub priority_fee_percentiles: FeePercentiles,
     
     /// Total gas fees burned (in ETH)
     pub fees_burned_eth: f64,
     
-    /// Total priority fees to proposer (in ETH)
+    /// Total priority fees to proposer: tip per gas times gas used (in ETH)
     pub priority_fees_eth: f64,
+
+    /// Exact `fees_burned_eth`, in wei (decimal string)
+    #[serde(default)]
+    pub fees_burned_wei: Option<String>,
+
+    /// Exact `priority_fees_eth`, in wei (decimal string)
+    #[serde(default)]
+    pub priority_fees_wei: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeePercentiles {
+    pub p10: f64,
+    pub p25: f64,
+    pub p75: f64,
+    pub p90: f64,
+}
+
+impl FeePercentiles {
+    /// Percentiles of `sorted`, which must be in ascending order
+    pub fn from_sorted(sorted: &[f64]) -> Self {
+        Self {
+            p10: percentile(sorted, 10.0),
+            p25: percentile(sorted, 25.0),
+            p75: percentile(sorted, 75.0),
+            p90: percentile(sorted, 90.0),
+        }
+    }
+}
+
+/// `p`-th percentile (0-100) of ascending `sorted`, interpolating between
+/// neighbouring values. Empty input yields 0.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let Some(last) = sorted.len().checked_sub(1) else {
+        return 0.0;
+    };
+
+    let rank = (p / 100.0).clamp(0.0, 1.0) * last as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,6 +190,28 @@ pub struct TransactionMetrics {
     
     /// Failed transactions
     pub failed_count: usize,
+
+    /// Gas actually used by each transaction, from its receipt
+    #[serde(default)]
+    pub gas_usage: Vec<TransactionGas>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionGas {
+    pub tx_hash: String,
+
+    /// Position of the transaction in its block, as given by its receipt
+    #[serde(default)]
+    pub tx_index: u64,
+
+    /// Gas consumed by the transaction
+    pub gas_used: u64,
+
+    /// Price actually paid per unit of gas (in gwei)
+    pub effective_gas_price_gwei: f64,
+
+    /// Receipt status; pre-Byzantium receipts carry none and count as successful
+    pub success: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,14 +242,18 @@ pub struct MevIndicators {
     /// DEX arbitrage opportunities
     pub arbitrage_ops: Vec<ArbitrageOp>,
     
-    /// Liquidations detected
-    pub liquidations: usize,
+    /// Lending-protocol liquidations
+    pub liquidations: Vec<Liquidation>,
     
     /// Total estimated MEV value (in ETH)
     pub estimated_mev_eth: f64,
     
     /// Known MEV bot addresses in this block
     pub mev_bot_addresses: Vec<String>,
+
+    /// Findings of detectors outside the built-in categories
+    #[serde(default)]
+    pub other_findings: Vec<DetectorFinding>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -122,6 +263,14 @@ pub struct SandwichAttack {
     pub backrun_tx: String,
     pub estimated_profit_eth: f64,
     pub dex: String,
+
+    /// Pool the three swaps went through
+    #[serde(default)]
+    pub pool: String,
+
+    /// Sender of the front-run transaction
+    #[serde(default)]
+    pub attacker: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -132,19 +281,241 @@ pub struct ArbitrageOp {
     pub dexes_involved: Vec<String>,
 }
 
+/// Finding reported by a custom MEV detector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectorFinding {
+    /// Name of the detector that reported it
+    pub detector: String,
+    pub tx_hashes: Vec<String>,
+    pub description: String,
+    pub estimated_profit_eth: f64,
+}
+
+/// A liquidation decoded from a lending protocol's event. Amounts are raw
+/// token units as emitted, since the assets' decimals aren't known here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Liquidation {
+    pub tx_hash: String,
+
+    /// Aave V2, Aave V3, Compound V2, Compound V3 or Maker
+    pub protocol: String,
+    pub liquidator: String,
+    pub borrower: String,
+
+    /// Collateral token (cToken for Compound V2, ilk name for Maker)
+    pub collateral_asset: String,
+    pub collateral_amount: String,
+
+    /// Debt token (the market or Comet for Compound, DAI for Maker)
+    pub debt_asset: String,
+    pub debt_repaid: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PbsMetrics {
     /// Was this block built via PBS?
     pub is_pbs_block: bool,
     
-    /// Builder address (if identifiable)
+    /// Fee recipient of a recognized builder (if identifiable)
     pub builder_address: Option<String>,
+
+    /// Canonical builder name and how it was matched
+    #[serde(default)]
+    pub builder_match: Option<BuilderMatch>,
     
-    /// Estimated builder payment to proposer (in ETH)
+    /// Builder payment to proposer: value of the payment transaction (in ETH)
     pub builder_payment_eth: Option<f64>,
     
     /// Extra data field (often contains builder info)
     pub extra_data: String,
+
+    /// Hash of the builder -> proposer payment transaction
+    #[serde(default)]
+    pub payment_tx: Option<String>,
+
+    /// Proposer fee recipient receiving the payment
+    #[serde(default)]
+    pub proposer_fee_recipient: Option<String>,
+
+    /// What the builder kept: priority fees + direct transfers - payment (in ETH)
+    #[serde(default)]
+    pub builder_margin_eth: Option<f64>,
+
+    /// Exact `builder_payment_eth`, in wei (decimal string)
+    #[serde(default)]
+    pub builder_payment_wei: Option<String>,
+
+    /// Exact `builder_margin_eth`, in wei (decimal string, negative when the
+    /// builder paid out more than it earned)
+    #[serde(default)]
+    pub builder_margin_wei: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuilderMatch {
+    /// Canonical builder name from the registry
+    pub name: String,
+
+    /// Which block field identified the builder
+    pub source: MatchSource,
+
+    pub confidence: MatchConfidence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchSource {
+    FeeRecipient,
+    ExtraData,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchConfidence {
+    /// Extra_data names a different builder than the fee recipient
+    Low,
+    /// Only the self-reported extra_data matched
+    Medium,
+    /// The fee recipient matched
+    High,
+}
+
+impl fmt::Display for MatchSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MatchSource::FeeRecipient => "fee_recipient",
+            MatchSource::ExtraData => "extra_data",
+            MatchSource::Both => "both",
+        })
+    }
+}
+
+impl fmt::Display for MatchConfidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MatchConfidence::Low => "low",
+            MatchConfidence::Medium => "medium",
+            MatchConfidence::High => "high",
+        })
+    }
+}
+
+/// Known address and what it belongs to.
+///
+/// Labels compare and hash by address only, so a set of labels can be
+/// queried with a plain (lowercase) address string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressLabel {
+    pub address: String,
+
+    pub category: LabelCategory,
+
+    /// Human-readable owner, e.g. "Uniswap V2 Router"
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl PartialEq for AddressLabel {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+    }
+}
+
+impl Eq for AddressLabel {}
+
+impl Hash for AddressLabel {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address.hash(state);
+    }
+}
+
+impl Borrow<str> for AddressLabel {
+    fn borrow(&self) -> &str {
+        &self.address
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelCategory {
+    Searcher,
+    Builder,
+    Router,
+    Cex,
+    Other,
+}
+
+impl fmt::Display for LabelCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LabelCategory::Searcher => "searcher",
+            LabelCategory::Builder => "builder",
+            LabelCategory::Router => "router",
+            LabelCategory::Cex => "cex",
+            LabelCategory::Other => "other",
+        })
+    }
+}
+
+impl fmt::Display for AddressLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({}, {})", self.address, name, self.category),
+            None => write!(f, "{} ({})", self.address, self.category),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderDisagreement {
+    /// Endpoint that disagreed (host only, API keys are stripped)
+    pub endpoint: String,
+
+    /// Hash returned by the endpoint the block was fetched from
+    pub expected_hash: String,
+
+    /// Hash returned by the disagreeing endpoint
+    pub reported_hash: String,
+}
+
+/// Chain reorganization observed while following the head
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReorgEvent {
+    /// Block that replaced a stored block, or whose parent didn't match the
+    /// stored chain
+    pub detected_at: u64,
+
+    /// Last block shared by both chains (`None` if the fork is older than the window)
+    pub common_ancestor: Option<u64>,
+
+    /// Number of blocks replaced
+    pub depth: usize,
+
+    /// Hashes dropped from the chain, oldest first
+    pub orphaned: Vec<String>,
+
+    /// Hashes that replaced them, oldest first
+    pub replacement: Vec<String>,
+}
+
+impl fmt::Display for ReorgEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use colored::Colorize;
+
+        writeln!(f, "\n{}", "CHAIN REORG".red().bold())?;
+        writeln!(f, "  Detected at block: {}", self.detected_at)?;
+        match self.common_ancestor {
+            Some(ancestor) => writeln!(f, "  Common ancestor: {}", ancestor)?,
+            None => writeln!(f, "  Common ancestor: beyond tracked window")?,
+        }
+        writeln!(f, "  Depth: {}", self.depth)?;
+        for (old, new) in self.orphaned.iter().zip(&self.replacement) {
+            writeln!(f, "  {} -> {}", old, new)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl fmt::Display for BlockLifecycle {
@@ -159,6 +530,12 @@ impl fmt::Display for BlockLifecycle {
         
         writeln!(f, "\n{}", "TIMING METRICS".green().bold())?;
         writeln!(f, "  Block Time: {:.2}s", self.timing.block_time)?;
+        if let Some(delay) = self.timing.propagation_delay {
+            writeln!(f, "  Propagation Delay: {:.3}s after slot start", delay)?;
+        }
+        if let Some(delay) = self.timing.timestamp_delay {
+            writeln!(f, "  Arrival: {:.3}s after block timestamp", delay)?;
+        }
         
         writeln!(f, "\n{}", "GAS METRICS".green().bold())?;
         writeln!(f, "  Gas Used: {} / {} ({:.1}%)", 
@@ -168,6 +545,11 @@ impl fmt::Display for BlockLifecycle {
         )?;
         writeln!(f, "  Base Fee: {:.2} gwei", self.gas.base_fee_gwei)?;
         writeln!(f, "  Avg Priority Fee: {:.2} gwei", self.gas.avg_priority_fee_gwei)?;
+        writeln!(f, "  Median Priority Fee: {:.2} gwei (p10 {:.2}, p90 {:.2})",
+            self.gas.median_priority_fee_gwei,
+            self.gas.priority_fee_percentiles.p10,
+            self.gas.priority_fee_percentiles.p90,
+        )?;
         writeln!(f, "  Fees Burned: {:.4} ETH", self.gas.fees_burned_eth)?;
         writeln!(f, "  Priority Fees: {:.4} ETH", self.gas.priority_fees_eth)?;
         
@@ -184,13 +566,49 @@ impl fmt::Display for BlockLifecycle {
         writeln!(f, "\n{}", "MEV INDICATORS".green().bold())?;
         writeln!(f, "  Sandwich Attacks: {}", self.mev.sandwich_attacks.len())?;
         writeln!(f, "  Arbitrage Ops: {}", self.mev.arbitrage_ops.len())?;
-        writeln!(f, "  Liquidations: {}", self.mev.liquidations)?;
+        writeln!(f, "  Liquidations: {}", self.mev.liquidations.len())?;
+        for liquidation in &self.mev.liquidations {
+            writeln!(f, "    {} {} liquidated by {}",
+                liquidation.protocol, liquidation.borrower, liquidation.liquidator
+            )?;
+        }
+        for finding in &self.mev.other_findings {
+            writeln!(f, "  [{}] {}", finding.detector, finding.description)?;
+        }
         writeln!(f, "  Estimated MEV: {:.4} ETH", self.mev.estimated_mev_eth)?;
         
         writeln!(f, "\n{}", "PBS METRICS".green().bold())?;
         writeln!(f, "  PBS Block: {}", if self.pbs.is_pbs_block { "Yes" } else { "No" })?;
-        if let Some(builder) = &self.pbs.builder_address {
-            writeln!(f, "  Builder: {}", builder)?;
+        if let Some(builder) = &self.pbs.builder_match {
+            writeln!(f, "  Builder: {} (matched by {}, {} confidence)",
+                builder.name, builder.source, builder.confidence
+            )?;
+        }
+        if let Some(address) = &self.pbs.builder_address {
+            writeln!(f, "  Builder Address: {}", address)?;
+        }
+        if let Some(payment) = self.pbs.builder_payment_eth {
+            writeln!(f, "  Proposer Payment: {:.4} ETH", payment)?;
+        }
+        if let Some(recipient) = &self.pbs.proposer_fee_recipient {
+            writeln!(f, "  Proposer Fee Recipient: {}", recipient)?;
+        }
+        if let Some(margin) = self.pbs.builder_margin_eth {
+            writeln!(f, "  Builder Margin: {:.4} ETH", margin)?;
+        }
+
+        if !self.labels.is_empty() {
+            writeln!(f, "\n{}", "KNOWN ADDRESSES".green().bold())?;
+            for label in &self.labels {
+                writeln!(f, "  {}", label)?;
+            }
+        }
+
+        if !self.provider_disagreements.is_empty() {
+            writeln!(f, "\n{}", "PROVIDER DISAGREEMENTS".red().bold())?;
+            for disagreement in &self.provider_disagreements {
+                writeln!(f, "  {}: {}", disagreement.endpoint, disagreement.reported_hash)?;
+            }
         }
         
         Ok(())
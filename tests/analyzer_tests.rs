@@ -0,0 +1,586 @@
+use blockstream_inspector::analyzer::{AnalysisEvent, BlockAnalyzer};
+use blockstream_inspector::dex::{topic, weth};
+use blockstream_inspector::fixtures::RpcFixtures;
+use blockstream_inspector::mev::DetectorRegistry;
+use blockstream_inspector::rpc::RpcConfig;
+use blockstream_inspector::types::BlockLifecycle;
+use ethers::types::{Address, H256, U256};
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+mod common;
+use common::spawn_mock_node;
+
+const GWEI: u64 = 1_000_000_000;
+const BASE_FEE: u64 = 10 * GWEI;
+
+fn address(byte: u8) -> Address {
+    Address::repeat_byte(byte)
+}
+
+/// Mock transaction; every transaction pays a 2 gwei tip and uses 21000 of
+/// its 30000 gas limit
+struct MockTx {
+    from: Address,
+    to: Address,
+    value: u128,
+    logs: Vec<Value>,
+}
+
+impl MockTx {
+    fn transfer(from: Address, to: Address, value: u128) -> Self {
+        MockTx {
+            from,
+            to,
+            value,
+            logs: Vec::new(),
+        }
+    }
+}
+
+fn word(value: u128) -> String {
+    format!("{:064x}", U256::from(value))
+}
+
+fn address_topic(address: Address) -> H256 {
+    H256::from(address)
+}
+
+fn transfer_log(token: Address, from: Address, to: Address, value: u128) -> Value {
+    json!({
+        "address": token,
+        "topics": [topic("Transfer(address,address,uint256)"), address_topic(from), address_topic(to)],
+        "data": format!("0x{}", word(value)),
+    })
+}
+
+/// Uniswap V2 swap in `pool` (token0 = WETH, token1 = `token`) with the
+/// matching transfers; `buy` swaps WETH for the token
+fn v2_swap(
+    pool: Address,
+    token: Address,
+    trader: Address,
+    buy: bool,
+    amount_in: u128,
+    amount_out: u128,
+) -> Vec<Value> {
+    let (token_in, token_out) = if buy {
+        (weth(), token)
+    } else {
+        (token, weth())
+    };
+    let amounts = if buy {
+        [amount_in, 0, 0, amount_out]
+    } else {
+        [0, amount_in, amount_out, 0]
+    };
+
+    vec![
+        transfer_log(token_in, trader, pool, amount_in),
+        transfer_log(token_out, pool, trader, amount_out),
+        json!({
+            "address": pool,
+            "topics": [
+                topic("Swap(address,uint256,uint256,uint256,uint256,address)"),
+                address_topic(trader),
+                address_topic(trader),
+            ],
+            "data": format!("0x{}", amounts.map(word).concat()),
+        }),
+    ]
+}
+
+fn tx_hash(index: usize) -> H256 {
+    H256::from_low_u64_be(index as u64 + 1)
+}
+
+fn block_json(number: u64, miner: Address, txs: &[MockTx]) -> Value {
+    let transactions: Vec<Value> = txs
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| {
+            json!({
+                "hash": tx_hash(i),
+                "nonce": "0x0",
+                "from": tx.from,
+                "to": tx.to,
+                "value": format!("0x{:x}", tx.value),
+                "gas": "0x7530",
+                "gasPrice": format!("0x{:x}", BASE_FEE + 2 * GWEI),
+                "maxFeePerGas": format!("0x{:x}", BASE_FEE + 2 * GWEI),
+                "maxPriorityFeePerGas": format!("0x{:x}", 2 * GWEI),
+                "type": "0x2",
+                "input": "0x",
+                "transactionIndex": format!("0x{:x}", i),
+                "v": "0x1",
+                "r": "0x1",
+                "s": "0x1",
+            })
+        })
+        .collect();
+
+    json!({
+        "number": format!("0x{:x}", number),
+        "hash": H256::from_low_u64_be(number),
+        "parentHash": H256::from_low_u64_be(number - 1),
+        "timestamp": format!("0x{:x}", 1_700_000_000 + number * 12),
+        "miner": miner,
+        "extraData": "0x",
+        "gasUsed": format!("0x{:x}", 21_000 * txs.len()),
+        "gasLimit": "0x1c9c380",
+        "baseFeePerGas": format!("0x{:x}", BASE_FEE),
+        "transactions": transactions,
+    })
+}
+
+fn receipts_json(txs: &[MockTx]) -> Value {
+    let receipts: Vec<Value> = txs
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| {
+            json!({
+                "transactionHash": tx_hash(i),
+                "transactionIndex": format!("0x{:x}", i),
+                "from": tx.from,
+                "to": tx.to,
+                "cumulativeGasUsed": format!("0x{:x}", 21_000 * (i + 1)),
+                "gasUsed": "0x5208",
+                "effectiveGasPrice": format!("0x{:x}", BASE_FEE + 2 * GWEI),
+                "logs": tx.logs,
+                "logsBloom": format!("0x{}", "00".repeat(256)),
+                "status": "0x1",
+            })
+        })
+        .collect();
+
+    Value::Array(receipts)
+}
+
+/// Serve `txs` as block 100 (and empty parents below it) and analyze it
+async fn analyze_mock_block(miner: Address, txs: Vec<MockTx>) -> BlockLifecycle {
+    analyze_mock_block_with(miner, txs, DetectorRegistry::default()).await
+}
+
+async fn analyze_mock_block_with(
+    miner: Address,
+    txs: Vec<MockTx>,
+    detectors: DetectorRegistry,
+) -> BlockLifecycle {
+    let analyzer = mock_analyzer(miner, txs).await.with_detectors(detectors);
+    let mut results = analyzer.analyze_range(100, 100).await.unwrap();
+    results.pop().unwrap()
+}
+
+/// Analyzer connected to a node whose head is block 100, holding `txs`
+async fn mock_analyzer(miner: Address, txs: Vec<MockTx>) -> BlockAnalyzer {
+    let node = mock_node(miner, txs).await;
+    BlockAnalyzer::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap()
+}
+
+/// Mock node whose head is block 100, holding `txs`
+async fn mock_node(miner: Address, txs: Vec<MockTx>) -> String {
+    spawn_mock_node(move |method, params| match method {
+        "eth_blockNumber" => json!("0x64"),
+        "eth_getBlockByNumber" => {
+            let number =
+                u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16)
+                    .unwrap();
+            if number == 100 {
+                block_json(number, miner, &txs)
+            } else {
+                block_json(number, miner, &[])
+            }
+        }
+        "eth_getBlockReceipts" => receipts_json(&txs),
+        _ => Value::Null,
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_priority_fees_use_receipt_gas() {
+    let user = address(0x11);
+    let txs = vec![
+        MockTx::transfer(user, address(0x22), 0),
+        MockTx::transfer(user, address(0x33), 0),
+    ];
+
+    let lifecycle = analyze_mock_block(address(0xbb), txs).await;
+
+    // 2 gwei tip * 21000 gas * 2 txs
+    assert!((lifecycle.gas.priority_fees_eth - 0.000084).abs() < 1e-12);
+    assert_eq!(
+        lifecycle.gas.priority_fees_wei.as_deref(),
+        Some("84000000000000")
+    );
+    assert!((lifecycle.gas.median_priority_fee_gwei - 2.0).abs() < 1e-9);
+    assert_eq!(lifecycle.transactions.failed_count, 0);
+
+    let indexes: Vec<u64> = lifecycle
+        .transactions
+        .gas_usage
+        .iter()
+        .map(|tx| tx.tx_index)
+        .collect();
+    assert_eq!(indexes, vec![0, 1]);
+}
+
+#[tokio::test]
+async fn test_searcher_estimate_uses_paid_tips() {
+    let searcher: Address = "0x0000000000007f150bd6f54c40a34d7c3d5e9f56"
+        .parse()
+        .unwrap();
+    let lifecycle = analyze_mock_block(
+        address(0x99),
+        vec![MockTx::transfer(searcher, address(0x22), 0)],
+    )
+    .await;
+
+    // 2 gwei tip * 21000 gas used, not the 30000 gas limit
+    assert!((lifecycle.mev.estimated_mev_eth - 0.000042).abs() < 1e-12);
+}
+
+/// Builder 0xbb, tipped 0.1 ETH directly, pays proposer 0xcc 0.05 ETH
+fn pbs_payment_txs() -> Vec<MockTx> {
+    let user = address(0x11);
+    let eth = 1_000_000_000_000_000_000u128;
+
+    vec![
+        MockTx::transfer(user, address(0x22), 0),
+        // Coinbase tip paid straight to the builder
+        MockTx::transfer(user, address(0xbb), eth / 10),
+        MockTx::transfer(address(0xbb), address(0xcc), eth / 20),
+    ]
+}
+
+#[tokio::test]
+async fn test_detects_proposer_payment() {
+    let proposer = address(0xcc);
+    let lifecycle = analyze_mock_block(address(0xbb), pbs_payment_txs()).await;
+    let pbs = &lifecycle.pbs;
+
+    assert!(pbs.is_pbs_block);
+    assert_eq!(pbs.payment_tx, Some(format!("{:?}", tx_hash(2))));
+    assert_eq!(pbs.proposer_fee_recipient, Some(format!("{:?}", proposer)));
+    assert!((pbs.builder_payment_eth.unwrap() - 0.05).abs() < 1e-12);
+
+    // 3 * 2 gwei * 21000 in tips + 0.1 transferred - 0.05 paid out
+    assert!((pbs.builder_margin_eth.unwrap() - 0.050126).abs() < 1e-9);
+    assert_eq!(
+        pbs.builder_payment_wei.as_deref(),
+        Some("50000000000000000")
+    );
+    assert_eq!(pbs.builder_margin_wei.as_deref(), Some("50126000000000000"));
+}
+
+#[tokio::test]
+async fn test_no_payment_in_locally_built_block() {
+    let user = address(0x11);
+    let txs = vec![MockTx::transfer(user, address(0x22), 1_000)];
+
+    let lifecycle = analyze_mock_block(address(0xbb), txs).await;
+
+    assert!(!lifecycle.pbs.is_pbs_block);
+    assert_eq!(lifecycle.pbs.builder_payment_eth, None);
+    assert_eq!(lifecycle.pbs.builder_margin_eth, None);
+}
+
+/// Attacker 0xa1 buys, victim 0x11 buys, attacker sells for 0.2 ETH profit
+fn sandwich_txs() -> Vec<MockTx> {
+    let pool = address(0x50);
+    let token = address(0x70);
+    let eth = 1_000_000_000_000_000_000u128;
+
+    // The attacker goes through its own bot contract, the victim through a router
+    let swap_tx = |from: Address, to: Address, buy: bool, amount_in: u128, amount_out: u128| MockTx {
+        from,
+        to,
+        value: 0,
+        logs: v2_swap(pool, token, from, buy, amount_in, amount_out),
+    };
+
+    vec![
+        swap_tx(address(0xa1), address(0xb0), true, eth, 1_000),
+        swap_tx(address(0x11), address(0xc0), true, 2 * eth, 1_900),
+        swap_tx(address(0xa1), address(0xb0), false, 1_000, eth + eth / 5),
+    ]
+}
+
+#[tokio::test]
+async fn test_detects_sandwich_from_swap_logs() {
+    let attacker = address(0xa1);
+    let lifecycle = analyze_mock_block(address(0xbb), sandwich_txs()).await;
+    let sandwiches = &lifecycle.mev.sandwich_attacks;
+
+    assert_eq!(sandwiches.len(), 1);
+    assert_eq!(sandwiches[0].frontrun_tx, format!("{:?}", tx_hash(0)));
+    assert_eq!(sandwiches[0].victim_tx, format!("{:?}", tx_hash(1)));
+    assert_eq!(sandwiches[0].backrun_tx, format!("{:?}", tx_hash(2)));
+    assert_eq!(sandwiches[0].attacker, format!("{:?}", attacker));
+    assert!((sandwiches[0].estimated_profit_eth - 0.2).abs() < 1e-9);
+    assert!((lifecycle.mev.estimated_mev_eth - 0.2).abs() < 1e-9);
+}
+
+/// Searcher 0xa1 buys a token in one pool and sells it in another for
+/// 0.1 ETH profit, in a single transaction
+fn arbitrage_txs() -> Vec<MockTx> {
+    let searcher = address(0xa1);
+    let token = address(0x70);
+    let eth = 1_000_000_000_000_000_000u128;
+
+    let mut logs = v2_swap(address(0x50), token, searcher, true, eth, 1_000);
+    logs.extend(v2_swap(address(0x51), token, searcher, false, 1_000, eth + eth / 10));
+
+    vec![MockTx {
+        from: searcher,
+        to: address(0xb0),
+        value: 0,
+        logs,
+    }]
+}
+
+#[tokio::test]
+async fn test_detects_arbitrage_from_swap_logs() {
+    let lifecycle = analyze_mock_block(address(0xbb), arbitrage_txs()).await;
+    let arbitrages = &lifecycle.mev.arbitrage_ops;
+
+    assert_eq!(arbitrages.len(), 1);
+    assert_eq!(arbitrages[0].tx_hash, format!("{:?}", tx_hash(0)));
+    assert_eq!(arbitrages[0].path.len(), 3);
+    assert!((arbitrages[0].estimated_profit_eth - 0.1).abs() < 1e-9);
+    assert!(lifecycle.mev.sandwich_attacks.is_empty());
+}
+
+/// Liquidator 0xa1 repays 500 units of 0xd0 debt for borrower 0xb0 in the
+/// Aave V3 pool, seizing 1000 units of 0xc0 collateral
+fn liquidation_txs() -> Vec<MockTx> {
+    let pool: Address = "0x87870bca3f3fd6335c3f4ce8392d69350b4fa4e2"
+        .parse()
+        .unwrap();
+    let liquidator = address(0xa1);
+    let call = json!({
+        "address": pool,
+        "topics": [
+            topic("LiquidationCall(address,address,address,uint256,uint256,address,bool)"),
+            address_topic(address(0xc0)),
+            address_topic(address(0xd0)),
+            address_topic(address(0xb0)),
+        ],
+        "data": format!(
+            "0x{}{}{}{}",
+            word(500),
+            word(1_000),
+            hex::encode(address_topic(liquidator)),
+            word(0)
+        ),
+    });
+
+    vec![MockTx {
+        from: liquidator,
+        to: pool,
+        value: 0,
+        logs: vec![call],
+    }]
+}
+
+#[tokio::test]
+async fn test_disabled_detector_reports_nothing() {
+    let mut detectors = DetectorRegistry::default();
+    detectors.disable("sandwich").unwrap();
+
+    let lifecycle = analyze_mock_block_with(address(0xbb), sandwich_txs(), detectors).await;
+
+    assert!(lifecycle.mev.sandwich_attacks.is_empty());
+    assert_eq!(lifecycle.mev.estimated_mev_eth, 0.0);
+}
+
+#[tokio::test]
+async fn test_range_reports_progress_events() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let events = seen.clone();
+
+    let analyzer = mock_analyzer(address(0xbb), Vec::new())
+        .await
+        .with_progress(move |event| {
+            if let AnalysisEvent::BlockAnalyzed(lifecycle) = event {
+                events.lock().unwrap().push(lifecycle.block_number);
+            }
+        });
+    let results = analyzer.analyze_range(98, 100).await.unwrap();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(*seen.lock().unwrap(), vec![98, 99, 100]);
+}
+
+#[tokio::test]
+async fn test_detect_mev_returns_summary() {
+    let analyzer = mock_analyzer(address(0xbb), sandwich_txs()).await;
+
+    let summary = analyzer.detect_mev(2, 0.1).await.unwrap();
+
+    assert_eq!(summary.blocks_analyzed, 3);
+    assert_eq!(summary.flagged.len(), 1);
+    assert_eq!(summary.flagged[0].block_number, 100);
+    assert!((summary.total_mev_eth - 0.2).abs() < 1e-9);
+    assert!((summary.average_mev_eth() - 0.2 / 3.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_replays_recorded_block_offline() {
+    let dir = std::env::temp_dir().join(format!("bsi-analyzer-fixtures-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let node = mock_node(address(0xbb), sandwich_txs()).await;
+    let recording = RpcConfig {
+        fixtures: Some(RpcFixtures::record(&dir)),
+        ..Default::default()
+    };
+    let recorder = BlockAnalyzer::with_config(&[node], recording)
+        .await
+        .unwrap();
+    let recorded = recorder.analyze_range(99, 100).await.unwrap();
+
+    // Nothing listens here, so every answer has to come from the fixtures
+    let replaying = RpcConfig {
+        fixtures: Some(RpcFixtures::replay(&dir)),
+        ..Default::default()
+    };
+    let replayer = BlockAnalyzer::with_config(&["http://127.0.0.1:9".to_string()], replaying)
+        .await
+        .unwrap();
+    let replayed = replayer.analyze_range(99, 100).await.unwrap();
+
+    assert_eq!(replayed.len(), 2);
+    assert_eq!(replayed[1].block_hash, recorded[1].block_hash);
+    assert_eq!(replayed[1].mev.sandwich_attacks.len(), 1);
+    assert_eq!(
+        serde_json::to_value(&replayed[1].mev).unwrap(),
+        serde_json::to_value(&recorded[1].mev).unwrap()
+    );
+
+    // Blocks outside the recording are an error, not a silent network call
+    assert!(replayer.analyze_range(101, 101).await.is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Committed recordings of the scenario blocks above, one directory each
+const SCENARIOS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/scenarios");
+
+/// Analyze block 100 of a committed scenario recording without a node.
+/// With `RECORD_FIXTURES` set, the recording is first made again from a mock
+/// node serving `txs`.
+async fn replay_scenario(name: &str, miner: Address, txs: Vec<MockTx>) -> BlockLifecycle {
+    let dir = std::path::Path::new(SCENARIOS).join(name);
+
+    if std::env::var_os("RECORD_FIXTURES").is_some() {
+        let _ = std::fs::remove_dir_all(&dir);
+        let recording = RpcConfig {
+            fixtures: Some(RpcFixtures::record(&dir)),
+            ..Default::default()
+        };
+        BlockAnalyzer::with_config(&[mock_node(miner, txs).await], recording)
+            .await
+            .unwrap()
+            .analyze_range(100, 100)
+            .await
+            .unwrap();
+    }
+
+    // Nothing listens here, so every answer has to come from the fixtures
+    let replaying = RpcConfig {
+        fixtures: Some(RpcFixtures::replay(&dir)),
+        ..Default::default()
+    };
+    let analyzer = BlockAnalyzer::with_config(&["http://127.0.0.1:9".to_string()], replaying)
+        .await
+        .unwrap();
+    analyzer
+        .analyze_range(100, 100)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_replays_pbs_payment_block() {
+    let lifecycle = replay_scenario("pbs_payment", address(0xbb), pbs_payment_txs()).await;
+    let pbs = &lifecycle.pbs;
+
+    assert_eq!(lifecycle.block_number, 100);
+    assert!(pbs.is_pbs_block);
+    assert_eq!(pbs.builder_address, Some(format!("{:?}", address(0xbb))));
+    assert_eq!(pbs.payment_tx, Some(format!("{:?}", tx_hash(2))));
+    assert_eq!(
+        pbs.proposer_fee_recipient,
+        Some(format!("{:?}", address(0xcc)))
+    );
+    assert_eq!(
+        pbs.builder_payment_wei.as_deref(),
+        Some("50000000000000000")
+    );
+    assert_eq!(pbs.builder_margin_wei.as_deref(), Some("50126000000000000"));
+    assert_eq!(
+        lifecycle.gas.priority_fees_wei.as_deref(),
+        Some("126000000000000")
+    );
+}
+
+#[tokio::test]
+async fn test_replays_sandwich_block() {
+    let lifecycle = replay_scenario("sandwich", address(0xbb), sandwich_txs()).await;
+    let mev = &lifecycle.mev;
+
+    assert_eq!(mev.sandwich_attacks.len(), 1);
+    let sandwich = &mev.sandwich_attacks[0];
+    assert_eq!(sandwich.frontrun_tx, format!("{:?}", tx_hash(0)));
+    assert_eq!(sandwich.victim_tx, format!("{:?}", tx_hash(1)));
+    assert_eq!(sandwich.backrun_tx, format!("{:?}", tx_hash(2)));
+    assert_eq!(sandwich.pool, format!("{:?}", address(0x50)));
+    assert_eq!(sandwich.attacker, format!("{:?}", address(0xa1)));
+    assert!((sandwich.estimated_profit_eth - 0.2).abs() < 1e-9);
+    assert!(mev.arbitrage_ops.is_empty());
+    assert!((mev.estimated_mev_eth - 0.2).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_replays_arbitrage_block() {
+    let lifecycle = replay_scenario("arbitrage", address(0xbb), arbitrage_txs()).await;
+    let mev = &lifecycle.mev;
+
+    assert_eq!(mev.arbitrage_ops.len(), 1);
+    let arbitrage = &mev.arbitrage_ops[0];
+    assert_eq!(arbitrage.tx_hash, format!("{:?}", tx_hash(0)));
+    assert_eq!(
+        arbitrage.path,
+        vec![
+            format!("{:?}", weth()),
+            format!("{:?}", address(0x70)),
+            format!("{:?}", weth()),
+        ]
+    );
+    assert!((arbitrage.estimated_profit_eth - 0.1).abs() < 1e-9);
+    assert!(mev.sandwich_attacks.is_empty());
+    assert!((mev.estimated_mev_eth - 0.1).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_replays_liquidation_block() {
+    let lifecycle = replay_scenario("liquidation", address(0xbb), liquidation_txs()).await;
+    let liquidations = &lifecycle.mev.liquidations;
+
+    assert_eq!(liquidations.len(), 1);
+    let liquidation = &liquidations[0];
+    assert_eq!(liquidation.tx_hash, format!("{:?}", tx_hash(0)));
+    assert_eq!(liquidation.protocol, "Aave V3");
+    assert_eq!(liquidation.liquidator, format!("{:?}", address(0xa1)));
+    assert_eq!(liquidation.borrower, format!("{:?}", address(0xb0)));
+    assert_eq!(liquidation.collateral_asset, format!("{:?}", address(0xc0)));
+    assert_eq!(liquidation.collateral_amount, "1000");
+    assert_eq!(liquidation.debt_asset, format!("{:?}", address(0xd0)));
+    assert_eq!(liquidation.debt_repaid, "500");
+    assert!(!lifecycle.pbs.is_pbs_block);
+}
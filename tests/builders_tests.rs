@@ -0,0 +1,88 @@
+use blockstream_inspector::builders::BuilderRegistry;
+use blockstream_inspector::types::{MatchConfidence, MatchSource};
+
+const REGISTRY: &str = r#"
+[[builder]]
+name = "Flashbots"
+fee_recipients = ["0xDAFEA492D9c6733ae3d56b7Ed1ADB60692c98Bc5"]
+extra_data = ["Flashbots"]
+
+[[builder]]
+name = "beaverbuild"
+fee_recipients = ["0x95222290dd7278aa3ddd389cc1e1d165cc4bafe5"]
+extra_data = ["beaverbuild"]
+"#;
+
+const FLASHBOTS: &str = "0xdafea492d9c6733ae3d56b7ed1adb60692c98bc5";
+
+#[test]
+fn test_match_by_fee_recipient_and_extra_data() {
+    let registry = BuilderRegistry::from_toml_str(REGISTRY).unwrap();
+    let found = registry.identify(FLASHBOTS, "Illuminate Dmocratize Dstribute (flashbots)").unwrap();
+
+    assert_eq!(found.name, "Flashbots");
+    assert_eq!(found.source, MatchSource::Both);
+    assert_eq!(found.confidence, MatchConfidence::High);
+}
+
+#[test]
+fn test_match_by_fee_recipient_only() {
+    let registry = BuilderRegistry::from_toml_str(REGISTRY).unwrap();
+    let found = registry.identify(FLASHBOTS, "").unwrap();
+
+    assert_eq!(found.source, MatchSource::FeeRecipient);
+    assert_eq!(found.confidence, MatchConfidence::High);
+}
+
+#[test]
+fn test_extra_data_only_is_medium_confidence() {
+    let registry = BuilderRegistry::from_toml_str(REGISTRY).unwrap();
+    let found = registry
+        .identify("0x0000000000000000000000000000000000000001", "beaverbuild.org")
+        .unwrap();
+
+    assert_eq!(found.name, "beaverbuild");
+    assert_eq!(found.source, MatchSource::ExtraData);
+    assert_eq!(found.confidence, MatchConfidence::Medium);
+}
+
+#[test]
+fn test_conflicting_extra_data_lowers_confidence() {
+    let registry = BuilderRegistry::from_toml_str(REGISTRY).unwrap();
+    let found = registry.identify(FLASHBOTS, "beaverbuild.org").unwrap();
+
+    // The fee recipient wins, but the block claims another builder
+    assert_eq!(found.name, "Flashbots");
+    assert_eq!(found.confidence, MatchConfidence::Low);
+}
+
+#[test]
+fn test_unknown_builder() {
+    let registry = BuilderRegistry::from_toml_str(REGISTRY).unwrap();
+    assert!(registry.identify("0x0000000000000000000000000000000000000001", "geth").is_none());
+}
+
+#[test]
+fn test_load_json_registry() {
+    let path = "/tmp/test_builders.json";
+    std::fs::write(
+        path,
+        r#"{"builder": [{"name": "Titan", "fee_recipients": ["0x4838b106fce9647bdf1e7877bf73ce8b0bad5f97"]}]}"#,
+    )
+    .unwrap();
+
+    let registry = BuilderRegistry::load(path).unwrap();
+    let found = registry
+        .identify("0x4838b106fce9647bdf1e7877bf73ce8b0bad5f97", "")
+        .unwrap();
+    assert_eq!(found.name, "Titan");
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_default_registry_is_valid() {
+    let registry = BuilderRegistry::default();
+    assert!(!registry.is_empty());
+    assert!(registry.identify(FLASHBOTS, "").is_some());
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use blockstream_inspector::analyzer::BlockAnalyzer;
+use blockstream_inspector::cache::{BlockCache, CachedSource};
+use blockstream_inspector::source::{BlockSource, FixtureSource};
+use ethers::types::{Block, H256, Trace, Transaction, TransactionReceipt};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+use common::{fixture_source, temp_dir};
+
+/// Fixture source holding blocks 1..=head that counts every call made to it
+struct CountingSource {
+    blocks: FixtureSource,
+    calls: AtomicUsize,
+}
+
+impl CountingSource {
+    fn new(head: u64) -> Self {
+        Self {
+            blocks: fixture_source(1..=head),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn count(&self) {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl BlockSource for CountingSource {
+    async fn get_block(&self, number: u64) -> Result<Option<Block<Transaction>>> {
+        self.count();
+        self.blocks.get_block(number).await
+    }
+
+    async fn get_block_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Vec<TransactionReceipt>> {
+        self.count();
+        self.blocks.get_block_receipts(block).await
+    }
+
+    async fn get_block_traces(&self, number: u64) -> Result<Vec<Trace>> {
+        self.count();
+        self.blocks.get_block_traces(number).await
+    }
+
+    async fn get_latest_block_number(&self) -> Result<u64> {
+        self.count();
+        self.blocks.get_latest_block_number().await
+    }
+}
+
+#[tokio::test]
+async fn test_repeated_range_makes_no_source_calls() {
+    let dir = temp_dir("cache_repeat");
+    // Head 100, so blocks up to 36 are final
+    let source = CachedSource::new(CountingSource::new(100), BlockCache::open(&dir).unwrap());
+    let analyzer = BlockAnalyzer::with_source(source);
+
+    let first = analyzer.analyze_range(10, 20).await.unwrap();
+    assert!(analyzer.source().inner().calls() > 0);
+
+    let calls = analyzer.source().inner().calls();
+    let second = analyzer.analyze_range(10, 20).await.unwrap();
+
+    assert_eq!(analyzer.source().inner().calls(), calls);
+    assert_eq!(second.len(), 11);
+    assert_eq!(second[0].block_hash, first[0].block_hash);
+    assert_eq!(second[0].gas.gas_used, first[0].gas.gas_used);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_cache_survives_reopening() {
+    let dir = temp_dir("cache_reopen");
+    let source = CachedSource::new(CountingSource::new(100), BlockCache::open(&dir).unwrap());
+    BlockAnalyzer::with_source(source)
+        .analyze_range(10, 12)
+        .await
+        .unwrap();
+
+    let cache = BlockCache::open(&dir).unwrap();
+    let stats = cache.stats();
+    assert_eq!(stats.blocks, 4); // 9 is read as the parent of 10
+    assert_eq!(stats.first_block, Some(9));
+    assert_eq!(stats.last_block, Some(12));
+    assert!(stats.bytes > 0);
+
+    let source = CachedSource::new(CountingSource::new(100), cache);
+    let block = source.get_block(11).await.unwrap().unwrap();
+    assert_eq!(source.get_block_receipts(&block).await.unwrap().len(), 2);
+    assert_eq!(
+        source.get_block_hash(12).await.unwrap(),
+        Some(H256::from_low_u64_be(12))
+    );
+    assert_eq!(source.inner().calls(), 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_unfinalized_blocks_are_not_cached() {
+    let dir = temp_dir("cache_unfinalized");
+    let source = CachedSource::new(CountingSource::new(100), BlockCache::open(&dir).unwrap());
+
+    source.get_block(36).await.unwrap();
+    source.get_block(37).await.unwrap();
+
+    assert!(source.cache().contains(36));
+    assert!(!source.cache().contains(37));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_size_limit_evicts_least_recently_used() {
+    let dir = temp_dir("cache_limit");
+    let source = CachedSource::new(CountingSource::new(100), BlockCache::open(&dir).unwrap());
+    for number in 1..=3 {
+        source.get_block(number).await.unwrap();
+    }
+
+    // Fill a limited cache: reading block 1 again makes 2 the oldest
+    let entry_size = source.cache().stats().bytes / 3;
+    let cache = BlockCache::open(&dir)
+        .unwrap()
+        .with_max_bytes(entry_size * 3 + entry_size / 2);
+    let source = CachedSource::new(CountingSource::new(100), cache);
+    source.get_block(1).await.unwrap();
+    source.get_block(4).await.unwrap();
+
+    let cache = source.cache();
+    assert!(cache.contains(1));
+    assert!(!cache.contains(2));
+    assert!(cache.contains(3));
+    assert!(cache.contains(4));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_prune_below_and_clear() {
+    let dir = temp_dir("cache_prune");
+    let source = CachedSource::new(CountingSource::new(100), BlockCache::open(&dir).unwrap());
+    for number in 1..=5 {
+        source.get_block(number).await.unwrap();
+    }
+
+    let cache = source.cache();
+    assert_eq!(cache.prune_below(3).unwrap(), 2);
+    assert_eq!(cache.stats().first_block, Some(3));
+    assert_eq!(cache.clear().unwrap(), 3);
+    assert_eq!(BlockCache::open(&dir).unwrap().stats().blocks, 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
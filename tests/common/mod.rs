@@ -0,0 +1,282 @@
+//! Mock JSON-RPC servers and test data shared by the integration tests
+
+#![allow(dead_code)]
+
+use blockstream_inspector::source::FixtureSource;
+use blockstream_inspector::types::*;
+use ethers::types::{Address, Block, H256, Transaction, TransactionReceipt, U64, U256};
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Read one HTTP request from the socket and return its JSON body
+async fn read_json_body(socket: &mut tokio::net::TcpStream) -> Value {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let n = socket.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+
+        let text = String::from_utf8_lossy(&buf);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+
+            if buf.len() >= header_end + 4 + content_length {
+                return serde_json::from_slice(&buf[header_end + 4..]).unwrap();
+            }
+        }
+    }
+}
+
+/// Raw HTTP reply sent by the mock node
+pub struct Reply {
+    pub status: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Value,
+}
+
+impl Reply {
+    pub fn ok(body: Value) -> Self {
+        Reply {
+            status: "200 OK",
+            headers: Vec::new(),
+            body,
+        }
+    }
+}
+
+/// Spawn an HTTP server that answers the n-th request (0-based) with `reply_for(n, body)`
+pub async fn spawn_http_mock<F>(reply_for: F) -> String
+where
+    F: Fn(usize, &Value) -> Reply + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        for n in 0.. {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_json_body(&mut socket).await;
+            let reply = reply_for(n, &request);
+
+            let body = reply.body.to_string();
+            let mut response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                reply.status,
+                body.len()
+            );
+            for (name, value) in &reply.headers {
+                response.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            response.push_str("\r\n");
+            response.push_str(&body);
+
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    format!("http://{}", addr)
+}
+
+/// Spawn a JSON-RPC server that answers every call with `result_for(method, params)`.
+/// Batch answers are sent back in reverse order.
+pub async fn spawn_mock_node<F>(result_for: F) -> String
+where
+    F: Fn(&str, &Value) -> Value + Send + Sync + 'static,
+{
+    spawn_mock_node_with_errors(move |method, params| Ok(result_for(method, params))).await
+}
+
+/// Like [`spawn_mock_node`], but `answer_for` may return a JSON-RPC error object
+pub async fn spawn_mock_node_with_errors<F>(answer_for: F) -> String
+where
+    F: Fn(&str, &Value) -> Result<Value, Value> + Send + Sync + 'static,
+{
+    spawn_http_mock(move |_, request| {
+        let answer = |call: &Value| match answer_for(call["method"].as_str().unwrap(), &call["params"]) {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": call["id"], "result": result}),
+            Err(error) => json!({"jsonrpc": "2.0", "id": call["id"], "error": error}),
+        };
+
+        Reply::ok(match request {
+            Value::Array(calls) => Value::Array(calls.iter().rev().map(answer).collect()),
+            call => answer(call),
+        })
+    })
+    .await
+}
+
+/// Fully populated flashbots-built block with two transactions (one failed),
+/// a sandwich and a liquidation
+pub fn create_test_block(number: u64) -> BlockLifecycle {
+    BlockLifecycle {
+        block_number: number,
+        block_hash: format!("0x{:064x}", number),
+        parent_hash: format!("0x{:064x}", number - 1),
+        timestamp: 1698765432,
+        proposer: "0xabcdef".to_string(),
+        builder: Some("flashbots".to_string()),
+        timing: TimingMetrics {
+            block_time: 12.0,
+            timestamp: 1698765432,
+            propagation_delay: None,
+            slot: None,
+            received_at: None,
+            timestamp_delay: None,
+        },
+        gas: GasMetrics {
+            gas_used: 29834521,
+            gas_limit: 30000000,
+            utilization: 99.45,
+            base_fee_gwei: 25.34,
+            avg_priority_fee_gwei: 1.52,
+            median_priority_fee_gwei: 1.2,
+            priority_fee_percentiles: FeePercentiles::default(),
+            fees_burned_eth: 0.7563,
+            priority_fees_eth: 0.0453,
+            fees_burned_wei: Some("756300000000000000".to_string()),
+            priority_fees_wei: Some("45300000000000000".to_string()),
+        },
+        transactions: TransactionMetrics {
+            total_count: 2,
+            type_breakdown: TypeBreakdown {
+                legacy: 0,
+                eip2930: 0,
+                eip1559: 2,
+                eip4844_blob: 0,
+            },
+            ordering: OrderingMetrics {
+                sorted_by_priority: true,
+                anomalies: 0,
+                avg_deviation: 0.0,
+            },
+            failed_count: 1,
+            gas_usage: vec![
+                TransactionGas {
+                    tx_hash: "0xt1".to_string(),
+                    tx_index: 0,
+                    gas_used: 21000,
+                    effective_gas_price_gwei: 27.0,
+                    success: true,
+                },
+                TransactionGas {
+                    tx_hash: "0xt2".to_string(),
+                    tx_index: 1,
+                    gas_used: 50000,
+                    effective_gas_price_gwei: 26.0,
+                    success: false,
+                },
+            ],
+        },
+        mev: MevIndicators {
+            sandwich_attacks: vec![SandwichAttack {
+                frontrun_tx: "0xf1".to_string(),
+                victim_tx: "0xv1".to_string(),
+                backrun_tx: "0xb1".to_string(),
+                estimated_profit_eth: 0.2,
+                dex: "Uniswap V2".to_string(),
+                pool: "0xpool".to_string(),
+                attacker: "0xbot".to_string(),
+            }],
+            arbitrage_ops: vec![],
+            liquidations: vec![Liquidation {
+                tx_hash: "0xl1".to_string(),
+                protocol: "Aave V3".to_string(),
+                liquidator: "0xliq".to_string(),
+                borrower: "0xborrower".to_string(),
+                collateral_asset: "0xweth".to_string(),
+                collateral_amount: "1000".to_string(),
+                debt_asset: "0xusdc".to_string(),
+                debt_repaid: "2000".to_string(),
+            }],
+            estimated_mev_eth: 0.2,
+            mev_bot_addresses: vec!["0xbot".to_string()],
+            other_findings: vec![],
+        },
+        pbs: PbsMetrics {
+            is_pbs_block: true,
+            builder_address: Some("0xbuilder".to_string()),
+            builder_match: None,
+            builder_payment_eth: Some(0.05),
+            extra_data: "flashbots".to_string(),
+            payment_tx: None,
+            proposer_fee_recipient: None,
+            builder_margin_eth: None,
+            builder_payment_wei: Some("50000000000000000".to_string()),
+            builder_margin_wei: None,
+        },
+        provider_disagreements: vec![],
+        labels: vec![],
+    }
+}
+
+const GWEI: u64 = 1_000_000_000;
+
+/// Chain block with `txs` plain transfers, whose parent is block `number - 1`
+pub fn test_block(number: u64, txs: usize) -> Block<Transaction> {
+    let transactions = (0..txs)
+        .map(|i| Transaction {
+            hash: H256::from_low_u64_be(number * 1000 + i as u64),
+            from: Address::repeat_byte(0x11),
+            gas: U256::from(21_000),
+            transaction_index: Some(U64::from(i)),
+            ..Default::default()
+        })
+        .collect();
+
+    Block {
+        number: Some(U64::from(number)),
+        hash: Some(H256::from_low_u64_be(number)),
+        parent_hash: H256::from_low_u64_be(number.saturating_sub(1)),
+        timestamp: U256::from(1_700_000_000 + number * 12),
+        gas_used: U256::from(21_000 * txs),
+        gas_limit: U256::from(30_000_000),
+        base_fee_per_gas: Some(U256::from(10 * GWEI)),
+        transactions,
+        ..Default::default()
+    }
+}
+
+/// Receipts paying a 2 gwei tip on 21000 gas each
+pub fn test_receipts(block: &Block<Transaction>) -> Vec<TransactionReceipt> {
+    block
+        .transactions
+        .iter()
+        .map(|tx| TransactionReceipt {
+            transaction_hash: tx.hash,
+            transaction_index: tx.transaction_index.unwrap_or_default(),
+            from: tx.from,
+            gas_used: Some(U256::from(21_000)),
+            effective_gas_price: Some(U256::from(12 * GWEI)),
+            status: Some(U64::one()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Fixture source holding the given blocks, two transactions each
+pub fn fixture_source(numbers: impl IntoIterator<Item = u64>) -> FixtureSource {
+    numbers
+        .into_iter()
+        .fold(FixtureSource::new(), |source, number| {
+            let block = test_block(number, 2);
+            let receipts = test_receipts(&block);
+            source.with_block(block, receipts)
+        })
+}
+
+/// Fresh directory path under the system temp dir, unique to this process
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("blockstream_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
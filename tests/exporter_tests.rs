@@ -1,12 +1,16 @@
 
 use blockstream_inspector::types::*;
-use blockstream_inspector::exporter::Exporter;
+use blockstream_inspector::exporter::{Exporter, JsonLinesWriter, reorgs_path};
+use arrow::array::{Array, AsArray};
+use arrow::datatypes::{Decimal128Type, Float64Type, UInt64Type};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use std::fs;
 
 fn create_test_block() -> BlockLifecycle {
     BlockLifecycle {
         block_number: 18000000,
         block_hash: "0x1234567890abcdef".to_string(),
+        parent_hash: "0xabcdef".to_string(),
         timestamp: 1698765432,
         proposer: "0xabcdef".to_string(),
         builder: Some("flashbots".to_string()),
@@ -14,6 +18,9 @@ fn create_test_block() -> BlockLifecycle {
             block_time: 12.05,
             timestamp: 1698765432,
             propagation_delay: None,
+            slot: None,
+            received_at: None,
+            timestamp_delay: None,
         },
         gas: GasMetrics {
             gas_used: 29834521,
@@ -21,8 +28,12 @@ fn create_test_block() -> BlockLifecycle {
             utilization: 99.45,
             base_fee_gwei: 25.34,
             avg_priority_fee_gwei: 1.52,
+            median_priority_fee_gwei: 1.2,
+            priority_fee_percentiles: FeePercentiles::default(),
             fees_burned_eth: 0.7563,
             priority_fees_eth: 0.0453,
+            fees_burned_wei: Some("756300000000000000".to_string()),
+            priority_fees_wei: Some("45300000000000000".to_string()),
         },
         transactions: TransactionMetrics {
             total_count: 247,
@@ -38,20 +49,30 @@ fn create_test_block() -> BlockLifecycle {
                 avg_deviation: 0.5,
             },
             failed_count: 3,
+            gas_usage: vec![],
         },
         mev: MevIndicators {
             sandwich_attacks: vec![],
             arbitrage_ops: vec![],
-            liquidations: 2,
+            liquidations: vec![],
             estimated_mev_eth: 2.3451,
             mev_bot_addresses: vec!["0x123".to_string()],
+            other_findings: vec![],
         },
         pbs: PbsMetrics {
             is_pbs_block: true,
             builder_address: Some("flashbots".to_string()),
+            builder_match: None,
             builder_payment_eth: Some(0.05),
             extra_data: "flashbots".to_string(),
+            payment_tx: None,
+            proposer_fee_recipient: None,
+            builder_margin_eth: None,
+            builder_payment_wei: Some("50000000000000000".to_string()),
+            builder_margin_wei: None,
         },
+        provider_disagreements: vec![],
+        labels: vec![],
     }
 }
 
@@ -172,3 +193,238 @@ fn test_export_with_none_values() {
     // Cleanup
     fs::remove_file(path).ok();
 }
+
+#[test]
+fn test_reorgs_path() {
+    assert_eq!(reorgs_path("data/live.csv"), "data/live_reorgs.csv");
+    assert_eq!(reorgs_path("live"), "live_reorgs");
+}
+
+#[test]
+fn test_export_reorgs() {
+    let reorgs = vec![ReorgEvent {
+        detected_at: 18000002,
+        common_ancestor: Some(18000000),
+        depth: 1,
+        orphaned: vec!["0xaaa".to_string()],
+        replacement: vec!["0xbbb".to_string()],
+    }];
+    let path = "/tmp/test_reorgs.csv";
+
+    Exporter::export_reorgs_to_csv(&reorgs, path).unwrap();
+
+    let content = fs::read_to_string(path).unwrap();
+    let data_line = content.lines().nth(1).unwrap();
+    assert_eq!(data_line, "18000002,18000000,1,0xaaa,0xbbb");
+
+    // Cleanup
+    fs::remove_file(path).ok();
+}
+
+fn read_parquet(path: &str) -> arrow::record_batch::RecordBatch {
+    let file = fs::File::open(path).unwrap();
+    let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .unwrap()
+        .build()
+        .unwrap();
+    reader.next().unwrap().unwrap()
+}
+
+#[test]
+fn test_export_parquet_keeps_types_and_nested_data() {
+    let mut block = create_test_block();
+    block.mev.sandwich_attacks = vec![SandwichAttack {
+        frontrun_tx: "0xf1".to_string(),
+        victim_tx: "0xv1".to_string(),
+        backrun_tx: "0xb1".to_string(),
+        estimated_profit_eth: 0.25,
+        dex: "Uniswap V2".to_string(),
+        pool: "0xpool".to_string(),
+        attacker: "0xbot".to_string(),
+    }];
+    block.mev.arbitrage_ops = vec![ArbitrageOp {
+        tx_hash: "0xa1".to_string(),
+        path: vec![
+            "0xweth".to_string(),
+            "0xusdc".to_string(),
+            "0xweth".to_string(),
+        ],
+        estimated_profit_eth: 0.1,
+        dexes_involved: vec!["Uniswap V2".to_string(), "Uniswap V3".to_string()],
+    }];
+    block.mev.liquidations = vec![Liquidation {
+        tx_hash: "0xl1".to_string(),
+        protocol: "Aave V3".to_string(),
+        liquidator: "0xliq".to_string(),
+        borrower: "0xborrower".to_string(),
+        collateral_asset: "0xweth".to_string(),
+        // uint256::MAX, 78 digits
+        collateral_amount:
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+                .to_string(),
+        debt_asset: "0xusdc".to_string(),
+        debt_repaid: "1000000".to_string(),
+    }];
+    // More digits than an f64 holds, and a builder that paid out more than
+    // it earned
+    block.gas.priority_fees_wei = Some("123456789012345678901".to_string());
+    block.pbs.builder_margin_wei = Some("-1".to_string());
+    let path = "/tmp/test_export.parquet";
+
+    Exporter::export_to_parquet(&[block, create_test_block()], path).unwrap();
+    let batch = read_parquet(path);
+
+    assert_eq!(batch.num_rows(), 2);
+    let numbers = batch
+        .column_by_name("block_number")
+        .unwrap()
+        .as_primitive::<UInt64Type>();
+    assert_eq!(numbers.value(0), 18000000);
+
+    // ETH amounts are fixed-point with 18 decimals
+    let burned = batch
+        .column_by_name("fees_burned_eth")
+        .unwrap()
+        .as_primitive::<Decimal128Type>();
+    assert_eq!(burned.value(0), 756_300_000_000_000_000);
+    assert_eq!(burned.scale(), 18);
+    let tips = batch
+        .column_by_name("priority_fees_eth")
+        .unwrap()
+        .as_primitive::<Decimal128Type>();
+    assert_eq!(tips.value(0), 123_456_789_012_345_678_901);
+    let margin = batch
+        .column_by_name("builder_margin_eth")
+        .unwrap()
+        .as_primitive::<Decimal128Type>();
+    assert_eq!(margin.value(0), -1);
+    assert!(margin.is_null(1));
+
+    // MEV profits are estimates, kept in floating point
+    let mev = batch
+        .column_by_name("mev_estimated_eth")
+        .unwrap()
+        .as_primitive::<Float64Type>();
+    assert_eq!(mev.value(0), 2.3451);
+
+    let sandwiches = batch
+        .column_by_name("mev_sandwich_attacks")
+        .unwrap()
+        .as_list::<i32>();
+    assert_eq!(sandwiches.value(0).len(), 1);
+    assert_eq!(sandwiches.value(1).len(), 0);
+    let sandwich = sandwiches.value(0);
+    let attacker = sandwich.as_struct().column_by_name("attacker").unwrap();
+    assert_eq!(attacker.as_string::<i32>().value(0), "0xbot");
+
+    let arbitrage = batch
+        .column_by_name("mev_arbitrage_ops")
+        .unwrap()
+        .as_list::<i32>();
+    let arbitrage = arbitrage.value(0);
+    let path_column = arbitrage.as_struct().column_by_name("path").unwrap();
+    assert_eq!(path_column.as_list::<i32>().value(0).len(), 3);
+
+    let liquidations = batch
+        .column_by_name("mev_liquidations")
+        .unwrap()
+        .as_list::<i32>();
+    let liquidation = liquidations.value(0);
+    let collateral = liquidation
+        .as_struct()
+        .column_by_name("collateral_amount")
+        .unwrap();
+    assert_eq!(
+        collateral.as_string::<i32>().value(0),
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+    );
+
+    let bots = batch
+        .column_by_name("mev_bot_addresses")
+        .unwrap()
+        .as_list::<i32>();
+    assert_eq!(bots.value(0).as_string::<i32>().value(0), "0x123");
+
+    // Cleanup
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_export_reorgs_parquet() {
+    let reorgs = vec![ReorgEvent {
+        detected_at: 18000002,
+        common_ancestor: None,
+        depth: 2,
+        orphaned: vec!["0xaaa".to_string(), "0xccc".to_string()],
+        replacement: vec!["0xbbb".to_string()],
+    }];
+    let path = "/tmp/test_reorgs.parquet";
+
+    Exporter::export_reorgs_to_parquet(&reorgs, path).unwrap();
+    let batch = read_parquet(path);
+
+    assert_eq!(batch.num_rows(), 1);
+    assert!(batch.column_by_name("common_ancestor").unwrap().is_null(0));
+    let orphaned = batch
+        .column_by_name("orphaned_hashes")
+        .unwrap()
+        .as_list::<i32>();
+    assert_eq!(orphaned.value(0).len(), 2);
+
+    // Cleanup
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_export_json_keeps_nested_records() {
+    let mut block = create_test_block();
+    block.mev.arbitrage_ops = vec![ArbitrageOp {
+        tx_hash: "0xa1".to_string(),
+        path: vec!["0xweth".to_string(), "0xusdc".to_string()],
+        estimated_profit_eth: 0.1,
+        dexes_involved: vec!["Uniswap V3".to_string()],
+    }];
+    let path = "/tmp/test_export.json";
+
+    Exporter::export_to_json(&[block], path).unwrap();
+
+    let blocks: Vec<BlockLifecycle> =
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].block_number, 18000000);
+    assert_eq!(blocks[0].mev.arbitrage_ops[0].path.len(), 2);
+    assert_eq!(blocks[0].mev.mev_bot_addresses, vec!["0x123".to_string()]);
+
+    // Cleanup
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_json_lines_are_written_as_they_arrive() {
+    let path = "/tmp/test_export_stream.jsonl";
+    let mut writer = JsonLinesWriter::create(path).unwrap();
+
+    writer.write(&create_test_block());
+    // Each record is on disk before the writer finishes
+    let content = fs::read_to_string(path).unwrap();
+    assert_eq!(content.lines().count(), 1);
+
+    let mut block2 = create_test_block();
+    block2.block_number = 18000001;
+    writer.write(&block2);
+    assert_eq!(writer.finish().unwrap(), 2);
+
+    let content = fs::read_to_string(path).unwrap();
+    let numbers: Vec<u64> = content
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<BlockLifecycle>(line)
+                .unwrap()
+                .block_number
+        })
+        .collect();
+    assert_eq!(numbers, vec![18000000, 18000001]);
+
+    // Cleanup
+    fs::remove_file(path).ok();
+}
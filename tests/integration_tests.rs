@@ -1,5 +1,5 @@
-/// Integration tests for BlockStream Inspector
-/// These tests verify the entire system works together
+//! Integration tests for BlockStream Inspector
+//! These tests verify the entire system works together
 
 use blockstream_inspector::types::*;
 use blockstream_inspector::exporter::Exporter;
@@ -102,7 +102,7 @@ fn test_mev_metrics() {
     // Counts should be valid
     assert!(block.mev.sandwich_attacks.len() <= 100);
     assert!(block.mev.arbitrage_ops.len() <= 100);
-    assert!(block.mev.liquidations <= 100);
+    assert!(block.mev.liquidations.len() <= 100);
 }
 
 #[test]
@@ -156,6 +156,7 @@ fn create_test_block() -> BlockLifecycle {
     BlockLifecycle {
         block_number: 18000000,
         block_hash: "0x1234567890abcdef".to_string(),
+        parent_hash: "0xabcdef".to_string(),
         timestamp: 1698765432,
         proposer: "0xabcdef".to_string(),
         builder: Some("flashbots".to_string()),
@@ -163,6 +164,9 @@ fn create_test_block() -> BlockLifecycle {
             block_time: 12.05,
             timestamp: 1698765432,
             propagation_delay: None,
+            slot: None,
+            received_at: None,
+            timestamp_delay: None,
         },
         gas: GasMetrics {
             gas_used: 29834521,
@@ -170,8 +174,12 @@ fn create_test_block() -> BlockLifecycle {
             utilization: 99.45,
             base_fee_gwei: 25.34,
             avg_priority_fee_gwei: 1.52,
+            median_priority_fee_gwei: 1.2,
+            priority_fee_percentiles: FeePercentiles::default(),
             fees_burned_eth: 0.7563,
             priority_fees_eth: 0.0453,
+            fees_burned_wei: Some("756300000000000000".to_string()),
+            priority_fees_wei: Some("45300000000000000".to_string()),
         },
         transactions: TransactionMetrics {
             total_count: 247,
@@ -187,19 +195,29 @@ fn create_test_block() -> BlockLifecycle {
                 avg_deviation: 0.5,
             },
             failed_count: 3,
+            gas_usage: vec![],
         },
         mev: MevIndicators {
             sandwich_attacks: vec![],
             arbitrage_ops: vec![],
-            liquidations: 2,
+            liquidations: vec![],
             estimated_mev_eth: 2.3451,
             mev_bot_addresses: vec!["0x123".to_string()],
+            other_findings: vec![],
         },
         pbs: PbsMetrics {
             is_pbs_block: true,
             builder_address: Some("flashbots".to_string()),
+            builder_match: None,
             builder_payment_eth: Some(0.05),
             extra_data: "flashbots".to_string(),
+            payment_tx: None,
+            proposer_fee_recipient: None,
+            builder_margin_eth: None,
+            builder_payment_wei: Some("50000000000000000".to_string()),
+            builder_margin_wei: None,
         },
+        provider_disagreements: vec![],
+        labels: vec![],
     }
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+use blockstream_inspector::labels::LabelSet;
+use blockstream_inspector::types::LabelCategory;
+
+const LABELS: &str = r#"
+[[label]]
+address = "0xAAAAaaaaAAAAaaaaAAAAaaaaAAAAaaaaAAAAaaaa"
+category = "searcher"
+
+[[label]]
+address = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+category = "router"
+name = "Uniswap V2 Router"
+"#;
+
+#[test]
+fn test_is_known_mev_bot() {
+    let labels = LabelSet::default();
+
+    // Known MEV bot
+    assert!(labels.is_searcher("0x0000000000007f150bd6f54c40a34d7c3d5e9f56"));
+    assert!(labels.is_searcher("0xa57bd00134b2850b2a1c55860c9e9ea100fdd6cf"));
+
+    // Unknown address
+    assert!(!labels.is_searcher("0x1234567890abcdef1234567890abcdef12345678"));
+
+    // Case insensitive
+    assert!(labels.is_searcher("0X0000000000007F150BD6F54C40A34D7C3D5E9F56"));
+}
+
+#[test]
+fn test_lookup_ignores_case() {
+    let labels = LabelSet::from_toml_str(LABELS).unwrap();
+    let label = labels.get("0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+
+    assert_eq!(label.category, LabelCategory::Searcher);
+    assert_eq!(label.address, "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+}
+
+#[test]
+fn test_router_is_not_searcher() {
+    let labels = LabelSet::from_toml_str(LABELS).unwrap();
+
+    assert!(!labels.is_searcher("0x7a250d5630b4cf539739df2c5dacb4c659f2488d"));
+    assert_eq!(
+        labels.get("0x7a250d5630b4cf539739df2c5dacb4c659f2488d").unwrap().name.as_deref(),
+        Some("Uniswap V2 Router")
+    );
+}
+
+#[test]
+fn test_label_all_skips_unknown_and_duplicates() {
+    let labels = LabelSet::from_toml_str(LABELS).unwrap();
+    let found = labels.label_all([
+        "0x7a250d5630b4cf539739df2c5dacb4c659f2488d",
+        "0x0000000000000000000000000000000000000001",
+        "0x7A250D5630B4CF539739DF2C5DACB4C659F2488D",
+    ]);
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].category, LabelCategory::Router);
+}
+
+#[test]
+fn test_load_json_labels() {
+    let path = "/tmp/test_labels.json";
+    std::fs::write(
+        path,
+        r#"{"label": [{"address": "0x28c6c06298d514db089934071355e5743bf21d60", "category": "cex"}]}"#,
+    )
+    .unwrap();
+
+    let labels = LabelSet::load(path).unwrap();
+    assert_eq!(labels.len(), 1);
+    assert_eq!(
+        labels.get("0x28c6c06298d514db089934071355e5743bf21d60").unwrap().category,
+        LabelCategory::Cex
+    );
+
+    std::fs::remove_file(path).ok();
+}
@@ -0,0 +1,202 @@
+use blockstream_inspector::dex::topic;
+use blockstream_inspector::lending::decode_liquidations;
+use ethers::types::{Address, Bytes, H256, Log, Transaction, TransactionReceipt, U64, U256};
+
+fn address(byte: u8) -> Address {
+    Address::repeat_byte(byte)
+}
+
+fn hex(address: Address) -> String {
+    format!("{:?}", address)
+}
+
+fn address_topic(address: Address) -> H256 {
+    H256::from(address)
+}
+
+fn data(words: &[U256]) -> Bytes {
+    let mut bytes = Vec::new();
+    for word in words {
+        let mut buf = [0u8; 32];
+        word.to_big_endian(&mut buf);
+        bytes.extend_from_slice(&buf);
+    }
+    bytes.into()
+}
+
+fn address_word(address: Address) -> U256 {
+    U256::from_big_endian(address_topic(address).as_bytes())
+}
+
+fn log(emitter: Address, topics: Vec<H256>, words: &[U256]) -> Log {
+    Log {
+        address: emitter,
+        topics,
+        data: data(words),
+        ..Default::default()
+    }
+}
+
+fn receipt(index: u64, sender: Address, logs: Vec<Log>) -> TransactionReceipt {
+    TransactionReceipt {
+        transaction_hash: H256::from_low_u64_be(index + 1),
+        transaction_index: U64::from(index),
+        from: sender,
+        logs,
+        status: Some(U64::one()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_decodes_aave_v3_liquidation_call() {
+    let pool: Address = "0x87870bca3f3fd6335c3f4ce8392d69350b4fa4e2"
+        .parse()
+        .unwrap();
+    let event = log(
+        pool,
+        vec![
+            topic("LiquidationCall(address,address,address,uint256,uint256,address,bool)"),
+            address_topic(address(0xc0)),
+            address_topic(address(0xd0)),
+            address_topic(address(0xb0)),
+        ],
+        &[
+            U256::from(500),
+            U256::from(1_000),
+            address_word(address(0xa1)),
+            U256::zero(),
+        ],
+    );
+
+    let found = decode_liquidations(&[], &[receipt(0, address(0xa1), vec![event])]);
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].protocol, "Aave V3");
+    assert_eq!(found[0].tx_hash, format!("{:?}", H256::from_low_u64_be(1)));
+    assert_eq!(found[0].liquidator, hex(address(0xa1)));
+    assert_eq!(found[0].borrower, hex(address(0xb0)));
+    assert_eq!(found[0].collateral_asset, hex(address(0xc0)));
+    assert_eq!(found[0].collateral_amount, "1000");
+    assert_eq!(found[0].debt_asset, hex(address(0xd0)));
+    assert_eq!(found[0].debt_repaid, "500");
+}
+
+#[test]
+fn test_decodes_compound_v2_liquidate_borrow() {
+    let c_usdc = address(0xd0);
+    let event = log(
+        c_usdc,
+        vec![topic(
+            "LiquidateBorrow(address,address,uint256,address,uint256)",
+        )],
+        &[
+            address_word(address(0xa1)),
+            address_word(address(0xb0)),
+            U256::from(700),
+            address_word(address(0xc0)),
+            U256::from(42),
+        ],
+    );
+
+    let found = decode_liquidations(&[], &[receipt(0, address(0xa1), vec![event])]);
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].protocol, "Compound V2");
+    assert_eq!(found[0].liquidator, hex(address(0xa1)));
+    assert_eq!(found[0].borrower, hex(address(0xb0)));
+    assert_eq!(found[0].collateral_asset, hex(address(0xc0)));
+    assert_eq!(found[0].collateral_amount, "42");
+    assert_eq!(found[0].debt_asset, hex(c_usdc));
+    assert_eq!(found[0].debt_repaid, "700");
+}
+
+#[test]
+fn test_decodes_compound_v3_absorb_with_debt() {
+    let comet = address(0xe0);
+    let absorber = address(0xa1);
+    let borrower = address(0xb0);
+
+    let debt = log(
+        comet,
+        vec![
+            topic("AbsorbDebt(address,address,uint256,uint256)"),
+            address_topic(absorber),
+            address_topic(borrower),
+        ],
+        &[U256::from(900), U256::from(900)],
+    );
+    let collateral = log(
+        comet,
+        vec![
+            topic("AbsorbCollateral(address,address,address,uint256,uint256)"),
+            address_topic(absorber),
+            address_topic(borrower),
+            address_topic(address(0xc0)),
+        ],
+        &[U256::from(3), U256::from(950)],
+    );
+
+    let found = decode_liquidations(&[], &[receipt(0, absorber, vec![collateral, debt])]);
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].protocol, "Compound V3");
+    assert_eq!(found[0].liquidator, hex(absorber));
+    assert_eq!(found[0].collateral_amount, "3");
+    assert_eq!(found[0].debt_asset, hex(comet));
+    assert_eq!(found[0].debt_repaid, "900");
+}
+
+#[test]
+fn test_decodes_maker_bark() {
+    let keeper = address(0xa1);
+    let mut ilk = [0u8; 32];
+    ilk[..5].copy_from_slice(b"ETH-A");
+
+    let event = log(
+        address(0xdd),
+        vec![
+            topic("Bark(bytes32,address,uint256,uint256,uint256,address,uint256)"),
+            H256::from(ilk),
+            address_topic(address(0xb0)),
+            H256::from_low_u64_be(7),
+        ],
+        &[
+            U256::from(10),
+            U256::from(20),
+            U256::from(30),
+            address_word(address(0xcc)),
+        ],
+    );
+
+    let tx = Transaction {
+        hash: H256::from_low_u64_be(1),
+        from: keeper,
+        ..Default::default()
+    };
+
+    let found = decode_liquidations(&[tx], &[receipt(0, Address::zero(), vec![event])]);
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].protocol, "Maker");
+    assert_eq!(found[0].liquidator, hex(keeper));
+    assert_eq!(found[0].borrower, hex(address(0xb0)));
+    assert_eq!(found[0].collateral_asset, "ETH-A");
+    assert_eq!(found[0].collateral_amount, "10");
+    assert_eq!(found[0].debt_repaid, "30");
+}
+
+#[test]
+fn test_skips_failed_transactions() {
+    let event = log(
+        address(0xd0),
+        vec![topic(
+            "LiquidateBorrow(address,address,uint256,address,uint256)",
+        )],
+        &[U256::zero(); 5],
+    );
+    let mut failed = receipt(0, address(0xa1), vec![event]);
+    failed.status = Some(U64::zero());
+
+    assert!(decode_liquidations(&[], &[failed]).is_empty());
+}
@@ -0,0 +1,317 @@
+use blockstream_inspector::dex::{Dex, Swap, weth};
+use blockstream_inspector::mev::{
+    BlockContext, DetectorRegistry, MevDetector, MevFinding, detect_arbitrage, detect_sandwiches,
+};
+use blockstream_inspector::types::DetectorFinding;
+use ethers::types::{Address, Block, H256, Transaction, U256};
+
+const ETH: u128 = 1_000_000_000_000_000_000;
+
+fn token() -> Address {
+    Address::repeat_byte(0x70)
+}
+
+fn pool() -> Address {
+    Address::repeat_byte(0x50)
+}
+
+/// Swap in `pool()` sent by `from` through contract `to`. `buy` swaps WETH
+/// for `token()`, otherwise `token()` for WETH.
+fn swap(tx_index: usize, from: u8, to: u8, buy: bool, amount_in: u128, amount_out: u128) -> Swap {
+    let (token_in, token_out) = if buy {
+        (weth(), token())
+    } else {
+        (token(), weth())
+    };
+
+    Swap {
+        tx_index,
+        tx_hash: H256::from_low_u64_be(tx_index as u64),
+        tx_from: Address::repeat_byte(from),
+        tx_to: Some(Address::repeat_byte(to)),
+        pool: pool(),
+        dex: Dex::UniswapV2,
+        zero_for_one: buy,
+        amount_in: U256::from(amount_in),
+        amount_out: U256::from(amount_out),
+        token_in: Some(token_in),
+        token_out: Some(token_out),
+        recipient: Address::repeat_byte(to),
+    }
+}
+
+/// Swap of `token_in` for `token_out` in pool `pool` within transaction `tx_index`
+fn hop(
+    tx_index: usize,
+    pool: u8,
+    dex: Dex,
+    token_in: Address,
+    token_out: Address,
+    amount_in: u128,
+    amount_out: u128,
+) -> Swap {
+    Swap {
+        tx_index,
+        tx_hash: H256::from_low_u64_be(tx_index as u64),
+        tx_from: Address::repeat_byte(0xa1),
+        tx_to: Some(Address::repeat_byte(0xb0)),
+        pool: Address::repeat_byte(pool),
+        dex,
+        zero_for_one: true,
+        amount_in: U256::from(amount_in),
+        amount_out: U256::from(amount_out),
+        token_in: Some(token_in),
+        token_out: Some(token_out),
+        recipient: Address::repeat_byte(0xb0),
+    }
+}
+
+#[test]
+fn test_detects_sandwich() {
+    let swaps = vec![
+        swap(0, 0xa1, 0xb0, true, ETH, 1_000),
+        swap(1, 0x11, 0xc0, true, 2 * ETH, 1_900),
+        swap(2, 0xa1, 0xb0, false, 1_000, ETH + ETH / 10),
+    ];
+
+    let found = detect_sandwiches(&swaps);
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        found[0].frontrun_tx,
+        format!("{:?}", H256::from_low_u64_be(0))
+    );
+    assert_eq!(
+        found[0].victim_tx,
+        format!("{:?}", H256::from_low_u64_be(1))
+    );
+    assert_eq!(
+        found[0].backrun_tx,
+        format!("{:?}", H256::from_low_u64_be(2))
+    );
+    assert_eq!(found[0].pool, format!("{:?}", pool()));
+    assert_eq!(found[0].dex, "Uniswap V2");
+    assert!((found[0].estimated_profit_eth - 0.1).abs() < 1e-9);
+}
+
+#[test]
+fn test_profit_priced_when_starting_from_token() {
+    // Attacker sells the token first and buys it back cheaper
+    let swaps = vec![
+        swap(0, 0xa1, 0xb0, false, 1_000, ETH),
+        swap(1, 0x11, 0xc0, false, 500, ETH / 3),
+        swap(2, 0xa1, 0xb0, true, ETH, 1_100),
+    ];
+
+    let found = detect_sandwiches(&swaps);
+
+    // 100 extra tokens at the back-run price of 1 ETH / 1100 tokens
+    assert_eq!(found.len(), 1);
+    assert!((found[0].estimated_profit_eth - 100.0 / 1_100.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_no_sandwich_without_victim() {
+    let swaps = vec![
+        swap(0, 0xa1, 0xb0, true, ETH, 1_000),
+        swap(1, 0xa1, 0xb0, false, 1_000, ETH),
+    ];
+
+    assert!(detect_sandwiches(&swaps).is_empty());
+}
+
+#[test]
+fn test_no_sandwich_when_victim_trades_other_way() {
+    let swaps = vec![
+        swap(0, 0xa1, 0xb0, true, ETH, 1_000),
+        swap(1, 0x11, 0xc0, false, 1_000, ETH),
+        swap(2, 0xa1, 0xb0, false, 1_000, ETH),
+    ];
+
+    assert!(detect_sandwiches(&swaps).is_empty());
+}
+
+#[test]
+fn test_router_users_are_not_one_attacker() {
+    // Three different senders through the same router contract
+    let swaps = vec![
+        swap(0, 0x01, 0xee, true, ETH, 1_000),
+        swap(1, 0x02, 0xee, true, ETH, 900),
+        swap(2, 0x03, 0xee, false, 1_000, ETH),
+    ];
+
+    assert!(detect_sandwiches(&swaps).is_empty());
+}
+
+#[test]
+fn test_detects_two_pool_arbitrage() {
+    let swaps = vec![
+        hop(3, 0x50, Dex::UniswapV2, weth(), token(), ETH, 1_000),
+        hop(
+            3,
+            0x51,
+            Dex::UniswapV3,
+            token(),
+            weth(),
+            1_000,
+            ETH + ETH / 20,
+        ),
+    ];
+
+    let found = detect_arbitrage(&swaps);
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].tx_hash, format!("{:?}", H256::from_low_u64_be(3)));
+    assert_eq!(
+        found[0].path,
+        vec![
+            format!("{:?}", weth()),
+            format!("{:?}", token()),
+            format!("{:?}", weth())
+        ]
+    );
+    assert_eq!(found[0].dexes_involved, vec!["Uniswap V2", "Uniswap V3"]);
+    assert!((found[0].estimated_profit_eth - 0.05).abs() < 1e-9);
+}
+
+#[test]
+fn test_triangular_arbitrage_priced_through_weth() {
+    let usdc = Address::repeat_byte(0x71);
+
+    // token -> WETH -> USDC -> token, ending with 100 more tokens
+    let swaps = vec![
+        hop(0, 0x50, Dex::UniswapV2, token(), weth(), 1_000, ETH),
+        hop(0, 0x52, Dex::UniswapV2, weth(), usdc, ETH, 2_000),
+        hop(0, 0x53, Dex::UniswapV2, usdc, token(), 2_000, 1_100),
+    ];
+
+    let found = detect_arbitrage(&swaps);
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path.len(), 4);
+    assert_eq!(found[0].dexes_involved, vec!["Uniswap V2"]);
+    // 100 tokens at 1 ETH / 1000 tokens
+    assert!((found[0].estimated_profit_eth - 0.1).abs() < 1e-9);
+}
+
+#[test]
+fn test_no_arbitrage_for_losing_or_open_paths() {
+    let usdc = Address::repeat_byte(0x71);
+
+    let swaps = vec![
+        // Round trip that loses money
+        hop(0, 0x50, Dex::UniswapV2, weth(), token(), ETH, 1_000),
+        hop(0, 0x51, Dex::UniswapV2, token(), weth(), 1_000, ETH / 2),
+        // Path that never returns to WETH
+        hop(1, 0x50, Dex::UniswapV2, weth(), token(), ETH, 1_000),
+        hop(1, 0x53, Dex::UniswapV2, token(), usdc, 1_000, 2_000),
+    ];
+
+    assert!(detect_arbitrage(&swaps).is_empty());
+}
+
+#[test]
+fn test_arbitrage_stays_within_one_transaction() {
+    let swaps = vec![
+        hop(0, 0x50, Dex::UniswapV2, weth(), token(), ETH, 1_000),
+        hop(1, 0x51, Dex::UniswapV2, token(), weth(), 1_000, 2 * ETH),
+    ];
+
+    assert!(detect_arbitrage(&swaps).is_empty());
+}
+
+/// Flags every block with more than `max_txs` transactions
+struct BusyBlockDetector {
+    max_txs: usize,
+}
+
+impl MevDetector for BusyBlockDetector {
+    fn name(&self) -> &str {
+        "busy"
+    }
+
+    fn configure(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        match key {
+            "max_txs" => self.max_txs = value.parse()?,
+            _ => anyhow::bail!("no option {}", key),
+        }
+        Ok(())
+    }
+
+    fn detect(&self, block: &BlockContext) -> Vec<MevFinding> {
+        if block.block.transactions.len() <= self.max_txs {
+            return Vec::new();
+        }
+        vec![MevFinding::Other(DetectorFinding {
+            detector: self.name().to_string(),
+            tx_hashes: Vec::new(),
+            description: "busy block".to_string(),
+            estimated_profit_eth: 0.0,
+        })]
+    }
+}
+
+fn block_with_txs(count: usize) -> Block<Transaction> {
+    Block {
+        transactions: vec![Transaction::default(); count],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_default_registry_has_builtin_detectors() {
+    let registry = DetectorRegistry::default();
+
+    assert_eq!(
+        registry.names(),
+        vec!["sandwich", "arbitrage", "liquidation"]
+    );
+    assert!(registry.is_enabled("sandwich"));
+    assert!(DetectorRegistry::new().names().is_empty());
+}
+
+#[test]
+fn test_custom_detector_enable_disable_configure() {
+    let mut registry = DetectorRegistry::new();
+    registry.register(Box::new(BusyBlockDetector { max_txs: 10 }));
+
+    let block = block_with_txs(5);
+    let context = BlockContext::new(&block, &[]);
+    assert!(registry.detect(&context).is_empty());
+
+    registry.configure_from("busy.max_txs=3").unwrap();
+    let findings = registry.detect(&context);
+    assert_eq!(findings.len(), 1);
+    assert!(matches!(&findings[0], MevFinding::Other(f) if f.detector == "busy"));
+
+    registry.disable("busy").unwrap();
+    assert!(!registry.is_enabled("busy"));
+    assert!(registry.detect(&context).is_empty());
+
+    registry.enable("busy").unwrap();
+    assert_eq!(registry.detect(&context).len(), 1);
+}
+
+#[test]
+fn test_registry_rejects_bad_options() {
+    let mut registry = DetectorRegistry::default();
+
+    assert!(registry.disable("frontrun").is_err());
+    assert!(registry.configure_from("sandwich.min_profit_eth").is_err());
+    assert!(
+        registry
+            .configure_from("sandwich.min_profit_eth=lots")
+            .is_err()
+    );
+    assert!(registry.configure_from("arbitrage.max_hops=3").is_err());
+    assert!(
+        registry
+            .configure_from("sandwich.min_profit_eth=0.5")
+            .is_ok()
+    );
+    assert!(
+        registry
+            .configure_from("liquidation.protocols=aave, maker")
+            .is_ok()
+    );
+}
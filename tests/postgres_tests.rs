@@ -0,0 +1,312 @@
+//! Runs against a live server when POSTGRES_TEST_URL is set, e.g.
+//! `POSTGRES_TEST_URL=postgres://postgres@localhost/inspector_test cargo test`;
+//! skipped otherwise.
+
+use blockstream_inspector::postgres::{PostgresStore, redact_password};
+use blockstream_inspector::types::*;
+
+mod common;
+use common::create_test_block;
+
+/// Connection URL whose tables live in `schema`, or None when no server is configured
+fn schema_url(schema: &str) -> Option<String> {
+    let Ok(url) = std::env::var("POSTGRES_TEST_URL") else {
+        eprintln!("POSTGRES_TEST_URL not set, skipping");
+        return None;
+    };
+    let separator = if url.contains('?') { '&' } else { '?' };
+    Some(format!(
+        "{}{}options=-csearch_path%3D{}",
+        url, separator, schema
+    ))
+}
+
+/// Store in a fresh, empty schema named after the test
+async fn test_store(schema: &str) -> Option<PostgresStore> {
+    let url = schema_url(schema)?;
+    let (client, connection) = tokio_postgres::connect(&url, tokio_postgres::NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(connection);
+    client
+        .batch_execute(&format!(
+            "DROP SCHEMA IF EXISTS {0} CASCADE; CREATE SCHEMA {0}",
+            schema
+        ))
+        .await
+        .unwrap();
+
+    Some(PostgresStore::connect(&url).await.unwrap())
+}
+
+async fn count(store: &PostgresStore, table: &str) -> i64 {
+    store
+        .client()
+        .query_one(&format!("SELECT COUNT(*) FROM {}", table), &[])
+        .await
+        .unwrap()
+        .get(0)
+}
+
+#[tokio::test]
+async fn test_migrations_apply_once() {
+    let Some(store) = test_store("test_migrations").await else {
+        return;
+    };
+    let version = store.schema_version().await.unwrap();
+    assert!(version >= 1);
+    drop(store);
+
+    // Reconnecting finds the schema up to date and changes nothing
+    let url = schema_url("test_migrations").unwrap();
+    let store = PostgresStore::connect(&url).await.unwrap();
+    assert_eq!(store.schema_version().await.unwrap(), version);
+    assert_eq!(count(&store, "schema_migrations").await, version as i64);
+}
+
+#[tokio::test]
+async fn test_writes_normalized_tables() {
+    let Some(mut store) = test_store("test_normalized").await else {
+        return;
+    };
+    let mut block = create_test_block(100);
+    block.pbs.extra_data = String::new();
+    store.write_blocks(&[block]).await.unwrap();
+
+    assert_eq!(count(&store, "blocks").await, 1);
+    assert_eq!(count(&store, "transactions").await, 2);
+    assert_eq!(count(&store, "mev_findings").await, 2);
+    assert_eq!(count(&store, "builders").await, 1);
+
+    let row = store
+        .client()
+        .query_one(
+            "SELECT tx_hashes, actor, details->>'pool' FROM mev_findings WHERE kind = 'sandwich'",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, Vec<String>>(0), vec!["0xf1", "0xv1", "0xb1"]);
+    assert_eq!(row.get::<_, String>(1), "0xbot");
+    assert_eq!(row.get::<_, String>(2), "0xpool");
+
+    // Empty strings stay empty rather than turning into NULLs
+    let extra_data: String = store
+        .client()
+        .query_one("SELECT extra_data FROM blocks", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(extra_data, "");
+}
+
+#[tokio::test]
+async fn test_nul_padded_extra_data_is_stored() {
+    let Some(mut store) = test_store("test_nul_extra_data").await else {
+        return;
+    };
+    let mut block = create_test_block(100);
+    block.pbs.extra_data = "beaverbuild.org\0\0\0".to_string();
+    store.write_blocks(&[block]).await.unwrap();
+
+    let extra_data: String = store
+        .client()
+        .query_one("SELECT extra_data FROM blocks", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(extra_data, "beaverbuild.org");
+}
+
+#[tokio::test]
+async fn test_upserts_are_idempotent() {
+    let Some(mut store) = test_store("test_idempotent").await else {
+        return;
+    };
+    let mut live = create_test_block(101);
+    live.timing.received_at = Some(1698765433.5);
+    store
+        .write_blocks(&[create_test_block(100), live])
+        .await
+        .unwrap();
+
+    // An overlapping range run, with a block repeated inside the batch and
+    // one block's sandwich gone
+    let mut updated = create_test_block(101);
+    updated.mev.sandwich_attacks.clear();
+    store
+        .write_blocks(&[create_test_block(101), updated, create_test_block(102)])
+        .await
+        .unwrap();
+
+    assert_eq!(count(&store, "blocks").await, 3);
+    assert_eq!(count(&store, "transactions").await, 6);
+    assert_eq!(count(&store, "mev_findings").await, 5);
+
+    let received_at: Option<f64> = store
+        .client()
+        .query_one("SELECT received_at FROM blocks WHERE number = 101", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(received_at, Some(1698765433.5));
+
+    let row = store
+        .client()
+        .query_one(
+            "SELECT first_block, last_block FROM builders WHERE name = 'flashbots'",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!((row.get::<_, i64>(0), row.get::<_, i64>(1)), (100, 102));
+}
+
+#[tokio::test]
+async fn test_concurrent_writers_of_the_same_blocks() {
+    let Some(mut first) = test_store("test_concurrent").await else {
+        return;
+    };
+    let url = schema_url("test_concurrent").unwrap();
+    let mut second = PostgresStore::connect(&url).await.unwrap();
+
+    // Without a builder, nothing else serializes the two writers
+    let forward: Vec<_> = (100..110)
+        .map(|number| BlockLifecycle {
+            builder: None,
+            ..create_test_block(number)
+        })
+        .collect();
+    let backward: Vec<_> = forward.iter().rev().cloned().collect();
+    for _ in 0..10 {
+        let (a, b) = tokio::join!(first.write_blocks(&forward), second.write_blocks(&backward));
+        a.unwrap();
+        b.unwrap();
+    }
+
+    assert_eq!(count(&first, "blocks").await, 10);
+    assert_eq!(count(&first, "transactions").await, 20);
+    assert_eq!(count(&first, "mev_findings").await, 20);
+}
+
+#[tokio::test]
+async fn test_connection_error_is_reported_by_next_write() {
+    let Some(mut store) = test_store("test_connection_error").await else {
+        return;
+    };
+    let pid: i32 = store
+        .client()
+        .query_one("SELECT pg_backend_pid()", &[])
+        .await
+        .unwrap()
+        .get(0);
+
+    let (admin, connection) = tokio_postgres::connect(
+        &std::env::var("POSTGRES_TEST_URL").unwrap(),
+        tokio_postgres::NoTls,
+    )
+    .await
+    .unwrap();
+    tokio::spawn(connection);
+    admin
+        .execute("SELECT pg_terminate_backend($1)", &[&pid])
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let err = store
+        .write_blocks(&[create_test_block(100)])
+        .await
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("terminating connection"));
+}
+
+#[tokio::test]
+async fn test_reorgs_mark_orphaned_blocks() {
+    let Some(mut store) = test_store("test_reorgs").await else {
+        return;
+    };
+    store
+        .write_blocks(&[create_test_block(100), create_test_block(101)])
+        .await
+        .unwrap();
+
+    let reorg = ReorgEvent {
+        detected_at: 102,
+        common_ancestor: Some(100),
+        depth: 1,
+        orphaned: vec![create_test_block(101).block_hash],
+        replacement: vec!["0xnew".to_string()],
+    };
+    store
+        .write_reorgs(std::slice::from_ref(&reorg))
+        .await
+        .unwrap();
+    store.write_reorgs(&[reorg]).await.unwrap();
+
+    assert_eq!(count(&store, "reorgs").await, 1);
+    let orphaned: Vec<i64> = store
+        .client()
+        .query("SELECT number FROM blocks WHERE orphaned", &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+    assert_eq!(orphaned, vec![101]);
+}
+
+#[tokio::test]
+async fn test_rewritten_block_is_canonical_again() {
+    let Some(mut store) = test_store("test_flip_flop").await else {
+        return;
+    };
+    store.write_blocks(&[create_test_block(100)]).await.unwrap();
+    let reorg = ReorgEvent {
+        detected_at: 101,
+        common_ancestor: Some(99),
+        depth: 1,
+        orphaned: vec![create_test_block(100).block_hash],
+        replacement: vec!["0xnew".to_string()],
+    };
+    store.write_reorgs(&[reorg]).await.unwrap();
+
+    // The chain flips back to the original block
+    store.write_blocks(&[create_test_block(100)]).await.unwrap();
+
+    let orphaned: bool = store
+        .client()
+        .query_one("SELECT orphaned FROM blocks", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert!(!orphaned);
+}
+
+#[test]
+fn test_redacts_password_for_printing() {
+    assert_eq!(
+        redact_password("postgres://inspector:s3cret@db:5432/blocks"),
+        "postgres://inspector:***@db:5432/blocks"
+    );
+    assert_eq!(
+        redact_password("postgres://inspector@db/blocks"),
+        "postgres://inspector@db/blocks"
+    );
+    assert_eq!(
+        redact_password("postgres://inspector@db/blocks?sslmode=disable&password=s3cret"),
+        "postgres://inspector@db/blocks?sslmode=disable&password=***"
+    );
+    assert_eq!(
+        redact_password("host=db user=inspector password=s3cret dbname=blocks"),
+        "host=db user=inspector password=*** dbname=blocks"
+    );
+    assert_eq!(
+        redact_password(r"host=db password = 'it\'s secret' dbname=blocks"),
+        "host=db password=*** dbname=blocks"
+    );
+    assert_eq!(
+        redact_password("host=db user=no_password dbname=blocks"),
+        "host=db user=no_password dbname=blocks"
+    );
+}
@@ -0,0 +1,256 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use blockstream_inspector::analyzer::BlockAnalyzer;
+use blockstream_inspector::reorg::ReorgTracker;
+use blockstream_inspector::source::{BlockSource, FixtureBlock, FixtureSource, HeadStream};
+use ethers::types::{Block, H256, Trace, Transaction, TransactionReceipt, U64, U256};
+use futures::stream::{self, StreamExt};
+use std::sync::{Arc, Mutex};
+
+fn hash(n: u8) -> String {
+    format!("0x{:064x}", n)
+}
+
+#[test]
+fn test_parent_match_is_not_reorg() {
+    let mut tracker = ReorgTracker::new(8);
+    tracker.insert(100, hash(1));
+
+    assert!(!tracker.is_reorg(101, &hash(1)));
+    assert!(tracker.is_reorg(101, &hash(2)));
+}
+
+#[test]
+fn test_unknown_parent_is_not_reorg() {
+    let mut tracker = ReorgTracker::new(8);
+    tracker.insert(100, hash(1));
+
+    // Block 102's parent (101) was never seen, so there is nothing to compare
+    assert!(!tracker.is_reorg(102, &hash(9)));
+    assert!(!tracker.is_reorg(0, &hash(9)));
+}
+
+#[test]
+fn test_hash_comparison_ignores_case() {
+    let mut tracker = ReorgTracker::new(8);
+    tracker.insert(100, "0xABCDEF".to_string());
+
+    assert!(!tracker.is_reorg(101, "0xabcdef"));
+}
+
+#[test]
+fn test_window_drops_oldest_hashes() {
+    let mut tracker = ReorgTracker::new(3);
+    for n in 0..5u8 {
+        tracker.insert(100 + n as u64, hash(n));
+    }
+
+    assert_eq!(tracker.oldest(), Some(102));
+    assert_eq!(tracker.hash_at(101), None);
+    assert_eq!(tracker.hash_at(104), Some(hash(4).as_str()));
+}
+
+#[test]
+fn test_insert_drops_blocks_above_new_tip() {
+    let mut tracker = ReorgTracker::new(8);
+    for n in 0..4u8 {
+        tracker.insert(100 + n as u64, hash(n));
+    }
+
+    // A replacement at 101 invalidates the old 102 and 103
+    tracker.insert(101, hash(9));
+
+    assert_eq!(tracker.hash_at(100), Some(hash(0).as_str()));
+    assert_eq!(tracker.hash_at(101), Some(hash(9).as_str()));
+    assert_eq!(tracker.hash_at(102), None);
+    assert_eq!(tracker.hash_at(103), None);
+}
+
+/// Hash of block `number` on fork `fork` (0 is the initial chain)
+fn fork_hash(number: u64, fork: u64) -> H256 {
+    H256::from_low_u64_be(fork << 32 | number)
+}
+
+fn fork_block(number: u64, fork: u64, parent_fork: u64) -> Block<Transaction> {
+    Block {
+        number: Some(U64::from(number)),
+        hash: Some(fork_hash(number, fork)),
+        parent_hash: fork_hash(number - 1, parent_fork),
+        timestamp: U256::from(1_700_000_000 + number * 12),
+        ..Default::default()
+    }
+}
+
+/// Chain at block 10 whose head then moves through `steps`: each step
+/// installs its blocks and announces the last one as the new head
+struct ScriptedChain {
+    chain: Arc<Mutex<FixtureSource>>,
+    steps: Mutex<Vec<Vec<Block<Transaction>>>>,
+}
+
+impl ScriptedChain {
+    fn new(steps: Vec<Vec<Block<Transaction>>>) -> Self {
+        let chain = (1..=10).fold(FixtureSource::new(), |chain, number| {
+            chain.with_block(fork_block(number, 0, 0), Vec::new())
+        });
+
+        Self {
+            chain: Arc::new(Mutex::new(chain)),
+            steps: Mutex::new(steps),
+        }
+    }
+
+    fn snapshot(&self) -> FixtureSource {
+        self.chain.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl BlockSource for ScriptedChain {
+    async fn get_block(&self, number: u64) -> Result<Option<Block<Transaction>>> {
+        self.snapshot().get_block(number).await
+    }
+
+    async fn get_block_receipts(
+        &self,
+        _block: &Block<Transaction>,
+    ) -> Result<Vec<TransactionReceipt>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_block_traces(&self, _number: u64) -> Result<Vec<Trace>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_latest_block_number(&self) -> Result<u64> {
+        self.snapshot().get_latest_block_number().await
+    }
+
+    async fn subscribe_new_heads(&self) -> Result<Option<HeadStream<'_>>> {
+        let steps = std::mem::take(&mut *self.steps.lock().unwrap());
+        let chain = self.chain.clone();
+
+        Ok(Some(Box::pin(stream::iter(steps).map(move |blocks| {
+            let head = blocks.last().unwrap();
+            let header = Block {
+                number: head.number,
+                hash: head.hash,
+                parent_hash: head.parent_hash,
+                ..Default::default()
+            };
+
+            let mut chain = chain.lock().unwrap();
+            for block in blocks {
+                chain.insert(FixtureBlock {
+                    block,
+                    receipts: Vec::new(),
+                    traces: Vec::new(),
+                });
+            }
+            header
+        }))))
+    }
+}
+
+fn hex(hash: H256) -> String {
+    format!("{:?}", hash)
+}
+
+#[tokio::test]
+async fn test_live_detects_one_block_reorg_at_same_height() {
+    let chain = ScriptedChain::new(vec![vec![fork_block(11, 0, 0)], vec![fork_block(11, 1, 0)]]);
+
+    let results = BlockAnalyzer::with_source(chain)
+        .monitor_live(2)
+        .await
+        .unwrap();
+
+    assert_eq!(results.reorgs.len(), 1);
+    let reorg = &results.reorgs[0];
+    assert_eq!(reorg.detected_at, 11);
+    assert_eq!(reorg.common_ancestor, Some(10));
+    assert_eq!(reorg.depth, 1);
+    assert_eq!(reorg.orphaned, vec![hex(fork_hash(11, 0))]);
+    assert_eq!(reorg.replacement, vec![hex(fork_hash(11, 1))]);
+
+    let hashes: Vec<_> = results
+        .blocks
+        .iter()
+        .map(|b| b.block_hash.clone())
+        .collect();
+    assert_eq!(hashes, vec![hex(fork_hash(11, 0)), hex(fork_hash(11, 1))]);
+}
+
+#[tokio::test]
+async fn test_live_detects_deep_reorg_at_same_height() {
+    let chain = ScriptedChain::new(vec![
+        vec![fork_block(11, 0, 0)],
+        vec![fork_block(12, 0, 0)],
+        vec![fork_block(13, 0, 0)],
+        vec![
+            fork_block(11, 1, 0),
+            fork_block(12, 1, 1),
+            fork_block(13, 1, 1),
+        ],
+    ]);
+
+    let results = BlockAnalyzer::with_source(chain)
+        .monitor_live(4)
+        .await
+        .unwrap();
+
+    assert_eq!(results.reorgs.len(), 1);
+    let reorg = &results.reorgs[0];
+    assert_eq!(reorg.detected_at, 13);
+    assert_eq!(reorg.common_ancestor, Some(10));
+    assert_eq!(reorg.depth, 3);
+    assert_eq!(
+        reorg.orphaned,
+        (11..=13).map(|n| hex(fork_hash(n, 0))).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        reorg.replacement,
+        (11..=13).map(|n| hex(fork_hash(n, 1))).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_live_detects_deep_reorg_on_longer_chain() {
+    let chain = ScriptedChain::new(vec![
+        vec![fork_block(11, 0, 0)],
+        vec![fork_block(12, 0, 0)],
+        vec![
+            fork_block(11, 1, 0),
+            fork_block(12, 1, 1),
+            fork_block(13, 1, 1),
+        ],
+    ]);
+
+    let results = BlockAnalyzer::with_source(chain)
+        .monitor_live(3)
+        .await
+        .unwrap();
+
+    assert_eq!(results.reorgs.len(), 1);
+    let reorg = &results.reorgs[0];
+    assert_eq!(reorg.detected_at, 13);
+    assert_eq!(reorg.common_ancestor, Some(10));
+    assert_eq!(reorg.depth, 2);
+    assert_eq!(
+        reorg.replacement,
+        vec![hex(fork_hash(11, 1)), hex(fork_hash(12, 1))]
+    );
+}
+
+#[tokio::test]
+async fn test_live_ignores_repeated_head() {
+    let chain = ScriptedChain::new(vec![vec![fork_block(11, 0, 0)], vec![fork_block(11, 0, 0)]]);
+
+    let results = BlockAnalyzer::with_source(chain)
+        .monitor_live(2)
+        .await
+        .unwrap();
+
+    assert!(results.reorgs.is_empty());
+    assert_eq!(results.blocks.len(), 1);
+}
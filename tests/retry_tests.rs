@@ -0,0 +1,72 @@
+use blockstream_inspector::retry::{RetryPolicy, compute_units, is_rate_limit_code};
+use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use std::time::Duration;
+
+#[test]
+fn test_backoff_grows_exponentially_with_jitter() {
+    let policy = RetryPolicy {
+        max_retries: 5,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_secs(10),
+    };
+
+    for attempt in 0..4 {
+        let full = Duration::from_millis(100 * 2u64.pow(attempt));
+        let delay = policy.backoff(attempt);
+        assert!(delay >= full / 2, "attempt {attempt}: {delay:?}");
+        assert!(delay <= full, "attempt {attempt}: {delay:?}");
+    }
+}
+
+#[test]
+fn test_backoff_is_capped() {
+    let policy = RetryPolicy {
+        max_retries: 50,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_secs(2),
+    };
+
+    assert!(policy.backoff(40) <= Duration::from_secs(2));
+}
+
+#[test]
+fn test_retry_after_seconds() {
+    let policy = RetryPolicy::default();
+    let mut headers = HeaderMap::new();
+
+    assert_eq!(policy.retry_after(&headers), None);
+
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+    assert_eq!(policy.retry_after(&headers), Some(Duration::from_secs(3)));
+
+    // Capped at max_backoff
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("3600"));
+    assert_eq!(policy.retry_after(&headers), Some(policy.max_backoff));
+}
+
+#[test]
+fn test_retry_after_http_date_in_the_past() {
+    let policy = RetryPolicy::default();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        RETRY_AFTER,
+        HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+    );
+
+    assert_eq!(policy.retry_after(&headers), Some(Duration::ZERO));
+}
+
+#[test]
+fn test_compute_units() {
+    assert_eq!(compute_units("eth_blockNumber"), 10);
+    assert_eq!(compute_units("eth_getBlockByNumber"), 16);
+    assert!(compute_units("eth_getBlockReceipts") > compute_units("eth_getTransactionReceipt"));
+    assert_eq!(compute_units("some_unknown_method"), 20);
+}
+
+#[test]
+fn test_rate_limit_codes() {
+    assert!(is_rate_limit_code(429));
+    assert!(is_rate_limit_code(-32005));
+    assert!(!is_rate_limit_code(-32602));
+}
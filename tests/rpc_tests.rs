@@ -1,7 +1,13 @@
-use blockstream_inspector::rpc::{wei_to_eth, wei_to_gwei};
+use blockstream_inspector::rpc::{EthereumRpcClient, RpcConfig, wei_to_eth, wei_to_gwei};
 use ethers::{
-    types::{U256},
+    types::{H256, U256},
 };
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+use common::{spawn_mock_node, spawn_mock_node_with_errors};
 
 #[test]
 fn test_wei_to_eth_conversion() {
@@ -39,21 +45,6 @@ fn test_wei_to_gwei_conversion() {
     assert_eq!(result, 0.0);
 }
 
-#[test]
-fn test_is_known_mev_bot() {
-    let client_mock = MockRpcClient::new();
-
-    // Known MEV bot
-    assert!(client_mock.is_known_mev_bot("0x0000000000007f150bd6f54c40a34d7c3d5e9f56"));
-    assert!(client_mock.is_known_mev_bot("0xa57bd00134b2850b2a1c55860c9e9ea100fdd6cf"));
-
-    // Unknown address
-    assert!(!client_mock.is_known_mev_bot("0x1234567890abcdef1234567890abcdef12345678"));
-
-    // Case insensitive
-    assert!(client_mock.is_known_mev_bot("0X0000000000007F150BD6F54C40A34D7C3D5E9F56"));
-}
-
 #[test]
 fn test_large_wei_amounts() {
     // Test with very large amounts
@@ -62,20 +53,454 @@ fn test_large_wei_amounts() {
     assert!((result - 1000.0).abs() < 0.001);
 }
 
-// Mock client for testing MEV bot detection
-struct MockRpcClient;
+/// Mock node at head `head` whose block hashes are `hash_byte` repeated
+async fn spawn_chain(head: u64, hash_byte: u8) -> String {
+    spawn_mock_node(move |method, params| match method {
+        "eth_blockNumber" => json!(format!("0x{:x}", head)),
+        "eth_getBlockByNumber" => {
+            let number = u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+            if number > head {
+                return Value::Null;
+            }
+            json!({
+                "number": format!("0x{:x}", number),
+                "hash": H256::repeat_byte(hash_byte),
+                "timestamp": "0x0",
+            })
+        }
+        _ => Value::Null,
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_quorum_reports_hash_disagreement() {
+    let primary = spawn_chain(100, 0xaa).await;
+    let agreeing = spawn_chain(100, 0xaa).await;
+    let forked = spawn_chain(100, 0xbb).await;
+
+    let config = RpcConfig {
+        quorum: true,
+        ..RpcConfig::default()
+    };
+    let client = EthereumRpcClient::with_config(&[primary, agreeing, forked.clone()], config)
+        .await
+        .unwrap();
+
+    let disagreements = client.cross_check(&[(42, H256::repeat_byte(0xaa))]).await;
+    let found = &disagreements[&42];
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].endpoint, forked.trim_start_matches("http://"));
+    assert_eq!(found[0].reported_hash, format!("{:?}", H256::repeat_byte(0xbb)));
+}
+
+#[tokio::test]
+async fn test_quorum_ignores_lagging_endpoint() {
+    let primary = spawn_chain(100, 0xaa).await;
+    let lagging = spawn_chain(90, 0xaa).await;
+
+    let client = EthereumRpcClient::with_config(&[primary, lagging], RpcConfig::default())
+        .await
+        .unwrap();
+
+    // Block 95 doesn't exist yet on the lagging node: not a disagreement
+    let disagreements = client.cross_check(&[(95, H256::repeat_byte(0xaa))]).await;
+    assert!(disagreements.is_empty());
+}
+
+#[tokio::test]
+async fn test_switches_away_from_lagging_endpoint() {
+    let lagging = spawn_chain(90, 0xaa).await;
+    let synced = spawn_chain(100, 0xaa).await;
+
+    let client = EthereumRpcClient::with_config(&[lagging, synced.clone()], RpcConfig::default())
+        .await
+        .unwrap();
+
+    assert_eq!(client.get_latest_block_number().await.unwrap(), 100);
+    assert_eq!(client.active_endpoint(), synced.trim_start_matches("http://"));
+}
+
+#[tokio::test]
+async fn test_subscribe_new_heads_over_ipc() {
+    use futures::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let dir = std::env::temp_dir().join(format!("bsi-ipc-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("node.ipc");
+    let _ = std::fs::remove_file(&path);
+
+    // Answer eth_subscribe, then push two heads
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 4096];
+        let n = socket.read(&mut buf).await.unwrap();
+        let request: Value = serde_json::from_slice(&buf[..n]).unwrap();
+        assert_eq!(request["method"], "eth_subscribe");
+
+        let reply = json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x1"});
+        socket.write_all(reply.to_string().as_bytes()).await.unwrap();
+
+        // Give the client a moment to register the subscription id
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        for number in [7u64, 8] {
+            let head = json!({
+                "jsonrpc": "2.0",
+                "method": "eth_subscription",
+                "params": {
+                    "subscription": "0x1",
+                    "result": {"number": format!("0x{:x}", number), "timestamp": "0x0"},
+                },
+            });
+            socket.write_all(head.to_string().as_bytes()).await.unwrap();
+        }
+
+        // Keep the connection open until the client is done
+        let _ = socket.read(&mut buf).await;
+    });
+
+    let node = spawn_chain(8, 0xaa).await;
+    let config = RpcConfig {
+        pubsub_url: Some(path.to_string_lossy().into_owned()),
+        ..RpcConfig::default()
+    };
+    let client = EthereumRpcClient::with_config(&[node], config).await.unwrap();
+
+    let mut heads = client.subscribe_new_heads().await.unwrap().unwrap();
+    let (first, second) = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        (heads.next().await.unwrap(), heads.next().await.unwrap())
+    })
+    .await
+    .expect("no heads received");
+
+    assert_eq!(first.number.unwrap().as_u64(), 7);
+    assert_eq!(second.number.unwrap().as_u64(), 8);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_no_subscription_without_pubsub_url() {
+    let node = spawn_chain(8, 0xaa).await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
+
+    assert!(client.subscribe_new_heads().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_live_polls_when_pubsub_connect_fails() {
+    use blockstream_inspector::analyzer::{AnalysisEvent, BlockAnalyzer};
+    use std::sync::Mutex;
+
+    let node = spawn_chain(8, 0xaa).await;
+    let config = RpcConfig {
+        pubsub_url: Some("/nonexistent/bsi-node.ipc".to_string()),
+        ..RpcConfig::default()
+    };
+    let client = EthereumRpcClient::with_config(&[node], config).await.unwrap();
+    assert!(client.subscribe_new_heads().await.is_err());
+
+    let failures = Arc::new(Mutex::new(Vec::new()));
+    let seen = failures.clone();
+    let analyzer = BlockAnalyzer::with_source(client).with_progress(move |event| {
+        if let AnalysisEvent::SubscriptionFailed(err) = event {
+            seen.lock().unwrap().push(format!("{:#}", err));
+        }
+    });
+
+    analyzer.monitor_live(1).await.unwrap();
+
+    let failures = failures.lock().unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].contains("IPC socket"));
+}
+
+fn tx_hash(block: u64, index: u64) -> H256 {
+    H256::from_low_u64_be(block * 100 + index)
+}
 
-impl MockRpcClient {
-    fn new() -> Self {
-        MockRpcClient
+fn block_with_txs(number: u64) -> Value {
+    let txs: Vec<Value> = (0..2)
+        .map(|i| {
+            json!({
+                "hash": tx_hash(number, i),
+                "nonce": "0x0",
+                "from": "0x0000000000000000000000000000000000000001",
+                "value": "0x0",
+                "gas": "0x5208",
+                "gasPrice": "0x3b9aca00",
+                "input": "0x",
+                "transactionIndex": format!("0x{:x}", i),
+                "v": "0x1",
+                "r": "0x1",
+                "s": "0x1",
+            })
+        })
+        .collect();
+
+    json!({
+        "number": format!("0x{:x}", number),
+        "hash": H256::from_low_u64_be(number),
+        "timestamp": "0x0",
+        "transactions": txs,
+    })
+}
+
+/// Receipt for transaction `index` of `block`; the second transaction reverts
+fn receipt(block: u64, index: u64) -> Value {
+    json!({
+        "transactionHash": tx_hash(block, index),
+        "transactionIndex": format!("0x{:x}", index),
+        "from": "0x0000000000000000000000000000000000000001",
+        "cumulativeGasUsed": "0xa410",
+        "gasUsed": "0x5208",
+        "effectiveGasPrice": "0x3b9aca00",
+        "logs": [],
+        "logsBloom": format!("0x{}", "00".repeat(256)),
+        "status": if index == 1 { "0x0" } else { "0x1" },
+    })
+}
+
+fn block_param(params: &Value) -> u64 {
+    u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+}
+
+#[tokio::test]
+async fn test_stream_blocks_fetches_block_receipts() {
+    use futures::StreamExt;
+
+    let node = spawn_mock_node(|method, params| match method {
+        "eth_blockNumber" => json!("0x10"),
+        "eth_getBlockByNumber" => block_with_txs(block_param(params)),
+        "eth_getBlockReceipts" => {
+            // Requested by block hash, which encodes the number here
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+            let number = hash.to_low_u64_be();
+            json!([receipt(number, 0), receipt(number, 1)])
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
+
+    let fetched: Vec<_> = client.stream_blocks(1..=2, 2).collect().await;
+
+    for (number, result) in (1..=2).zip(fetched) {
+        let fetched = result.unwrap();
+        assert_eq!(fetched.receipts.len(), 2);
+        assert_eq!(fetched.receipts[0].transaction_hash, tx_hash(number, 0));
+        assert_eq!(fetched.receipts[1].status.unwrap().as_u64(), 0);
+    }
+}
+
+#[tokio::test]
+async fn test_falls_back_to_transaction_receipts() {
+    let node = spawn_mock_node_with_errors(|method, params| match method {
+        "eth_blockNumber" => Ok(json!("0x10")),
+        "eth_getBlockByNumber" => Ok(block_with_txs(block_param(params))),
+        "eth_getBlockReceipts" => Err(json!({"code": -32601, "message": "method not found"})),
+        "eth_getTransactionReceipt" => {
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+            let raw = hash.to_low_u64_be();
+            Ok(receipt(raw / 100, raw % 100))
+        }
+        _ => Ok(Value::Null),
+    })
+    .await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
+
+    let block = client.get_block_by_number(5).await.unwrap().unwrap();
+    let receipts = client.get_block_receipts(&block).await.unwrap();
+
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(receipts[1].transaction_hash, tx_hash(5, 1));
+    assert_eq!(receipts[1].status.unwrap().as_u64(), 0);
+}
+
+/// Mock node at head 16 answering `eth_getBlockReceipts` with `error`;
+/// returns its URL and how often that method was called
+async fn spawn_node_without_block_receipts(error: Value) -> (String, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    let node = spawn_mock_node_with_errors(move |method, params| match method {
+        "eth_blockNumber" => Ok(json!("0x10")),
+        "eth_getBlockByNumber" => Ok(block_with_txs(block_param(params))),
+        "eth_getBlockReceipts" => {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Err(error.clone())
+        }
+        "eth_getTransactionReceipt" => {
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+            let raw = hash.to_low_u64_be();
+            // Block 7 is missing the receipt of its second transaction
+            if raw == 701 {
+                return Ok(Value::Null);
+            }
+            Ok(receipt(raw / 100, raw % 100))
+        }
+        _ => Ok(Value::Null),
+    })
+    .await;
+
+    (node, calls)
+}
+
+#[tokio::test]
+async fn test_unsupported_block_receipts_are_not_asked_again() {
+    let (node, calls) =
+        spawn_node_without_block_receipts(json!({"code": -32601, "message": "method not found"}))
+            .await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
+
+    for number in [5, 6] {
+        let block = client.get_block_by_number(number).await.unwrap().unwrap();
+        assert_eq!(client.get_block_receipts(&block).await.unwrap().len(), 2);
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_block_receipts_error_only_affects_that_call() {
+    let (node, calls) =
+        spawn_node_without_block_receipts(json!({"code": -32000, "message": "header not found"}))
+            .await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
+
+    for number in [5, 6] {
+        let block = client.get_block_by_number(number).await.unwrap().unwrap();
+        assert_eq!(client.get_block_receipts(&block).await.unwrap().len(), 2);
     }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_unsupported_message_naming_the_method_disables_it() {
+    let (node, calls) = spawn_node_without_block_receipts(json!({
+        "code": -32000,
+        "message": "the method eth_getBlockReceipts does not exist/is not available"
+    }))
+    .await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
+
+    for number in [5, 6] {
+        let block = client.get_block_by_number(number).await.unwrap().unwrap();
+        assert_eq!(client.get_block_receipts(&block).await.unwrap().len(), 2);
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_block_specific_does_not_exist_is_retried() {
+    let (node, calls) = spawn_node_without_block_receipts(json!({
+        "code": -32000,
+        "message": "header for block 0x5 does not exist"
+    }))
+    .await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
 
-    fn is_known_mev_bot(&self, address: &str) -> bool {
-        let known_bots = vec![
-            "0x0000000000007f150bd6f54c40a34d7c3d5e9f56",
-            "0xa57bd00134b2850b2a1c55860c9e9ea100fdd6cf",
-            "0x00000000003b3cc22af3ae1eac0440bcee416b40",
-        ];
-        known_bots.contains(&address.to_lowercase().as_str())
+    for number in [5, 6] {
+        let block = client.get_block_by_number(number).await.unwrap().unwrap();
+        assert_eq!(client.get_block_receipts(&block).await.unwrap().len(), 2);
     }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_missing_transaction_receipt_is_an_error() {
+    let (node, _) =
+        spawn_node_without_block_receipts(json!({"code": -32601, "message": "method not found"}))
+            .await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
+
+    let block = client.get_block_by_number(7).await.unwrap().unwrap();
+    let err = client.get_block_receipts(&block).await.unwrap_err();
+    assert!(err.to_string().contains("block 7"));
+}
+
+#[tokio::test]
+async fn test_fetches_block_traces() {
+    let node = spawn_mock_node(|method, params| match method {
+        "eth_blockNumber" => json!("0x2"),
+        "trace_block" => {
+            assert_eq!(params[0], json!("0x2"));
+            json!([{
+                "action": {
+                    "callType": "call",
+                    "from": "0x1111111111111111111111111111111111111111",
+                    "to": "0x2222222222222222222222222222222222222222",
+                    "gas": "0x5208",
+                    "input": "0x",
+                    "value": "0x0"
+                },
+                "result": {"gasUsed": "0x5208", "output": "0x"},
+                "subtraces": 0,
+                "traceAddress": [],
+                "type": "call",
+                "blockNumber": 2,
+                "blockHash": H256::from_low_u64_be(2),
+                "transactionHash": tx_hash(2, 0),
+                "transactionPosition": 0
+            }])
+        }
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthereumRpcClient::with_config(&[node], RpcConfig::default())
+        .await
+        .unwrap();
+
+    let traces = client.get_block_traces(2).await.unwrap();
+
+    assert_eq!(traces.len(), 1);
+    assert_eq!(traces[0].transaction_hash, Some(tx_hash(2, 0)));
+}
+
+#[tokio::test]
+async fn test_finalized_block_number_falls_back_to_depth() {
+    let finalized = spawn_mock_node(|method, params| match method {
+        "eth_getBlockByNumber" if params[0] == json!("finalized") => json!({
+            "number": "0x80",
+            "hash": H256::from_low_u64_be(0x80),
+            "timestamp": "0x0",
+            "transactions": [],
+        }),
+        "eth_blockNumber" => json!("0x100"),
+        _ => Value::Null,
+    })
+    .await;
+    let client = EthereumRpcClient::with_config(&[finalized], RpcConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(client.get_finalized_block_number().await.unwrap(), 0x80);
+
+    // Nodes without the tag answer with an error
+    let untagged = spawn_mock_node_with_errors(|method, _| match method {
+        "eth_blockNumber" => Ok(json!("0x100")),
+        _ => Err(json!({"code": -32602, "message": "invalid block tag"})),
+    })
+    .await;
+    let client = EthereumRpcClient::with_config(&[untagged], RpcConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(client.get_finalized_block_number().await.unwrap(), 0x100 - 64);
 }
@@ -0,0 +1,259 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use blockstream_inspector::analyzer::BlockAnalyzer;
+use blockstream_inspector::exporter::{FileSink, reorgs_path};
+use blockstream_inspector::sink::{FlushPolicy, Sink, SinkStats, SinkWriter};
+use blockstream_inspector::types::{BlockLifecycle, ReorgEvent};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod common;
+use common::fixture_source;
+
+/// Analyze blocks `start..=end` of the fixture chain, without a sink
+async fn lifecycles(start: u64, end: u64) -> Vec<BlockLifecycle> {
+    BlockAnalyzer::with_source(fixture_source(1..=end))
+        .analyze_range(start, end)
+        .await
+        .unwrap()
+}
+
+/// Sink logging every call it receives
+#[derive(Clone, Default)]
+struct RecordingSink {
+    calls: Arc<Mutex<Vec<String>>>,
+    fail: bool,
+}
+
+impl RecordingSink {
+    fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Sink for RecordingSink {
+    async fn write_blocks(&mut self, blocks: &[BlockLifecycle]) -> Result<()> {
+        if self.fail {
+            anyhow::bail!("disk full");
+        }
+        let numbers: Vec<_> = blocks.iter().map(|b| b.block_number.to_string()).collect();
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("blocks {}", numbers.join(",")));
+        Ok(())
+    }
+
+    async fn write_reorgs(&mut self, reorgs: &[ReorgEvent]) -> Result<()> {
+        for reorg in reorgs {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("reorg {}", reorg.detected_at));
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.calls.lock().unwrap().push("finish".to_string());
+        Ok(())
+    }
+}
+
+fn reorg(detected_at: u64) -> ReorgEvent {
+    ReorgEvent {
+        detected_at,
+        common_ancestor: Some(detected_at - 2),
+        depth: 1,
+        orphaned: vec![format!("0x{:x}", detected_at - 1)],
+        replacement: vec!["0xnew".to_string()],
+    }
+}
+
+/// Wait for the background writer to catch up
+async fn settle() {
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
+
+#[tokio::test]
+async fn test_each_block_is_written_as_it_arrives() {
+    let sink = RecordingSink::default();
+    let writer = SinkWriter::spawn(sink.clone(), FlushPolicy::default());
+
+    for lifecycle in lifecycles(2, 3).await {
+        writer.write_block(lifecycle).unwrap();
+    }
+    settle().await;
+    assert_eq!(sink.calls(), vec!["blocks 2", "blocks 3"]);
+
+    let stats = writer.finish().await.unwrap();
+    assert_eq!(
+        stats,
+        SinkStats {
+            blocks: 2,
+            reorgs: 0
+        }
+    );
+    assert_eq!(sink.calls().last().unwrap(), "finish");
+}
+
+#[tokio::test]
+async fn test_blocks_are_batched_until_finish() {
+    let sink = RecordingSink::default();
+    let policy = FlushPolicy {
+        max_blocks: 3,
+        interval: None,
+    };
+    let writer = SinkWriter::spawn(sink.clone(), policy);
+
+    for lifecycle in lifecycles(2, 8).await {
+        writer.write_block(lifecycle).unwrap();
+    }
+    settle().await;
+    assert_eq!(sink.calls(), vec!["blocks 2,3,4", "blocks 5,6,7"]);
+
+    writer.finish().await.unwrap();
+    assert_eq!(
+        sink.calls(),
+        vec!["blocks 2,3,4", "blocks 5,6,7", "blocks 8", "finish"]
+    );
+}
+
+#[tokio::test]
+async fn test_interval_flushes_a_partial_batch() {
+    let sink = RecordingSink::default();
+    let policy = FlushPolicy {
+        max_blocks: 100,
+        interval: Some(Duration::from_millis(50)),
+    };
+    let writer = SinkWriter::spawn(sink.clone(), policy);
+
+    for lifecycle in lifecycles(2, 3).await {
+        writer.write_block(lifecycle).unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(sink.calls(), vec!["blocks 2,3"]);
+
+    writer.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reorg_writes_the_blocks_before_it() {
+    let sink = RecordingSink::default();
+    let policy = FlushPolicy {
+        max_blocks: 100,
+        interval: None,
+    };
+    let writer = SinkWriter::spawn(sink.clone(), policy);
+
+    let mut blocks = lifecycles(2, 3).await.into_iter();
+    writer.write_block(blocks.next().unwrap()).unwrap();
+    writer.write_reorg(reorg(3)).unwrap();
+    writer.write_block(blocks.next().unwrap()).unwrap();
+
+    let stats = writer.finish().await.unwrap();
+    assert_eq!(stats.reorgs, 1);
+    assert_eq!(
+        sink.calls(),
+        vec!["blocks 2", "reorg 3", "blocks 3", "finish"]
+    );
+}
+
+#[tokio::test]
+async fn test_write_error_is_reported_on_finish() {
+    let sink = RecordingSink {
+        fail: true,
+        ..Default::default()
+    };
+    let writer = SinkWriter::spawn(sink, FlushPolicy::default());
+
+    let mut blocks = lifecycles(2, 3).await.into_iter();
+    writer.write_block(blocks.next().unwrap()).unwrap();
+    settle().await;
+
+    // Later writes fail as soon as the writer has stopped
+    assert_eq!(writer.failure(), Some("disk full"));
+    let err = writer.write_block(blocks.next().unwrap()).unwrap_err();
+    assert!(err.to_string().contains("disk full"));
+
+    let err = writer.finish().await.unwrap_err();
+    assert!(err.to_string().contains("disk full"));
+}
+
+#[tokio::test]
+async fn test_analysis_stops_once_the_sink_fails() {
+    let sink = RecordingSink {
+        fail: true,
+        ..Default::default()
+    };
+    let writer = SinkWriter::spawn(sink, FlushPolicy::default());
+    writer
+        .write_block(lifecycles(2, 2).await.remove(0))
+        .unwrap();
+    settle().await;
+
+    let analyzer = BlockAnalyzer::with_source(fixture_source(1..=5)).with_sink(writer);
+    let err = analyzer.analyze_range(3, 5).await.unwrap_err();
+    assert!(err.to_string().contains("disk full"));
+}
+
+#[tokio::test]
+async fn test_analyzer_streams_to_sink_instead_of_collecting() {
+    let sink = RecordingSink::default();
+    let analyzer = BlockAnalyzer::with_source(fixture_source(1..=5))
+        .with_sink(SinkWriter::spawn(sink.clone(), FlushPolicy::default()));
+
+    let results = analyzer.analyze_range(2, 5).await.unwrap();
+    assert!(results.is_empty());
+
+    let stats = analyzer.sink().unwrap().finish().await.unwrap();
+    assert_eq!(stats.blocks, 4);
+    assert_eq!(
+        sink.calls(),
+        vec!["blocks 2", "blocks 3", "blocks 4", "blocks 5", "finish"]
+    );
+}
+
+#[tokio::test]
+async fn test_csv_file_is_readable_while_streaming() {
+    let path = "/tmp/test_sink_stream.csv";
+    let writer = SinkWriter::spawn(FileSink::csv(path).unwrap(), FlushPolicy::default());
+
+    for lifecycle in lifecycles(2, 3).await {
+        writer.write_block(lifecycle).unwrap();
+    }
+    settle().await;
+
+    // Header and both rows are on disk before the writer finishes
+    let content = std::fs::read_to_string(path).unwrap();
+    assert_eq!(content.lines().count(), 3);
+
+    writer.write_reorg(reorg(4)).unwrap();
+    writer.finish().await.unwrap();
+
+    let reorgs = std::fs::read_to_string(reorgs_path(path)).unwrap();
+    assert_eq!(reorgs.lines().count(), 2);
+    assert!(reorgs.starts_with("detected_at,"));
+
+    std::fs::remove_file(path).ok();
+    std::fs::remove_file(reorgs_path(path)).ok();
+}
+
+#[tokio::test]
+async fn test_json_array_is_closed_on_finish() {
+    let path = "/tmp/test_sink_stream.json";
+    let writer = SinkWriter::spawn(FileSink::json(path).unwrap(), FlushPolicy::default());
+
+    for lifecycle in lifecycles(2, 4).await {
+        writer.write_block(lifecycle).unwrap();
+    }
+    writer.finish().await.unwrap();
+
+    let content = std::fs::read_to_string(path).unwrap();
+    let blocks: Vec<BlockLifecycle> = serde_json::from_str(&content).unwrap();
+    let numbers: Vec<u64> = blocks.iter().map(|b| b.block_number).collect();
+    assert_eq!(numbers, vec![2, 3, 4]);
+
+    std::fs::remove_file(path).ok();
+}
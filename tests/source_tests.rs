@@ -0,0 +1,86 @@
+use blockstream_inspector::analyzer::BlockAnalyzer;
+use blockstream_inspector::source::{BlockSource, FixtureBlock, FixtureSource, ReplaySource};
+use ethers::types::H256;
+
+mod common;
+use common::{fixture_source, temp_dir, test_block, test_receipts};
+
+#[tokio::test]
+async fn test_fixture_source_serves_blocks() {
+    let source = fixture_source([100, 101]);
+
+    assert_eq!(source.len(), 2);
+    assert_eq!(source.get_latest_block_number().await.unwrap(), 101);
+    assert_eq!(
+        source.get_block_hash(100).await.unwrap(),
+        Some(H256::from_low_u64_be(100))
+    );
+    assert!(source.get_block(102).await.unwrap().is_none());
+
+    let block = source.get_block(101).await.unwrap().unwrap();
+    assert_eq!(source.get_block_receipts(&block).await.unwrap().len(), 2);
+    assert!(
+        FixtureSource::new()
+            .get_latest_block_number()
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_analyzer_runs_against_fixture() {
+    let analyzer = BlockAnalyzer::with_source(fixture_source([99, 100, 101]));
+
+    let results = analyzer.analyze_range(100, 102).await.unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].block_number, 100);
+    assert_eq!(results[0].timing.block_time, 12.0);
+    // 2 gwei tip * 21000 gas * 2 txs
+    assert!((results[0].gas.priority_fees_eth - 0.000084).abs() < 1e-12);
+
+    let latest = analyzer.analyze_single_block("latest").await.unwrap();
+    assert_eq!(latest.lifecycle.block_number, 101);
+    assert_eq!(latest.receipts.len(), 2);
+}
+
+#[tokio::test]
+async fn test_replay_source_reads_recorded_blocks() {
+    let dir = temp_dir("replay");
+    let replay = ReplaySource::new(&dir);
+
+    for number in [100, 101] {
+        let block = test_block(number, 1);
+        let receipts = test_receipts(&block);
+        replay
+            .write(&FixtureBlock {
+                block,
+                receipts,
+                traces: Vec::new(),
+            })
+            .unwrap();
+    }
+
+    assert_eq!(replay.get_latest_block_number().await.unwrap(), 101);
+    assert!(replay.get_block(99).await.unwrap().is_none());
+
+    let analyzer = BlockAnalyzer::with_source(ReplaySource::new(&dir));
+    let results = analyzer.analyze_range(100, 101).await.unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].transactions.total_count, 1);
+    assert_eq!(
+        results[1].parent_hash,
+        format!("{:?}", H256::from_low_u64_be(100))
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_replay_source_without_recordings() {
+    let replay = ReplaySource::new(temp_dir("empty"));
+
+    assert!(replay.get_latest_block_number().await.is_err());
+    assert!(replay.get_block(1).await.unwrap().is_none());
+}
@@ -0,0 +1,161 @@
+use blockstream_inspector::exporter::Exporter;
+use blockstream_inspector::sqlite::SqliteStore;
+use blockstream_inspector::types::*;
+
+mod common;
+use common::create_test_block;
+
+fn count(store: &SqliteStore, table: &str) -> i64 {
+    store
+        .connection()
+        .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_writes_normalized_tables() {
+    let mut store = SqliteStore::in_memory().unwrap();
+    store.write_blocks(&[create_test_block(100)]).unwrap();
+
+    assert_eq!(count(&store, "blocks"), 1);
+    assert_eq!(count(&store, "transactions"), 2);
+    assert_eq!(count(&store, "mev_findings"), 2);
+    assert_eq!(count(&store, "builders"), 1);
+
+    let (kind, actor, profit): (String, String, f64) = store
+        .connection()
+        .query_row(
+            "SELECT kind, actor, estimated_profit_eth FROM mev_findings WHERE venue = 'Uniswap V2'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(kind, "sandwich");
+    assert_eq!(actor, "0xbot");
+    assert!((profit - 0.2).abs() < 1e-12);
+}
+
+#[test]
+fn test_transactions_keep_their_receipt_index() {
+    let mut store = SqliteStore::in_memory().unwrap();
+    let mut block = create_test_block(100);
+    block.transactions.gas_usage[0].tx_index = 4;
+    block.transactions.gas_usage[1].tx_index = 9;
+    store.write_blocks(&[block]).unwrap();
+
+    let indexes: Vec<(u64, String)> = store
+        .connection()
+        .prepare("SELECT tx_index, tx_hash FROM transactions ORDER BY tx_index")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        indexes,
+        vec![(4, "0xt1".to_string()), (9, "0xt2".to_string())]
+    );
+}
+
+#[test]
+fn test_rewriting_a_block_replaces_its_rows() {
+    let mut store = SqliteStore::in_memory().unwrap();
+    store.write_blocks(&[create_test_block(100)]).unwrap();
+
+    let mut updated = create_test_block(100);
+    updated.mev.sandwich_attacks.clear();
+    updated.timing.received_at = None;
+    store.write_blocks(&[updated]).unwrap();
+
+    assert_eq!(count(&store, "blocks"), 1);
+    assert_eq!(count(&store, "transactions"), 2);
+    assert_eq!(count(&store, "mev_findings"), 1);
+}
+
+#[test]
+fn test_range_run_keeps_live_arrival_times() {
+    let mut store = SqliteStore::in_memory().unwrap();
+    let mut live = create_test_block(100);
+    live.timing.received_at = Some(1698765433.5);
+    store.write_blocks(&[live]).unwrap();
+
+    // A later range run doesn't know when the block arrived
+    store.write_blocks(&[create_test_block(100)]).unwrap();
+
+    let received_at: Option<f64> = store
+        .connection()
+        .query_row("SELECT received_at FROM blocks", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(received_at, Some(1698765433.5));
+}
+
+#[test]
+fn test_rewritten_block_is_canonical_again() {
+    let mut store = SqliteStore::in_memory().unwrap();
+    store.write_blocks(&[create_test_block(100)]).unwrap();
+    let reorg = ReorgEvent {
+        detected_at: 101,
+        common_ancestor: Some(99),
+        depth: 1,
+        orphaned: vec![create_test_block(100).block_hash],
+        replacement: vec!["0xnew".to_string()],
+    };
+    store.write_reorgs(&[reorg]).unwrap();
+
+    // The chain flips back to the original block
+    store.write_blocks(&[create_test_block(100)]).unwrap();
+
+    let orphaned: bool = store
+        .connection()
+        .query_row("SELECT orphaned FROM blocks", [], |row| row.get(0))
+        .unwrap();
+    assert!(!orphaned);
+}
+
+#[test]
+fn test_runs_accumulate_in_one_database() {
+    let path = std::env::temp_dir().join(format!("blockstream_sqlite_{}.db", std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = std::fs::remove_file(path);
+
+    Exporter::export_to_sqlite(&[create_test_block(100), create_test_block(101)], path).unwrap();
+    Exporter::export_to_sqlite(&[create_test_block(101), create_test_block(102)], path).unwrap();
+
+    let reorg = ReorgEvent {
+        detected_at: 103,
+        common_ancestor: Some(101),
+        depth: 1,
+        orphaned: vec![create_test_block(102).block_hash],
+        replacement: vec!["0xnew".to_string()],
+    };
+    Exporter::export_reorgs_to_sqlite(std::slice::from_ref(&reorg), path).unwrap();
+    Exporter::export_reorgs_to_sqlite(&[reorg], path).unwrap();
+
+    let store = SqliteStore::open(path).unwrap();
+    assert_eq!(count(&store, "blocks"), 3);
+    assert_eq!(count(&store, "reorgs"), 1);
+
+    let orphaned: Vec<u64> = store
+        .connection()
+        .prepare("SELECT number FROM blocks WHERE orphaned = 1")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(orphaned, vec![102]);
+
+    let (first, last): (u64, u64) = store
+        .connection()
+        .query_row(
+            "SELECT first_block, last_block FROM builders WHERE name = 'flashbots'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((first, last), (100, 102));
+
+    std::fs::remove_file(path).unwrap();
+}
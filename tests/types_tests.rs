@@ -1,11 +1,25 @@
 
 use blockstream_inspector::types::*;
 
+fn liquidation(protocol: &str) -> Liquidation {
+    Liquidation {
+        tx_hash: "0xabc".to_string(),
+        protocol: protocol.to_string(),
+        liquidator: "0x456".to_string(),
+        borrower: "0x789".to_string(),
+        collateral_asset: "0xc011".to_string(),
+        collateral_amount: "1000".to_string(),
+        debt_asset: "0xdeb7".to_string(),
+        debt_repaid: "500".to_string(),
+    }
+}
+
 // Helper function to create a test block
 fn create_test_block_lifecycle() -> BlockLifecycle {
     BlockLifecycle {
         block_number: 18000000,
         block_hash: "0x1234567890abcdef".to_string(),
+        parent_hash: "0xabcdef".to_string(),
         timestamp: 1698765432,
         proposer: "0xabcdef".to_string(),
         builder: Some("flashbots".to_string()),
@@ -13,6 +27,9 @@ fn create_test_block_lifecycle() -> BlockLifecycle {
             block_time: 12.05,
             timestamp: 1698765432,
             propagation_delay: None,
+            slot: None,
+            received_at: None,
+            timestamp_delay: None,
         },
         gas: GasMetrics {
             gas_used: 29834521,
@@ -20,8 +37,12 @@ fn create_test_block_lifecycle() -> BlockLifecycle {
             utilization: 99.45,
             base_fee_gwei: 25.34,
             avg_priority_fee_gwei: 1.52,
+            median_priority_fee_gwei: 1.2,
+            priority_fee_percentiles: FeePercentiles::default(),
             fees_burned_eth: 0.7563,
             priority_fees_eth: 0.0453,
+            fees_burned_wei: Some("756300000000000000".to_string()),
+            priority_fees_wei: Some("45300000000000000".to_string()),
         },
         transactions: TransactionMetrics {
             total_count: 247,
@@ -37,20 +58,30 @@ fn create_test_block_lifecycle() -> BlockLifecycle {
                 avg_deviation: 0.5,
             },
             failed_count: 3,
+            gas_usage: vec![],
         },
         mev: MevIndicators {
             sandwich_attacks: vec![],
             arbitrage_ops: vec![],
-            liquidations: 2,
+            liquidations: vec![liquidation("Aave V3"), liquidation("Maker")],
             estimated_mev_eth: 2.3451,
             mev_bot_addresses: vec!["0x123".to_string()],
+            other_findings: vec![],
         },
         pbs: PbsMetrics {
             is_pbs_block: true,
             builder_address: Some("flashbots".to_string()),
+            builder_match: None,
             builder_payment_eth: Some(0.05),
             extra_data: "flashbots".to_string(),
+            payment_tx: None,
+            proposer_fee_recipient: None,
+            builder_margin_eth: None,
+            builder_payment_wei: Some("50000000000000000".to_string()),
+            builder_margin_wei: None,
         },
+        provider_disagreements: vec![],
+        labels: vec![],
     }
 }
 
@@ -94,7 +125,8 @@ fn test_pbs_metrics() {
 #[test]
 fn test_mev_indicators() {
     let block = create_test_block_lifecycle();
-    assert_eq!(block.mev.liquidations, 2);
+    assert_eq!(block.mev.liquidations.len(), 2);
+    assert_eq!(block.mev.liquidations[1].protocol, "Maker");
     assert!(block.mev.estimated_mev_eth > 2.0);
     assert_eq!(block.mev.mev_bot_addresses.len(), 1);
 }
@@ -115,6 +147,9 @@ fn test_timing_metrics() {
         block_time: 12.5,
         timestamp: 1698765432,
         propagation_delay: Some(0.3),
+        slot: None,
+        received_at: None,
+        timestamp_delay: None,
     };
 
     assert_eq!(timing.block_time, 12.5);
@@ -122,6 +157,35 @@ fn test_timing_metrics() {
     assert_eq!(timing.propagation_delay.unwrap(), 0.3);
 }
 
+#[test]
+fn test_record_arrival() {
+    // Slot 7_000_000 starts at genesis + 84_000_000s; this block is 5s late
+    let slot_start = BEACON_GENESIS_TIME + 7_000_000 * SECONDS_PER_SLOT;
+    let mut timing = TimingMetrics {
+        block_time: 12.0,
+        timestamp: slot_start + 5,
+        propagation_delay: None,
+        slot: None,
+        received_at: None,
+        timestamp_delay: None,
+    };
+
+    timing.record_arrival(slot_start as f64 + 6.25);
+
+    assert_eq!(timing.slot, Some(7_000_000));
+    assert_eq!(timing.received_at, Some(slot_start as f64 + 6.25));
+    assert_eq!(timing.propagation_delay, Some(6.25));
+    assert_eq!(timing.timestamp_delay, Some(1.25));
+}
+
+#[test]
+fn test_slot_at() {
+    assert_eq!(slot_at(BEACON_GENESIS_TIME - 1), None);
+    assert_eq!(slot_at(BEACON_GENESIS_TIME), Some(0));
+    assert_eq!(slot_at(BEACON_GENESIS_TIME + 11), Some(0));
+    assert_eq!(slot_at(BEACON_GENESIS_TIME + 12), Some(1));
+}
+
 #[test]
 fn test_sandwich_attack() {
     let sandwich = SandwichAttack {
@@ -130,6 +194,8 @@ fn test_sandwich_attack() {
         backrun_tx: "0xccc".to_string(),
         estimated_profit_eth: 0.5,
         dex: "Uniswap".to_string(),
+        pool: "0xddd".to_string(),
+        attacker: "0xeee".to_string(),
     };
 
     assert_eq!(sandwich.frontrun_tx, "0xaaa");
@@ -176,3 +242,25 @@ fn test_display_formatting() {
     assert!(display_str.contains("MEV INDICATORS"));
     assert!(display_str.contains("PBS METRICS"));
 }
+
+#[test]
+fn test_percentile_interpolates() {
+    let sorted = [1.0, 2.0, 3.0, 4.0];
+
+    assert_eq!(percentile(&sorted, 0.0), 1.0);
+    assert_eq!(percentile(&sorted, 50.0), 2.5);
+    assert_eq!(percentile(&sorted, 100.0), 4.0);
+    assert_eq!(percentile(&[], 50.0), 0.0);
+    assert_eq!(percentile(&[7.0], 90.0), 7.0);
+}
+
+#[test]
+fn test_fee_percentiles() {
+    let tips: Vec<f64> = (0..=10).map(f64::from).collect();
+    let percentiles = FeePercentiles::from_sorted(&tips);
+
+    assert_eq!(percentiles.p10, 1.0);
+    assert_eq!(percentiles.p25, 2.5);
+    assert_eq!(percentiles.p75, 7.5);
+    assert_eq!(percentiles.p90, 9.0);
+}